@@ -0,0 +1,139 @@
+//! Count the reads made while iterating over entries.
+//!
+//! This reads the first 100k entries of the system journal, like the `main.rs` loop, through a
+//! wrapper which counts the reads and seeks that reach the file. Each entry takes two positioned
+//! reads (its object header, then the rest of the object in one span), so there should be about
+//! two seeks per entry, plus those for the entry arrays.
+//!
+//! ```plain
+//! cargo run --release --example entry_reads [journal directory]
+//! ```
+
+use std::{
+	io,
+	path::{Path, PathBuf},
+	pin::Pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	task::{Context, Poll},
+	time::Instant,
+};
+
+use futures_io::{AsyncRead, AsyncSeek};
+use futures_util::{Stream, StreamExt as _};
+use journald_format::{
+	impls::JournalOnDisk,
+	reader::{AsyncFileRead, FilenameInfo, JournalReader, JournalSelection, Seek},
+};
+
+const ENTRIES: usize = 100_000;
+
+/// Counts the reads and seeks made through the inner handle.
+struct CountReads<T> {
+	inner: T,
+	reads: Arc<AtomicUsize>,
+	seeks: Arc<AtomicUsize>,
+}
+
+impl<T: AsyncFileRead + Send> AsyncFileRead for CountReads<T> {
+	fn open(
+		&mut self,
+		filename: &Path,
+	) -> impl std::future::Future<Output = io::Result<()>> + Send {
+		self.inner.open(filename)
+	}
+
+	fn close(&mut self) -> impl std::future::Future<Output = ()> + Send {
+		self.inner.close()
+	}
+
+	fn current(&self) -> Option<&Path> {
+		self.inner.current()
+	}
+
+	fn read_floor(&self) -> u64 {
+		self.inner.read_floor()
+	}
+
+	fn set_read_floor(&mut self, floor: u64) {
+		self.inner.set_read_floor(floor);
+	}
+
+	fn read_ceiling(&self) -> u64 {
+		self.inner.read_ceiling()
+	}
+
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		self.inner.set_read_ceiling(ceiling);
+	}
+
+	fn list_files(
+		&self,
+		prefix: Option<&Path>,
+	) -> impl Stream<Item = io::Result<FilenameInfo>> + Unpin {
+		self.inner.list_files(prefix)
+	}
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for CountReads<T> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		self.reads.fetch_add(1, Ordering::Relaxed);
+		Pin::new(&mut self.inner).poll_read(cx, buf)
+	}
+}
+
+impl<T: AsyncSeek + Unpin> AsyncSeek for CountReads<T> {
+	fn poll_seek(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		pos: io::SeekFrom,
+	) -> Poll<io::Result<u64>> {
+		self.seeks.fetch_add(1, Ordering::Relaxed);
+		Pin::new(&mut self.inner).poll_seek(cx, pos)
+	}
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+	let root = std::env::args()
+		.nth(1)
+		.map_or_else(|| PathBuf::from("/var/log/journal"), PathBuf::from);
+	let reads = Arc::new(AtomicUsize::new(0));
+	let seeks = Arc::new(AtomicUsize::new(0));
+	let mut reader = JournalReader::new(CountReads {
+		inner: JournalOnDisk::new(root),
+		reads: reads.clone(),
+		seeks: seeks.clone(),
+	});
+	reader
+		.select(JournalSelection::current_machine("system")?)
+		.await?;
+	reader.seek(Seek::Oldest).await?;
+	let (seeks_before, reads_before) =
+		(seeks.load(Ordering::Relaxed), reads.load(Ordering::Relaxed));
+
+	let (mut entries, mut objects) = (0, 0);
+	let started = Instant::now();
+	let mut stream = reader.entries().take(ENTRIES);
+	while let Some(entry) = stream.next().await {
+		entries += 1;
+		objects += entry?.objects.len();
+	}
+	let elapsed = started.elapsed();
+
+	let seeks = seeks.load(Ordering::Relaxed) - seeks_before;
+	let reads = reads.load(Ordering::Relaxed) - reads_before;
+	println!("{entries} entries ({objects} objects) in {elapsed:?}");
+	println!(
+		"{seeks} seeks, {reads} reads ({:.2} seeks and {:.2} reads per entry)",
+		seeks as f64 / entries.max(1) as f64,
+		reads as f64 / entries.max(1) as f64,
+	);
+	Ok(())
+}
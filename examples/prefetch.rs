@@ -0,0 +1,63 @@
+//! Compare reading entries with and without read-ahead.
+//!
+//! This reads the first 100k entries of the system journal twice from the oldest, first with
+//! [`JournalReader::entries`], then with [`JournalReader::prefetched_entries`], and prints how long
+//! each took. Files are read with tokio's file IO, which runs reads on a thread pool, so reads
+//! started ahead of the consumer carry on while it works.
+//!
+//! ```plain
+//! cargo run --release --example prefetch [journal directory]
+//! ```
+
+use std::{io, path::PathBuf, time::Instant};
+
+use futures_util::{Stream, StreamExt as _};
+use journald_format::{
+	impls::JournalOnDisk,
+	objects::Entry,
+	reader::{JournalReader, JournalSelection, Seek},
+};
+
+const ENTRIES: usize = 100_000;
+
+/// Count the entries and objects of a stream.
+async fn count(
+	entries: impl Stream<Item = io::Result<Entry>> + Unpin,
+) -> io::Result<(usize, usize)> {
+	let (mut n, mut objects) = (0, 0);
+	let mut entries = entries.take(ENTRIES);
+	while let Some(entry) = entries.next().await {
+		n += 1;
+		objects += entry?.objects.len();
+	}
+	Ok((n, objects))
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+	let root = std::env::args()
+		.nth(1)
+		.map_or_else(|| PathBuf::from("/var/log/journal"), PathBuf::from);
+	let mut reader = JournalReader::new(JournalOnDisk::new(root));
+	reader
+		.select(JournalSelection::current_machine("system")?)
+		.await?;
+
+	reader.seek(Seek::Oldest).await?;
+	let started = Instant::now();
+	let (entries, objects) = count(reader.entries()).await?;
+	println!(
+		"entries:            {entries} entries ({objects} objects) in {:?}",
+		started.elapsed()
+	);
+
+	reader.seek(Seek::Oldest).await?;
+	let started = Instant::now();
+	let (entries, objects) = count(reader.prefetched_entries()).await?;
+	println!(
+		"prefetched_entries: {entries} entries ({objects} objects) in {:?}",
+		started.elapsed()
+	);
+
+	Ok(())
+}
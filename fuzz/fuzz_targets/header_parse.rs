@@ -0,0 +1,17 @@
+#![no_main]
+
+use journald_format::{header::Header, objects::ObjectHeader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(header) = Header::parse(data) {
+		// the header's own offsets, as a reader would follow them
+		let _ = header.arena_end();
+		if let Some(object) = usize::try_from(header.header_size.get())
+			.ok()
+			.and_then(|offset| data.get(offset..))
+		{
+			let _ = ObjectHeader::parse(object);
+		}
+	}
+});
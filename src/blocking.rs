@@ -0,0 +1,148 @@
+//! A blocking facade over [`JournalReader`], for programs which aren't async.
+//!
+//! [`BlockingJournalReader`] owns a current-thread Tokio runtime and drives the async reader on it,
+//! so it has the same semantics as [`JournalReader`], just without `.await`. It must not be used
+//! from within an async context, as blocking on a runtime from inside another one panics.
+
+use std::{collections::HashSet, io::Result};
+
+use futures_util::StreamExt as _;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+	objects::{Data, Entry},
+	reader::{AsyncFileRead, JournalReader, JournalSelection, ReadOptions, Seek},
+};
+
+pub struct BlockingJournalReader<T> {
+	runtime: Runtime,
+	reader: JournalReader<T>,
+}
+
+impl<T> std::fmt::Debug for BlockingJournalReader<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("BlockingJournalReader")
+			.field("reader", &self.reader)
+			.finish()
+	}
+}
+
+impl<T> BlockingJournalReader<T>
+where
+	T: AsyncFileRead,
+{
+	/// Initialize a new blocking journal reader.
+	pub fn new(io: T) -> Result<Self> {
+		Self::from_reader(JournalReader::new(io))
+	}
+
+	/// Initialize a new blocking journal reader with custom options.
+	pub fn with_options(io: T, options: ReadOptions) -> Result<Self> {
+		Self::from_reader(JournalReader::with_options(io, options))
+	}
+
+	/// Wrap an existing reader.
+	pub fn from_reader(reader: JournalReader<T>) -> Result<Self> {
+		let runtime = Builder::new_current_thread().enable_all().build()?;
+		Ok(Self { runtime, reader })
+	}
+
+	/// The wrapped reader, e.g. to read its [`position`](JournalReader::position).
+	pub fn reader(&self) -> &JournalReader<T> {
+		&self.reader
+	}
+
+	/// Unwrap the reader, dropping the runtime.
+	pub fn into_inner(self) -> JournalReader<T> {
+		self.reader
+	}
+
+	/// Run any async method of the reader to completion.
+	///
+	/// This is an escape hatch for the methods which aren't wrapped here.
+	pub fn block_on<'r, F, O>(&'r mut self, f: impl FnOnce(&'r mut JournalReader<T>) -> F) -> O
+	where
+		F: std::future::Future<Output = O>,
+	{
+		self.runtime.block_on(f(&mut self.reader))
+	}
+
+	/// List all available journals (machine ID, scope).
+	///
+	/// See [`JournalReader::list`].
+	pub fn list(&self) -> Result<HashSet<JournalSelection>> {
+		self.runtime.block_on(self.reader.list())
+	}
+
+	/// Select a journal to read.
+	///
+	/// See [`JournalReader::select`].
+	pub fn select(&mut self, journal: JournalSelection) -> Result<()> {
+		self.runtime.block_on(self.reader.select(journal))
+	}
+
+	/// Move the position of the reader.
+	///
+	/// See [`JournalReader::seek`].
+	pub fn seek(&mut self, seek: Seek) -> Result<()> {
+		self.runtime.block_on(self.reader.seek(seek))
+	}
+
+	/// Iterate over entries from the current position.
+	///
+	/// See [`JournalReader::entries`]: the position is moved past an entry before it's yielded, so
+	/// the iterator can be dropped at any point and a new one will resume with the next entry.
+	pub fn entries(&mut self) -> impl Iterator<Item = Result<Entry>> + '_ {
+		let Self { runtime, reader } = self;
+		let mut entries = reader.entries();
+		std::iter::from_fn(move || runtime.block_on(entries.next()))
+	}
+
+	/// Iterate over the data of an entry.
+	///
+	/// See [`JournalReader::entry_data`].
+	pub fn entry_data<'e>(
+		&'e mut self,
+		entry: &'e Entry,
+	) -> impl Iterator<Item = Result<Data>> + 'e {
+		let Self { runtime, reader } = self;
+		let mut data = reader.entry_data(entry);
+		std::iter::from_fn(move || runtime.block_on(data.next()))
+	}
+}
+
+#[test]
+fn test_blocking_reader() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let journal = (1..=3).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000).with_field(format!("N={n}")))
+	});
+	let mut reader = BlockingJournalReader::new(
+		MemoryFiles::default().with_file(&journal.latest_name(), journal.build()),
+	)
+	.unwrap();
+
+	let selection = JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	assert_eq!(reader.list().unwrap(), HashSet::from([selection.clone()]));
+	reader.select(selection).unwrap();
+	reader.seek(Seek::Oldest).unwrap();
+
+	let first = reader.entries().next().unwrap().unwrap();
+	assert_eq!(first.header.seqnum.get(), 1);
+	let data = reader
+		.entry_data(&first)
+		.map(|datum| datum.unwrap().value)
+		.collect::<Vec<_>>();
+	assert_eq!(data, vec!["entry 1", "1"]);
+
+	// resumes after the first entry
+	let rest = reader
+		.entries()
+		.map(|entry| entry.unwrap().header.seqnum.get())
+		.collect::<Vec<_>>();
+	assert_eq!(rest, vec![2, 3]);
+}
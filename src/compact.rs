@@ -0,0 +1,164 @@
+//! Compaction of journals, by rewriting them with only the objects their entries use.
+//!
+//! Journal files only ever grow while they're written, and can end up with objects nothing refers
+//! to, e.g. from a writer that crashed part-way through an entry. Rewriting the entries into a new
+//! file leaves those behind, and packs the rest tightly.
+
+use std::io::{Error, ErrorKind, Result};
+
+use futures_util::StreamExt as _;
+
+use crate::{
+	reader::{AsyncFileRead, JournalReader, Seek},
+	writer::{AsyncFileWrite, EntryMetadata, JournalWriter},
+};
+
+/// Copy all entries of the selected journal to a writer.
+///
+/// The entries are read from the oldest, and each is written with its fields and the realtime,
+/// monotonic time, boot ID, and sequence number of the original, so the new journal has the same
+/// entries (up to the order of the fields within each entry). Data and Field objects are interned
+/// afresh in the new file, so only the ones the entries refer to are kept. The new file is laid
+/// out by the writer's [`CreateOptions`](crate::writer::CreateOptions), e.g. for its compression
+/// and hash table sizes: pin its [`seqnum_id`](crate::writer::CreateOptions::with_seqnum_id) to
+/// the original's for cursors into the journal to stay valid.
+///
+/// Entries are read as with [`JournalReader::entries`], so the reader's priority filter applies,
+/// and Data objects as with [`JournalReader::entry_data`], so unreadable ones are skipped if the
+/// reader is [`tolerant`](crate::reader::ReadOptions::tolerant). The writer is left open.
+///
+/// Returns the number of entries written.
+#[tracing::instrument(level = "debug", skip(reader, writer))]
+pub async fn compact_journal<R: AsyncFileRead + Unpin, W: AsyncFileWrite>(
+	reader: &mut JournalReader<R>,
+	writer: &mut JournalWriter<W>,
+) -> Result<u64> {
+	reader.seek(Seek::Oldest).await?;
+
+	let mut n_entries = 0;
+	loop {
+		// a new entries() stream resumes where the last one left off
+		let Some(entry) = reader.entries().next().await else {
+			break;
+		};
+		let entry = entry?;
+
+		let mut fields = Vec::with_capacity(entry.objects.len());
+		let mut data = reader.entry_data(&entry);
+		while let Some(datum) = data.next().await {
+			let datum = datum?;
+			let key = String::from_utf8(datum.key.into()).map_err(|err| {
+				Error::new(
+					ErrorKind::InvalidData,
+					format!(
+						"field name in entry {} is not UTF-8: {err}",
+						entry.header.seqnum
+					),
+				)
+			})?;
+			fields.push((key, datum.value));
+		}
+
+		let metadata = EntryMetadata::default()
+			.with_realtime(entry.header.realtime)
+			.with_monotonic(entry.header.monotonic)
+			.with_boot_id(entry.header.boot_id)
+			.with_seqnum(entry.header.seqnum);
+		writer
+			.write_entry_with(metadata, fields.into_iter())
+			.await?;
+		n_entries += 1;
+	}
+
+	Ok(n_entries)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_compact_journal() {
+	use std::collections::BTreeSet;
+
+	use futures_util::TryStreamExt as _;
+
+	use crate::{
+		reader::{FilenameInfo, JournalSelection},
+		testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID},
+		writer::CreateOptions,
+	};
+
+	let garbage = b"nothing refers to this object";
+	let journal = JournalBuilder::new()
+		.custom_object(200, garbage)
+		.entry(
+			TestEntry::new(3, 1000)
+				.with_boot(0x1111, 100)
+				.with_field("PRIORITY=6"),
+		)
+		.entry(
+			TestEntry::new(5, 2000)
+				.with_boot(0x1111, 200)
+				.with_field(&b"BINARY=\xff\x00"[..]),
+		)
+		.entry(
+			TestEntry::new(6, 3000)
+				.with_boot(0x2222, 50)
+				.with_field("PRIORITY=6"),
+		);
+	let latest = journal.latest_name();
+	let selection = JournalSelection::from(latest.clone());
+
+	let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, journal.build()));
+	reader.select(selection.clone()).await.unwrap();
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, 0x9999, "system"),
+	);
+	assert_eq!(compact_journal(&mut reader, &mut writer).await.unwrap(), 3);
+	writer.close().await.unwrap();
+
+	// (seqnum, realtime, monotonic, boot ID, fields) of each entry
+	async fn read_all<R: AsyncFileRead + Unpin>(
+		reader: &mut JournalReader<R>,
+	) -> Vec<(u64, i64, u64, u128, BTreeSet<Vec<u8>>)> {
+		reader.seek(Seek::Oldest).await.unwrap();
+		let mut all = Vec::new();
+		loop {
+			let Some(entry) = reader.entries().next().await else {
+				break;
+			};
+			let entry = entry.unwrap();
+			let fields = reader
+				.entry_data(&entry)
+				.map_ok(|data| [&data.key[..], b"=", &data.value[..]].concat())
+				.try_collect()
+				.await
+				.unwrap();
+			all.push((
+				entry.seqnum().get(),
+				entry.realtime().as_microsecond(),
+				entry.header.monotonic.0.get(),
+				entry.boot_id(),
+				fields,
+			));
+		}
+		all
+	}
+
+	let files = writer.io().try_clone().unwrap();
+	let compacted = files
+		.bytes(&FilenameInfo::Latest {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.unwrap();
+	let has_garbage = |bytes: &[u8]| bytes.windows(garbage.len()).any(|window| window == garbage);
+	assert!(has_garbage(&journal.build()));
+	assert!(!has_garbage(&compacted));
+
+	let mut compacted_reader = JournalReader::new(files);
+	compacted_reader.select(selection).await.unwrap();
+	let original = read_all(&mut reader).await;
+	assert_eq!(original.len(), 3);
+	assert_eq!(read_all(&mut compacted_reader).await, original);
+	assert!(compacted_reader.verify_all().await.unwrap());
+}
@@ -0,0 +1,317 @@
+#[cfg(any(feature = "xz", feature = "zstd"))]
+use std::io::Read as _;
+use std::num::NonZeroU64;
+
+use flagset::FlagSet;
+
+use crate::{header::IncompatibleFlag, objects::DataCompression};
+
+/// Maximum size of a decompressed Data payload.
+///
+/// This is the same as systemd's `DATA_SIZE_MAX`, and protects readers from objects that expand to
+/// unreasonable sizes.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 768 * 1024 * 1024;
+
+/// The compression algorithms this build supports, as the header flags of files which use them.
+///
+/// Each algorithm is behind the crate feature of the same name (`xz`, `lz4`, and `zstd`). Journal
+/// files which use an algorithm that isn't supported can't be read, as any of their Data objects
+/// may be compressed with it.
+pub fn supported_compression() -> FlagSet<IncompatibleFlag> {
+	#[allow(unused_mut)]
+	let mut supported = FlagSet::default();
+	#[cfg(feature = "xz")]
+	{
+		supported |= IncompatibleFlag::CompressedXz;
+	}
+	#[cfg(feature = "lz4")]
+	{
+		supported |= IncompatibleFlag::CompressedLz4;
+	}
+	#[cfg(feature = "zstd")]
+	{
+		supported |= IncompatibleFlag::CompressedZstd;
+	}
+	supported
+}
+
+/// Check that the compression a file's flags say it uses is among the `supported` algorithms.
+///
+/// Errors with `Unsupported`, naming the crate features that are missing.
+pub(crate) fn check_supported(
+	flags: FlagSet<IncompatibleFlag>,
+	supported: FlagSet<IncompatibleFlag>,
+) -> std::io::Result<()> {
+	let missing = [
+		(IncompatibleFlag::CompressedXz, "xz"),
+		(IncompatibleFlag::CompressedLz4, "lz4"),
+		(IncompatibleFlag::CompressedZstd, "zstd"),
+	]
+	.into_iter()
+	.filter(|(flag, _)| flags.contains(*flag) && !supported.contains(*flag))
+	.map(|(_, feature)| feature)
+	.collect::<Vec<_>>();
+
+	if missing.is_empty() {
+		return Ok(());
+	}
+
+	let missing = missing.join(", ");
+	Err(std::io::Error::new(
+		std::io::ErrorKind::Unsupported,
+		format!(
+			"journal file uses {missing} compression, which this build doesn't support (missing crate features: {missing})"
+		),
+	))
+}
+
+/// Decompress a Data object payload.
+///
+/// Errors with `InvalidData` if the output would be larger than `max` bytes, or than `max_ratio`
+/// times the size of the compressed input, and with `Unsupported` if the compression algorithm was
+/// not enabled at build time. The limits are enforced while decompressing, so the output is never
+/// allocated past them.
+#[tracing::instrument(level = "trace", skip(data))]
+pub(crate) fn decompress(
+	compression: &DataCompression,
+	data: &[u8],
+	max: u64,
+	max_ratio: Option<NonZeroU64>,
+) -> std::io::Result<Vec<u8>> {
+	#[allow(unused_variables)]
+	let limit = Limit::new(data.len() as _, max, max_ratio);
+	match compression {
+		DataCompression::None => Ok(data.to_vec()),
+
+		#[cfg(feature = "xz")]
+		DataCompression::Xz => read_capped(xz2::read::XzDecoder::new(data), limit),
+
+		// systemd prefixes the LZ4 block with the decompressed size
+		#[cfg(feature = "lz4")]
+		DataCompression::Lz4 => {
+			let (size, block) = data.split_first_chunk::<8>().ok_or_else(|| {
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					"lz4 payload too short for size prefix",
+				)
+			})?;
+			let size = u64::from_le_bytes(*size);
+			limit.check(size)?;
+			lz4_flex::block::decompress(block, size as _)
+				.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+		}
+
+		#[cfg(feature = "zstd")]
+		DataCompression::Zstd => read_capped(zstd::stream::read::Decoder::new(data)?, limit),
+
+		#[allow(unreachable_patterns)]
+		other => Err(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			format!("decompressing {other:?} is not supported by this build"),
+		)),
+	}
+}
+
+/// Compress a Data object payload.
+///
+/// The output is in the format systemd uses, so it can be read back with [`decompress`].
+///
+/// Errors with `Unsupported` if the compression algorithm was not enabled at build time.
+#[tracing::instrument(level = "trace", skip(data))]
+pub(crate) fn compress(compression: &DataCompression, data: &[u8]) -> std::io::Result<Vec<u8>> {
+	match compression {
+		DataCompression::None => Ok(data.to_vec()),
+
+		#[cfg(feature = "xz")]
+		DataCompression::Xz => {
+			let stream = xz2::stream::Stream::new_easy_encoder(6, xz2::stream::Check::None)
+				.map_err(std::io::Error::from)?;
+			let mut out = Vec::new();
+			xz2::read::XzEncoder::new_stream(data, stream).read_to_end(&mut out)?;
+			Ok(out)
+		}
+
+		// systemd prefixes the LZ4 block with the decompressed size
+		#[cfg(feature = "lz4")]
+		DataCompression::Lz4 => {
+			let mut out = (data.len() as u64).to_le_bytes().to_vec();
+			out.extend(lz4_flex::block::compress(data));
+			Ok(out)
+		}
+
+		#[cfg(feature = "zstd")]
+		DataCompression::Zstd => zstd::bulk::compress(data, 0),
+
+		#[allow(unreachable_patterns)]
+		other => Err(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			format!("compressing with {other:?} is not supported by this build"),
+		)),
+	}
+}
+
+#[cfg(any(feature = "xz", feature = "zstd"))]
+fn read_capped(reader: impl std::io::Read, limit: Limit) -> std::io::Result<Vec<u8>> {
+	let mut out = Vec::new();
+	reader
+		.take(limit.size().saturating_add(1))
+		.read_to_end(&mut out)?;
+	limit.check(out.len() as _)?;
+	Ok(out)
+}
+
+/// The size limits of a decompressed payload.
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+	compressed: u64,
+	max: u64,
+	max_ratio: Option<NonZeroU64>,
+}
+
+impl Limit {
+	fn new(compressed: u64, max: u64, max_ratio: Option<NonZeroU64>) -> Self {
+		Self {
+			compressed,
+			max,
+			max_ratio,
+		}
+	}
+
+	/// The largest allowed size.
+	fn size(&self) -> u64 {
+		self.max_ratio.map_or(self.max, |ratio| {
+			self.max.min(self.compressed.saturating_mul(ratio.get()))
+		})
+	}
+
+	#[cfg_attr(
+		not(any(feature = "xz", feature = "lz4", feature = "zstd")),
+		allow(dead_code)
+	)]
+	fn check(&self, size: u64) -> std::io::Result<()> {
+		if size > self.max {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"decompressed payload is larger than the maximum of {} bytes",
+					self.max
+				),
+			))
+		} else if size > self.size() {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"decompressed payload expands more than {}x its compressed size of {} bytes",
+					// UNWRAP: size() is only smaller than max with a ratio
+					self.max_ratio.unwrap(),
+					self.compressed
+				),
+			))
+		} else {
+			Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn test_compress_roundtrip() {
+	let data = "MESSAGE=all work and no play ".repeat(50);
+	for compression in [
+		#[cfg(feature = "xz")]
+		DataCompression::Xz,
+		#[cfg(feature = "lz4")]
+		DataCompression::Lz4,
+		#[cfg(feature = "zstd")]
+		DataCompression::Zstd,
+	] {
+		let compressed = compress(&compression, data.as_bytes()).unwrap();
+		assert!(compressed.len() < data.len(), "{compression:?}");
+		assert_eq!(
+			decompress(&compression, &compressed, MAX_DECOMPRESSED_SIZE, None).unwrap(),
+			data.as_bytes(),
+			"{compression:?}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn test_decompress_ratio() {
+	// a bomb: a megabyte of zeros compresses to almost nothing
+	let data = vec![0; 1024 * 1024];
+	let ratio = NonZeroU64::new(100);
+	for compression in [
+		#[cfg(feature = "xz")]
+		DataCompression::Xz,
+		#[cfg(feature = "lz4")]
+		DataCompression::Lz4,
+		#[cfg(feature = "zstd")]
+		DataCompression::Zstd,
+	] {
+		let compressed = compress(&compression, &data).unwrap();
+		assert!(
+			(compressed.len() as u64) * 100 < data.len() as u64,
+			"{compression:?}"
+		);
+
+		let err = decompress(&compression, &compressed, MAX_DECOMPRESSED_SIZE, ratio).unwrap_err();
+		assert_eq!(
+			err.kind(),
+			std::io::ErrorKind::InvalidData,
+			"{compression:?}"
+		);
+		assert!(err.to_string().contains("expands more than 100x"), "{err}");
+
+		assert_eq!(
+			decompress(&compression, &compressed, MAX_DECOMPRESSED_SIZE, None)
+				.unwrap()
+				.len(),
+			data.len(),
+			"{compression:?}"
+		);
+	}
+
+	// ordinary text is well within the ratio
+	let text = "MESSAGE=all work and no play ".repeat(50);
+	for compression in [
+		#[cfg(feature = "lz4")]
+		DataCompression::Lz4,
+		#[cfg(feature = "zstd")]
+		DataCompression::Zstd,
+	] {
+		let compressed = compress(&compression, text.as_bytes()).unwrap();
+		assert_eq!(
+			decompress(&compression, &compressed, MAX_DECOMPRESSED_SIZE, ratio).unwrap(),
+			text.as_bytes(),
+			"{compression:?}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn test_supported_compression() {
+	let all = IncompatibleFlag::CompressedXz
+		| IncompatibleFlag::CompressedLz4
+		| IncompatibleFlag::CompressedZstd;
+	assert_eq!(
+		supported_compression().contains(IncompatibleFlag::CompressedZstd),
+		cfg!(feature = "zstd")
+	);
+	assert!((supported_compression() - all).is_empty());
+
+	let flags = IncompatibleFlag::Compact | IncompatibleFlag::CompressedZstd;
+	check_supported(flags, all).unwrap();
+	check_supported(IncompatibleFlag::Compact.into(), FlagSet::default()).unwrap();
+	let err = check_supported(
+		flags | IncompatibleFlag::CompressedXz,
+		IncompatibleFlag::CompressedLz4.into(),
+	)
+	.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+	assert_eq!(
+		err.to_string(),
+		"journal file uses xz, zstd compression, which this build doesn't support (missing crate features: xz, zstd)"
+	);
+}
@@ -21,6 +21,10 @@ pub fn reader_realtime_opt<R: no_std_io::Read + no_std_io::Seek>(
 	reader: &mut Reader<R>,
 ) -> Result<Option<Timestamp>, DekuError> {
 	let value = u64::from_reader_with_ctx(reader, Endian::Little)?;
+	if value == 0 {
+		return Ok(None);
+	}
+
 	Timestamp::from_microsecond(value.try_into()?)
 		.map_err(|err| DekuError::Assertion(format!("Invalid timestamp: {err}").into()))
 		.map(Some)
@@ -36,3 +40,13 @@ pub fn writer_realtime_opt<W: std::io::Write + std::io::Seek>(
 		.try_into()?;
 	value.to_writer(writer, Endian::Little)
 }
+
+/// Write an optional value, or zero (the default) if it's `None`.
+///
+/// Deku writes nothing for `None`, but on disk absent offsets and counters are zero.
+pub fn writer_or_zero<W: std::io::Write + std::io::Seek, T: DekuWriter<Endian> + Default>(
+	writer: &mut Writer<W>,
+	field: Option<T>,
+) -> Result<(), DekuError> {
+	field.unwrap_or_default().to_writer(writer, Endian::Little)
+}
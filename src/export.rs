@@ -0,0 +1,163 @@
+//! Encoding of fields for the textual journal formats.
+//!
+//! Field values are arbitrary bytes, so these never convert them lossily: values which can't be
+//! represented as text are written in the binary forms of the formats instead. What counts as text
+//! follows systemd: valid UTF-8 without control characters, except tabs and (where the format
+//! allows it) newlines.
+//!
+//! See the [Journal Export Formats](https://systemd.io/JOURNAL_EXPORT_FORMATS/) for reference.
+
+use std::io::{Result, Write};
+
+/// Write a field in the [Journal Export Format].
+///
+/// Text values are written as `KEY=value\n`. Other values, including any with a newline, are
+/// written as `KEY\n`, followed by the length of the value as a little-endian 64-bit integer, the
+/// value itself, and `\n`.
+///
+/// [Journal Export Format]: https://systemd.io/JOURNAL_EXPORT_FORMATS/#journal-export-format
+pub fn write_export_field(out: &mut impl Write, key: &[u8], value: &[u8]) -> Result<()> {
+	out.write_all(key)?;
+	if is_printable(value, false) {
+		out.write_all(b"=")?;
+	} else {
+		out.write_all(b"\n")?;
+		out.write_all(&(value.len() as u64).to_le_bytes())?;
+	}
+	out.write_all(value)?;
+	out.write_all(b"\n")
+}
+
+/// Write a field as a member of a [Journal JSON Format] object.
+///
+/// Text values are written as `"KEY":"value"`, escaped as needed. Other values are written as an
+/// array of byte values: `"KEY":[1,2,3]`. This doesn't write separators between members.
+///
+/// [Journal JSON Format]: https://systemd.io/JOURNAL_EXPORT_FORMATS/#journal-json-format
+pub fn write_json_field(out: &mut impl Write, key: &[u8], value: &[u8]) -> Result<()> {
+	write_json_string(out, key)?;
+	out.write_all(b":")?;
+	write_json_value(out, value)
+}
+
+/// Write all the values of a field as a member of a [Journal JSON Format] object.
+///
+/// A field with one value is written as with [`write_json_field`]. Fields which appear more than
+/// once in an entry are written with an array of their values, in order, each as a string or an
+/// array of byte values as for single values: `"KEY":["one",[1,2,3]]`. This doesn't write
+/// separators between members, and writes nothing if there are no values.
+///
+/// [Journal JSON Format]: https://systemd.io/JOURNAL_EXPORT_FORMATS/#journal-json-format
+pub fn write_json_fields(
+	out: &mut impl Write,
+	key: &[u8],
+	values: &[impl AsRef<[u8]>],
+) -> Result<()> {
+	match values {
+		[] => Ok(()),
+		[value] => write_json_field(out, key, value.as_ref()),
+		values => {
+			write_json_string(out, key)?;
+			out.write_all(b":[")?;
+			for (n, value) in values.iter().enumerate() {
+				if n > 0 {
+					out.write_all(b",")?;
+				}
+				write_json_value(out, value.as_ref())?;
+			}
+			out.write_all(b"]")
+		}
+	}
+}
+
+/// Write a value as a JSON string if it's text, or as an array of byte values otherwise.
+fn write_json_value(out: &mut impl Write, value: &[u8]) -> Result<()> {
+	if is_printable(value, true) {
+		return write_json_string(out, value);
+	}
+
+	out.write_all(b"[")?;
+	for (n, byte) in value.iter().enumerate() {
+		if n > 0 {
+			out.write_all(b",")?;
+		}
+		write!(out, "{byte}")?;
+	}
+	out.write_all(b"]")
+}
+
+/// Write bytes as a JSON string.
+///
+/// Invalid UTF-8 is replaced; callers should check with [`is_printable`] first.
+fn write_json_string(out: &mut impl Write, value: &[u8]) -> Result<()> {
+	out.write_all(b"\"")?;
+	for c in String::from_utf8_lossy(value).chars() {
+		match c {
+			'"' => out.write_all(b"\\\"")?,
+			'\\' => out.write_all(b"\\\\")?,
+			'\n' => out.write_all(b"\\n")?,
+			'\t' => out.write_all(b"\\t")?,
+			c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+			c => write!(out, "{c}")?,
+		}
+	}
+	out.write_all(b"\"")
+}
+
+/// Whether a value can be written as text.
+///
+/// This mirrors systemd's `utf8_is_printable_newline()`.
+fn is_printable(value: &[u8], newline: bool) -> bool {
+	let Ok(value) = std::str::from_utf8(value) else {
+		return false;
+	};
+
+	value.chars().all(|c| match c {
+		'\t' => true,
+		'\n' => newline,
+		c => !c.is_control(),
+	})
+}
+
+#[test]
+fn test_write_export_field() {
+	let mut out = Vec::new();
+	write_export_field(&mut out, b"MESSAGE", b"hello world").unwrap();
+	assert_eq!(out, b"MESSAGE=hello world\n");
+
+	for value in [&b"two\nlines"[..], b"nul\0byte", b"invalid \xff utf8"] {
+		let mut out = Vec::new();
+		write_export_field(&mut out, b"MESSAGE", value).unwrap();
+		let mut expected = b"MESSAGE\n".to_vec();
+		expected.extend((value.len() as u64).to_le_bytes());
+		expected.extend(value);
+		expected.push(b'\n');
+		assert_eq!(out, expected, "{value:?}");
+	}
+}
+
+#[test]
+fn test_write_json_field() {
+	let mut out = Vec::new();
+	write_json_field(&mut out, b"MESSAGE", b"say \"hi\"\n\tbye").unwrap();
+	assert_eq!(out, br#""MESSAGE":"say \"hi\"\n\tbye""#);
+
+	let mut out = Vec::new();
+	write_json_field(&mut out, b"MESSAGE", b"a\0b\nc\xff").unwrap();
+	assert_eq!(out, br#""MESSAGE":[97,0,98,10,99,255]"#);
+}
+
+#[test]
+fn test_write_json_fields() {
+	let mut out = Vec::new();
+	write_json_fields(&mut out, b"GROUP", &["wheel"]).unwrap();
+	assert_eq!(out, br#""GROUP":"wheel""#);
+
+	let mut out = Vec::new();
+	write_json_fields(&mut out, b"GROUP", &[&b"wheel"[..], b"a\0b"]).unwrap();
+	assert_eq!(out, br#""GROUP":["wheel",[97,0,98]]"#);
+
+	let mut out = Vec::new();
+	write_json_fields(&mut out, b"GROUP", &[] as &[&[u8]]).unwrap();
+	assert!(out.is_empty());
+}
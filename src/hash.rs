@@ -0,0 +1,124 @@
+use std::hash::Hasher as _;
+
+use siphasher::sip::SipHasher24;
+
+/// Hash data with Bob Jenkins' `lookup3` (`hashlittle2`), as systemd does for non-keyed files.
+///
+/// The two 32-bit halves are combined as `(c << 32) | b`, with both initial values zero.
+pub fn jenkins_hash64(data: &[u8]) -> u64 {
+	let (c, b) = hashlittle2(data, 0, 0);
+	(u64::from(c) << 32) | u64::from(b)
+}
+
+/// Hash data with SipHash-2-4 keyed with the file ID, as systemd does for files with
+/// [`IncompatibleFlag::KeyedHash`](crate::header::IncompatibleFlag::KeyedHash).
+pub fn keyed_hash64(file_id: u128, data: &[u8]) -> u64 {
+	let mut hasher = SipHasher24::new_with_key(&file_id.to_le_bytes());
+	hasher.write(data);
+	hasher.finish()
+}
+
+fn hashlittle2(data: &[u8], pc: u32, pb: u32) -> (u32, u32) {
+	let mut a = 0xdeadbeef_u32
+		.wrapping_add(data.len() as u32)
+		.wrapping_add(pc);
+	let mut b = a;
+	let mut c = a.wrapping_add(pb);
+
+	let word = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+
+	let mut rest = data;
+	while rest.len() > 12 {
+		a = a.wrapping_add(word(&rest[0..4]));
+		b = b.wrapping_add(word(&rest[4..8]));
+		c = c.wrapping_add(word(&rest[8..12]));
+		mix(&mut a, &mut b, &mut c);
+		rest = &rest[12..];
+	}
+
+	if rest.is_empty() {
+		return (c, b);
+	}
+
+	// the last block is zero-padded, which is the same as lookup3's fallthrough switch
+	let mut last = [0; 12];
+	last[..rest.len()].copy_from_slice(rest);
+	a = a.wrapping_add(word(&last[0..4]));
+	b = b.wrapping_add(word(&last[4..8]));
+	c = c.wrapping_add(word(&last[8..12]));
+	finalize(&mut a, &mut b, &mut c);
+	(c, b)
+}
+
+fn mix(a: &mut u32, b: &mut u32, c: &mut u32) {
+	*a = a.wrapping_sub(*c);
+	*a ^= c.rotate_left(4);
+	*c = c.wrapping_add(*b);
+	*b = b.wrapping_sub(*a);
+	*b ^= a.rotate_left(6);
+	*a = a.wrapping_add(*c);
+	*c = c.wrapping_sub(*b);
+	*c ^= b.rotate_left(8);
+	*b = b.wrapping_add(*a);
+	*a = a.wrapping_sub(*c);
+	*a ^= c.rotate_left(16);
+	*c = c.wrapping_add(*b);
+	*b = b.wrapping_sub(*a);
+	*b ^= a.rotate_left(19);
+	*a = a.wrapping_add(*c);
+	*c = c.wrapping_sub(*b);
+	*c ^= b.rotate_left(4);
+	*b = b.wrapping_add(*a);
+}
+
+fn finalize(a: &mut u32, b: &mut u32, c: &mut u32) {
+	*c ^= *b;
+	*c = c.wrapping_sub(b.rotate_left(14));
+	*a ^= *c;
+	*a = a.wrapping_sub(c.rotate_left(11));
+	*b ^= *a;
+	*b = b.wrapping_sub(a.rotate_left(25));
+	*c ^= *b;
+	*c = c.wrapping_sub(b.rotate_left(16));
+	*a ^= *c;
+	*a = a.wrapping_sub(c.rotate_left(4));
+	*b ^= *a;
+	*b = b.wrapping_sub(a.rotate_left(14));
+	*c ^= *b;
+	*c = c.wrapping_sub(b.rotate_left(24));
+}
+
+#[test]
+fn test_jenkins_hash64() {
+	// test vectors from lookup3.c's driver5()
+	assert_eq!(hashlittle2(b"", 0, 0), (0xdeadbeef, 0xdeadbeef));
+	assert_eq!(hashlittle2(b"", 0, 0xdeadbeef), (0xbd5b7dde, 0xdeadbeef));
+	assert_eq!(
+		hashlittle2(b"", 0xdeadbeef, 0xdeadbeef),
+		(0x9c093ccd, 0xbd5b7dde)
+	);
+	assert_eq!(
+		hashlittle2(b"Four score and seven years ago", 0, 0),
+		(0x17770551, 0xce7226e6)
+	);
+	assert_eq!(
+		hashlittle2(b"Four score and seven years ago", 0, 1),
+		(0xe3607cae, 0xbd371de4)
+	);
+	assert_eq!(
+		hashlittle2(b"Four score and seven years ago", 1, 0),
+		(0xcd628161, 0x6cbea4b3)
+	);
+
+	assert_eq!(
+		jenkins_hash64(b"Four score and seven years ago"),
+		0x17770551_ce7226e6
+	);
+}
+
+#[test]
+fn test_keyed_hash64() {
+	// reference vector from the SipHash paper: key 00..0f, empty message
+	let file_id = u128::from_le_bytes(std::array::from_fn(|n| n as u8));
+	assert_eq!(keyed_hash64(file_id, b""), 0x726fdb47dd0e0e31);
+}
@@ -1,10 +1,16 @@
+// flag values are aligned by bit position for readability
+#![allow(clippy::unusual_byte_groupings)]
+
 use std::num::{NonZeroU128, NonZeroU32, NonZeroU64};
 
 use deku::{ctx::Endian, no_std_io, prelude::*};
 use flagset::{flags, FlagSet};
+use futures_io::{AsyncSeek, AsyncWrite};
+use futures_util::io::{AsyncSeekExt as _, AsyncWriteExt as _};
 use jiff::Timestamp;
 
 use crate::{
+	hash::{jenkins_hash64, keyed_hash64},
 	monotonic::Monotonic,
 	objects::{
 		EntryArrayCompactItem, EntryArrayRegularItem, EntryObjectCompactItem,
@@ -49,6 +55,10 @@ pub struct Header {
 	/// Boot ID of the last entry in the journal file.
 	///
 	/// None if the journal is empty.
+	#[deku(
+		map = "|field: u128| -> Result<_, DekuError> { Ok(NonZeroU128::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_boot_id.map(NonZeroU128::get))"
+	)]
 	pub tail_entry_boot_id: Option<NonZeroU128>, // 16 = 72
 
 	/// The unique identifier of the sequence number domain.
@@ -99,11 +109,19 @@ pub struct Header {
 	/// The sequence number of the last entry in the journal file.
 	///
 	/// None if the journal is empty.
+	#[deku(
+		map = "|field: u64| -> Result<_, DekuError> { Ok(NonZeroU64::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_seqnum.map(NonZeroU64::get))"
+	)]
 	pub tail_entry_seqnum: Option<NonZeroU64>, // 8 = 168
 
 	/// The sequence number of the first entry in the journal file.
 	///
 	/// None if the journal is empty.
+	#[deku(
+		map = "|field: u64| -> Result<_, DekuError> { Ok(NonZeroU64::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.head_entry_seqnum.map(NonZeroU64::get))"
+	)]
 	pub head_entry_seqnum: Option<NonZeroU64>, // 8 = 176
 
 	/// The offset of the first entry array in the journal file.
@@ -125,7 +143,7 @@ pub struct Header {
 	/// None if the journal is empty.
 	#[deku(
 		reader = "crate::deku_helpers::reader_realtime_opt(deku::reader)",
-		writer = "crate::deku_helpers::writer_realtime_opt(deku::writer, &self.head_entry_realtime)"
+		writer = "crate::deku_helpers::writer_realtime_opt(deku::writer, &self.tail_entry_realtime)"
 	)]
 	pub tail_entry_realtime: Option<Timestamp>, // 8 = 200
 
@@ -134,30 +152,46 @@ pub struct Header {
 	/// None if the journal is empty.
 	///
 	/// If [`CompatibleFlag::TailEntryBootId`] is not set, this field cannot be trusted and should be ignored.
+	#[deku(
+		map = "|field: u64| -> Result<_, DekuError> { Ok(Monotonic::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_monotonic.map(|m| m.0.get()))"
+	)]
 	pub tail_entry_monotonic: Option<Monotonic>, // 8 = 208
 
 	/// The number of data objects in the journal file.
 	///
 	/// None if the journal was created before systemd 187.
-	#[deku(cond = "header_size.get() > 208")]
+	#[deku(
+		cond = "header_size.get() > 208",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.n_data)"
+	)]
 	pub n_data: Option<u64>, // 8 = 216
 
 	/// The number of field objects in the journal file.
 	///
 	/// None if the journal was created before systemd 187.
-	#[deku(cond = "header_size.get() > 216")]
+	#[deku(
+		cond = "header_size.get() > 216",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.n_fields)"
+	)]
 	pub n_fields: Option<u64>, // 8 = 224
 
 	/// The number of sealing tag objects in the journal file.
 	///
 	/// None if the journal was created before systemd 189.
-	#[deku(cond = "header_size.get() > 224")]
+	#[deku(
+		cond = "header_size.get() > 224",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.n_tags)"
+	)]
 	pub n_tags: Option<u64>, // 8 = 232
 
 	/// The number of entry arrays in the journal file.
 	///
-	/// None if the journal was created before systemd 240.
-	#[deku(cond = "header_size.get() > 232")]
+	/// None if the journal was created before systemd 189.
+	#[deku(
+		cond = "header_size.get() > 232",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.n_entry_arrays)"
+	)]
 	pub n_entry_arrays: Option<u64>, // 8 = 240
 
 	/// The depth of the longest chain of data hash objects in the journal file.
@@ -166,7 +200,10 @@ pub struct Header {
 	///
 	/// This is a measure of how often there are hash collisions in the data hash table, and is used
 	/// to determine when to rotate (when collisions are too frequent).
-	#[deku(cond = "header_size.get() > 240")]
+	#[deku(
+		cond = "header_size.get() > 240",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.data_hash_chain_depth)"
+	)]
 	pub data_hash_chain_depth: Option<u64>, // 8 = 248
 
 	/// The depth of the longest chain of field hash objects in the journal file.
@@ -175,31 +212,99 @@ pub struct Header {
 	///
 	/// This is a measure of how often there are hash collisions in the field hash table, and is
 	/// used to determine when to rotate (when collisions are too frequent).
-	#[deku(cond = "header_size.get() > 248")]
+	#[deku(
+		cond = "header_size.get() > 248",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.field_hash_chain_depth)"
+	)]
 	pub field_hash_chain_depth: Option<u64>, // 8 = 256
 
 	/// The offset of the last entry array in the journal file.
 	///
 	/// None if the journal was created before systemd 252.
-	#[deku(cond = "header_size.get() > 256")]
+	#[deku(
+		cond = "header_size.get() > 256",
+		map = "|field: Option<u32>| -> Result<_, DekuError> { Ok(field.and_then(NonZeroU32::new)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_array_offset.map(NonZeroU32::get))"
+	)]
 	pub tail_entry_array_offset: Option<NonZeroU32>, // 4 = 260
 
 	/// The number of entries in the last entry array in the journal file.
 	///
-	/// None if the journal was created before systemd 254.
-	#[deku(cond = "header_size.get() > 260")]
+	/// None if the journal was created before systemd 252.
+	#[deku(
+		cond = "header_size.get() > 260",
+		map = "|field: Option<u32>| -> Result<_, DekuError> { Ok(field.and_then(NonZeroU32::new)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_array_n_entries.map(NonZeroU32::get))"
+	)]
 	pub tail_entry_array_n_entries: Option<NonZeroU32>, // 4 = 264
 
 	/// The offset of the last entry in the journal file.
 	///
 	/// None if the journal was created before systemd 254, or if the journal is empty.
-	#[deku(cond = "header_size.get() > 264")]
+	#[deku(
+		cond = "header_size.get() > 264",
+		map = "|field: Option<u64>| -> Result<_, DekuError> { Ok(field.and_then(NonZeroU64::new)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_entry_offset.map(NonZeroU64::get))"
+	)]
 	pub tail_entry_offset: Option<NonZeroU64>, // 8 = 272
 }
 
+/// The signature at the start of every journal file.
+pub const SIGNATURE: &[u8; 8] = b"LPKSHHRH";
+
 pub const MIN_HEADER_SIZE: usize = 208;
 pub const MAX_HEADER_SIZE: usize = 272;
 
+/// Valid values of `header_size`: the end of each optional field.
+const HEADER_FIELD_ENDS: [u64; 10] = [208, 216, 224, 232, 240, 248, 256, 260, 264, 272];
+
+/// The systemd versions which added fields to the header, and the `header_size` they write.
+const HEADER_SIZE_BY_VERSION: [(u32, u64); 5] =
+	[(187, 224), (189, 240), (246, 256), (252, 264), (254, 272)];
+
+/// The `header_size` of journal files written by a version of systemd.
+///
+/// Versions older than 187 write the minimum header, and versions newer than 254 the largest this
+/// library knows about.
+pub fn header_size_for_version(systemd_version: u32) -> u64 {
+	HEADER_SIZE_BY_VERSION
+		.iter()
+		.rev()
+		.find(|(version, _)| systemd_version >= *version)
+		.map_or(MIN_HEADER_SIZE as _, |(_, size)| *size)
+}
+
+/// Offset of `header_size` in the header.
+const HEADER_SIZE_OFFSET: usize = 88;
+
+/// Check that a file's header isn't big-endian.
+///
+/// The `header_size` is always one of a few small values, so a file whose `header_size` only makes
+/// sense when read as big-endian was written by a big-endian machine. The flags would also be
+/// misread, but they can't tell apart a foreign file from one with unknown flags.
+fn check_byte_order(head: &[u8]) -> std::io::Result<()> {
+	let Some(bytes) = head
+		.get(HEADER_SIZE_OFFSET..HEADER_SIZE_OFFSET + 8)
+		.filter(|_| head.starts_with(SIGNATURE))
+	else {
+		// let the parser report what's wrong
+		return Ok(());
+	};
+
+	// UNWRAP: the slice is 8 bytes
+	let bytes: [u8; 8] = bytes.try_into().unwrap();
+	if !HEADER_FIELD_ENDS.contains(&u64::from_le_bytes(bytes))
+		&& HEADER_FIELD_ENDS.contains(&u64::from_be_bytes(bytes))
+	{
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			"journal file was written by a big-endian machine, which is unsupported",
+		));
+	}
+
+	Ok(())
+}
+
 impl From<Header> for FilenameInfo {
 	fn from(value: Header) -> Self {
 		if let (Some(head_seqnum), Some(head_realtime)) =
@@ -222,23 +327,125 @@ impl From<Header> for FilenameInfo {
 }
 
 impl Header {
+	/// Read the header at the start of a file.
+	///
+	/// Journal files are written in the byte order of the machine which wrote them, as systemd
+	/// doesn't byte-swap them, but this library only reads little-endian files. Files from a
+	/// big-endian machine are detected from their `header_size`, and are an `Unsupported` error
+	/// rather than being misread.
 	#[tracing::instrument(level = "trace", skip(io))]
 	pub async fn read<R: AsyncFileRead + Unpin>(io: &mut R) -> std::io::Result<Self> {
 		io.seek(std::io::SeekFrom::Start(0)).await?;
 		let head = io.read_bounded(MIN_HEADER_SIZE, MAX_HEADER_SIZE).await?;
+		let header = Self::parse(&head)?;
+		io.set_read_floor(header.header_size.get());
+		Ok(header)
+	}
+
+	/// Parse the header at the start of a file's bytes.
+	///
+	/// This does the same checks as [`read`](Self::read), without needing an IO source. Only the
+	/// first [`MAX_HEADER_SIZE`] bytes are looked at, so the rest of the file may or may not be
+	/// given, and nothing is allocated based on the input.
+	pub fn parse(bytes: &[u8]) -> std::io::Result<Self> {
+		let head = &bytes[..bytes.len().min(MAX_HEADER_SIZE)];
+		if head.len() < MIN_HEADER_SIZE {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::UnexpectedEof,
+				format!(
+					"header is too short ({} bytes, expected at least {MIN_HEADER_SIZE})",
+					head.len()
+				),
+			));
+		}
+		check_byte_order(head)?;
 
-		let (_, header) = Header::from_bytes((&head, 0))
+		let (_, header) = Header::from_bytes((head, 0))
 			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 
+		header.validate()?;
 		Ok(header)
 	}
 
+	/// Check the sizes that the offset math relies on.
+	///
+	/// A header larger than [`MAX_HEADER_SIZE`] is from a newer systemd, with fields this library
+	/// doesn't know about. That's allowed, with a warning, as the fields it does know about are
+	/// still where they were and the objects start after the whole header.
+	pub(crate) fn validate(&self) -> std::io::Result<()> {
+		let header_size = self.header_size.get();
+		if header_size < MIN_HEADER_SIZE as u64 {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("header_size {header_size} is smaller than {MIN_HEADER_SIZE}"),
+			));
+		}
+
+		if self.has_unknown_fields() {
+			// the first object is at the end of the header, and objects are 8-byte aligned
+			if !header_size.is_multiple_of(8) {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!("header_size {header_size} is not 8-byte aligned"),
+				));
+			}
+			tracing::warn!(
+				?header_size,
+				"journal file header is larger than {MAX_HEADER_SIZE} bytes, its extra fields are ignored"
+			);
+		} else if !HEADER_FIELD_ENDS.contains(&header_size) {
+			// optional fields are present based on the header_size, so it must end on a field boundary
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("header_size {header_size} doesn't end on a field boundary"),
+			));
+		}
+
+		let arena_size = self.arena_size.get();
+		if !arena_size.is_multiple_of(8) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("arena_size {arena_size} is not 8-byte aligned"),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Write the header at the start of a file.
+	///
+	/// This is the inverse of [`Header::read`]. Only the fields that exist for the `header_size`
+	/// are written; if the header is larger than what this library knows about, the bytes after
+	/// the known fields are left as they are.
+	#[tracing::instrument(level = "trace", skip(self, io))]
+	pub async fn write_to<W: AsyncWrite + AsyncSeek + Unpin>(
+		&self,
+		io: &mut W,
+	) -> std::io::Result<()> {
+		let head = self.serialize()?;
+		io.seek(std::io::SeekFrom::Start(0)).await?;
+		io.write_all(&head).await
+	}
+
+	/// Serialize the header as it's written at the start of a file.
+	///
+	/// This is `header_size` bytes: only the fields that exist for the size are included, and
+	/// absent values (`None`) are written as zeros.
+	pub fn serialize(&self) -> std::io::Result<Vec<u8>> {
+		let mut head = self
+			.to_bytes()
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+		head.truncate(self.header_size.get().try_into().unwrap_or(usize::MAX));
+		Ok(head)
+	}
+
 	/// Get the data hash table.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn data_hash_table<'h>(&'h self) -> HashTable<'h> {
 		HashTable {
 			offset: self.data_hash_table_offset,
 			size: self.data_hash_table_size,
+			n_objects: self.n_objects,
 			_phantom: std::marker::PhantomData,
 		}
 	}
@@ -249,6 +456,7 @@ impl Header {
 		HashTable {
 			offset: self.field_hash_table_offset,
 			size: self.field_hash_table_size,
+			n_objects: self.n_objects,
 			_phantom: std::marker::PhantomData,
 		}
 	}
@@ -258,11 +466,11 @@ impl Header {
 	/// This is approximated from header fields only, so is very fast, but can be inaccurate. For exact measurement
 	/// prefer to use [`HashTable::fill_level`](crate::tables::HashTable::fill_level) instead.
 	///
-	/// Returns None if the journal was created before systemd 187.
+	/// Returns None if the journal was created before systemd 187, or if the table has no slots.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn data_fill_level(&self) -> Option<f64> {
-		self.n_data
-			.map(|n| n as f64 / self.data_hash_table().capacity() as f64)
+		let capacity = NonZeroU64::new(self.data_hash_table().capacity())?;
+		self.n_data.map(|n| n as f64 / capacity.get() as f64)
 	}
 
 	/// How full the field hash table is.
@@ -270,20 +478,99 @@ impl Header {
 	/// This is approximated from header fields only, so is very fast, but can be inaccurate. For exact measurement
 	/// prefer to use [`HashTable::fill_level`](crate::tables::HashTable::fill_level) instead.
 	///
-	/// Returns None if the journal was created before systemd 187.
+	/// Returns None if the journal was created before systemd 187, or if the table has no slots.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn field_fill_level(&self) -> Option<f64> {
-		self.n_fields
-			.map(|n| n as f64 / self.field_hash_table().capacity() as f64)
+		let capacity = NonZeroU64::new(self.field_hash_table().capacity())?;
+		self.n_fields.map(|n| n as f64 / capacity.get() as f64)
 	}
 
 	/// Whether this journal file uses the compact layout.
+	///
+	/// This is [`IncompatibleFlag::Compact`], which sets the size of items in Entry and EntryArray
+	/// objects (see [`sizeof_entry_object_item`](Self::sizeof_entry_object_item) and
+	/// [`sizeof_entry_array_item`](Self::sizeof_entry_array_item)), and the layout of Data objects,
+	/// which have their own entry array fields in compact files.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn is_compact(&self) -> bool {
 		self.incompatible_flags.contains(IncompatibleFlag::Compact)
 	}
 
-	/// The size of Entry's items.
+	/// Whether the header has fields this library doesn't know about.
+	///
+	/// This is when the `header_size` is larger than [`MAX_HEADER_SIZE`], i.e. the file was written
+	/// by a newer systemd. The known fields are read as usual and the others are ignored.
+	pub fn has_unknown_fields(&self) -> bool {
+		self.header_size.get() > MAX_HEADER_SIZE as u64
+	}
+
+	/// The offset just past the arena, which is where the file's objects must end.
+	pub fn arena_end(&self) -> u64 {
+		self.header_size.get().saturating_add(self.arena_size.get())
+	}
+
+	/// Check that the compact flag is consistent with the header.
+	///
+	/// The compact format was added in systemd 252 along with the `tail_entry_array_*` fields, so
+	/// a compact file must have a header at least that large, and its arena must be addressable
+	/// (see [`check_compact_addressable`](Self::check_compact_addressable)).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub(crate) fn check_compact_layout(&self) -> std::io::Result<()> {
+		self.check_compact_addressable()?;
+
+		let header_size = self.header_size.get();
+		let min = header_size_for_version(252);
+		if self.is_compact() && header_size < min {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"compact journal file has a {header_size}-byte header, but compact files have at least {min}"
+				),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Check that the file can be addressed by compact (32-bit) item offsets.
+	///
+	/// Compact items store offsets as u32, so a compact file extending past 4 GiB is corrupt (it
+	/// should not have been created compact) and reading its items would yield wrong offsets.
+	///
+	/// Always succeeds for non-compact files.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub(crate) fn check_compact_addressable(&self) -> std::io::Result<()> {
+		let end = self.header_size.get().saturating_add(self.arena_size.get());
+		if self.is_compact() && end > u64::from(u32::MAX) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"compact journal file extends to offset {end}, which is not addressable by 32-bit items"
+				),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Hash a Data payload or a Field name with the algorithm this file uses.
+	#[tracing::instrument(level = "trace", skip(self, data))]
+	pub fn hash(&self, data: &[u8]) -> u64 {
+		if self
+			.incompatible_flags
+			.contains(IncompatibleFlag::KeyedHash)
+		{
+			keyed_hash64(self.file_id, data)
+		} else {
+			jenkins_hash64(data)
+		}
+	}
+
+	/// The size in bytes of the items of Entry objects, which follow the Entry's header.
+	///
+	/// Each item refers to one of the entry's Data objects. This is 4 in compact files (a 32-bit
+	/// offset) and 16 otherwise (a 64-bit offset and the Data's hash), see
+	/// [`is_compact`](Self::is_compact).
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn sizeof_entry_object_item(&self) -> u64 {
 		if self.is_compact() {
@@ -293,7 +580,10 @@ impl Header {
 		}
 	}
 
-	/// The size of EntryArray's items.
+	/// The size in bytes of the items of EntryArray objects, which follow the array's header.
+	///
+	/// Each item is the offset of an Entry object: this is 4 in compact files (32-bit offsets) and
+	/// 8 otherwise (64-bit offsets), see [`is_compact`](Self::is_compact).
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn sizeof_entry_array_item(&self) -> u64 {
 		if self.is_compact() {
@@ -304,6 +594,29 @@ impl Header {
 	}
 }
 
+#[cfg(test)]
+pub(crate) const HEADER_DATA: &[u8] = &[
+	0x4c, 0x50, 0x4b, 0x53, 0x48, 0x48, 0x52, 0x48, 0x02, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x00,
+	0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa0, 0x71, 0x3a, 0xc1, 0x94, 0xe5, 0x40, 0xcc,
+	0xa6, 0x62, 0xd1, 0x98, 0x8b, 0x5d, 0xd9, 0x24, 0xc4, 0x44, 0xc7, 0x1c, 0x03, 0x8d, 0x45, 0xb0,
+	0xaf, 0x20, 0x14, 0x44, 0xa8, 0x3b, 0x91, 0xc9, 0x82, 0xed, 0xa8, 0xaf, 0x55, 0x80, 0x4a, 0xbe,
+	0x8e, 0xca, 0x8e, 0xfb, 0x40, 0x72, 0xc6, 0x98, 0xae, 0x25, 0x7a, 0x22, 0x4b, 0x70, 0x40, 0x5a,
+	0x90, 0x42, 0xa9, 0x9a, 0xef, 0x05, 0x7c, 0xe0, 0x10, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0xf0, 0xfe, 0x7f, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x80, 0xe3, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0xd0, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x70, 0x17, 0x68, 0x02, 0x00, 0x00, 0x00, 0x00,
+	0x87, 0x4e, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe8, 0x4a, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x75, 0x12, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x94, 0x59, 0x2d, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x98, 0x09, 0x39, 0x00, 0x00, 0x00, 0x00, 0x00, 0x84, 0x11, 0x3e, 0x05, 0x68, 0x23, 0x06, 0x00,
+	0x23, 0xff, 0xf7, 0x14, 0x92, 0x23, 0x06, 0x00, 0xf6, 0x6f, 0x55, 0x54, 0x56, 0x00, 0x00, 0x00,
+	0xa4, 0x8e, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8d, 0x74, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x18, 0x16, 0xf3, 0x00, 0xda, 0xdb, 0x00, 0x00, 0x70, 0x17, 0x68, 0x02, 0x00, 0x00, 0x00, 0x00,
+	0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe0, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
 #[cfg(test)]
 #[tokio::test]
 async fn test_header_parse() {
@@ -311,29 +624,6 @@ async fn test_header_parse() {
 
 	use crate::tables::HASH_ITEM_SIZE;
 
-	const HEADER_DATA: &[u8] = &[
-		0x4c, 0x50, 0x4b, 0x53, 0x48, 0x48, 0x52, 0x48, 0x02, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00,
-		0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa0, 0x71, 0x3a, 0xc1, 0x94, 0xe5,
-		0x40, 0xcc, 0xa6, 0x62, 0xd1, 0x98, 0x8b, 0x5d, 0xd9, 0x24, 0xc4, 0x44, 0xc7, 0x1c, 0x03,
-		0x8d, 0x45, 0xb0, 0xaf, 0x20, 0x14, 0x44, 0xa8, 0x3b, 0x91, 0xc9, 0x82, 0xed, 0xa8, 0xaf,
-		0x55, 0x80, 0x4a, 0xbe, 0x8e, 0xca, 0x8e, 0xfb, 0x40, 0x72, 0xc6, 0x98, 0xae, 0x25, 0x7a,
-		0x22, 0x4b, 0x70, 0x40, 0x5a, 0x90, 0x42, 0xa9, 0x9a, 0xef, 0x05, 0x7c, 0xe0, 0x10, 0x01,
-		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0xfe, 0x7f, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xe3, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x20, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xd0, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x00, 0x70, 0x17, 0x68, 0x02, 0x00, 0x00, 0x00, 0x00, 0x87, 0x4e, 0x03, 0x00, 0x00, 0x00,
-		0x00, 0x00, 0xe8, 0x4a, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x75, 0x12, 0x2f, 0x00, 0x00,
-		0x00, 0x00, 0x00, 0x94, 0x59, 0x2d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x98, 0x09, 0x39, 0x00,
-		0x00, 0x00, 0x00, 0x00, 0x84, 0x11, 0x3e, 0x05, 0x68, 0x23, 0x06, 0x00, 0x23, 0xff, 0xf7,
-		0x14, 0x92, 0x23, 0x06, 0x00, 0xf6, 0x6f, 0x55, 0x54, 0x56, 0x00, 0x00, 0x00, 0xa4, 0x8e,
-		0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8d, 0x74, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-		0x00, 0x18, 0x16, 0xf3, 0x00, 0xda, 0xdb, 0x00, 0x00, 0x70, 0x17, 0x68, 0x02, 0x00, 0x00,
-		0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe0, 0x14, 0x00, 0x00, 0x00,
-		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-	];
-
 	let mut io = Cursor::new(HEADER_DATA);
 	let header = Header::read(&mut io).await.unwrap();
 	assert_eq!(
@@ -389,6 +679,96 @@ async fn test_header_parse() {
 	);
 }
 
+#[cfg(test)]
+#[tokio::test]
+async fn test_header_write() {
+	use futures_util::io::Cursor;
+
+	let header = Header::read(&mut Cursor::new(HEADER_DATA)).await.unwrap();
+	let mut io = Cursor::new(Vec::new());
+	header.write_to(&mut io).await.unwrap();
+	// the fixture goes on into the first object
+	assert_eq!(io.get_ref(), &HEADER_DATA[..MAX_HEADER_SIZE]);
+
+	// an older, smaller header only gets its own fields written
+	let mut header = header;
+	header.header_size = NonZeroU64::new(MIN_HEADER_SIZE as _).unwrap();
+	header.tail_entry_seqnum = None;
+	header.tail_entry_realtime = None;
+	let mut io = Cursor::new(vec![0xff; MAX_HEADER_SIZE]);
+	header.write_to(&mut io).await.unwrap();
+	assert_eq!(
+		&io.get_ref()[MIN_HEADER_SIZE..],
+		&[0xff; MAX_HEADER_SIZE - MIN_HEADER_SIZE]
+	);
+
+	let mut reread = Header::read(&mut Cursor::new(&io.get_ref()[..MIN_HEADER_SIZE]))
+		.await
+		.unwrap();
+	assert_eq!(reread.tail_entry_seqnum, None);
+	assert_eq!(reread.tail_entry_realtime, None);
+	assert_eq!(reread.n_data, None);
+	reread.n_data = header.n_data;
+	reread.n_fields = header.n_fields;
+	reread.n_tags = header.n_tags;
+	reread.n_entry_arrays = header.n_entry_arrays;
+	reread.data_hash_chain_depth = header.data_hash_chain_depth;
+	reread.field_hash_chain_depth = header.field_hash_chain_depth;
+	reread.tail_entry_array_offset = header.tail_entry_array_offset;
+	reread.tail_entry_array_n_entries = header.tail_entry_array_n_entries;
+	reread.tail_entry_offset = header.tail_entry_offset;
+	assert_eq!(reread, header);
+}
+
+#[test]
+fn test_header_roundtrip() {
+	// every header size, from the oldest to the newest: optional fields past the size are absent
+	for size in HEADER_FIELD_ENDS {
+		let mut bytes = HEADER_DATA[..size as usize].to_vec();
+		bytes[88..96].copy_from_slice(&size.to_le_bytes());
+		let (_, header) = Header::from_bytes((&bytes, 0)).unwrap();
+		assert_eq!(header.header_size.get(), size);
+		assert_eq!(header.serialize().unwrap(), bytes, "header_size {size}");
+	}
+
+	// absent values are zeros
+	let mut bytes = HEADER_DATA[..MAX_HEADER_SIZE].to_vec();
+	for field in [
+		56..72,   // tail_entry_boot_id
+		160..168, // tail_entry_seqnum
+		184..192, // head_entry_realtime
+		200..208, // tail_entry_monotonic
+		256..264, // tail_entry_array_offset, tail_entry_array_n_entries
+	] {
+		bytes[field].fill(0);
+	}
+	let (_, header) = Header::from_bytes((&bytes, 0)).unwrap();
+	assert_eq!(header.tail_entry_boot_id, None);
+	assert_eq!(header.tail_entry_seqnum, None);
+	assert_eq!(header.head_entry_realtime, None);
+	assert_eq!(header.tail_entry_monotonic, None);
+	assert_eq!(header.tail_entry_array_offset, None);
+	assert_eq!(header.tail_entry_array_n_entries, None);
+	assert_eq!(header.serialize().unwrap(), bytes);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_compact_addressable() {
+	use futures_util::io::Cursor;
+
+	let mut header = Header::read(&mut Cursor::new(HEADER_DATA)).await.unwrap();
+	assert!(header.is_compact());
+	header.check_compact_addressable().unwrap();
+
+	header.arena_size = NonZeroU64::new(u64::from(u32::MAX)).unwrap();
+	let err = header.check_compact_addressable().unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	header.incompatible_flags -= IncompatibleFlag::Compact;
+	header.check_compact_addressable().unwrap();
+}
+
 flags! {
 	/// Feature flags that can be ignored if not understood.
 	///
@@ -492,3 +872,120 @@ pub enum State {
 	/// The file is closed for writing and has been rotated.
 	Archived = 2,
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_header_validate() {
+	use futures_util::io::Cursor;
+
+	for (offset, value, error) in [
+		(88, 200, "header_size 200 is smaller than 208"),
+		(88, 284, "header_size 284 is not 8-byte aligned"),
+		(88, 212, "header_size 212 doesn't end on a field boundary"),
+		(96, 41942767, "arena_size 41942767 is not 8-byte aligned"),
+	] {
+		let mut data = HEADER_DATA.to_vec();
+		data[offset..offset + 8].copy_from_slice(&(value as u64).to_le_bytes());
+		let err = Header::read(&mut Cursor::new(&data[..])).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), error);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_header_big_endian() {
+	use futures_util::io::Cursor;
+
+	// only the sizes are swapped here, but that's what's checked
+	let mut data = HEADER_DATA.to_vec();
+	for offset in [88, 96] {
+		data[offset..offset + 8].reverse();
+	}
+	let err = Header::read(&mut Cursor::new(&data[..])).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+	assert_eq!(
+		err.to_string(),
+		"journal file was written by a big-endian machine, which is unsupported"
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_header_read_floor() {
+	use crate::{
+		reader::AsyncFileRead,
+		testing::{JournalBuilder, MemoryFiles},
+	};
+
+	let journal = JournalBuilder::new();
+	let mut io = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+	io.open(&MemoryFiles::make_filename(&journal.latest_name()))
+		.await
+		.unwrap();
+
+	// before the header is known, the floor is the smallest header size
+	assert!(io.read_some_at(MIN_HEADER_SIZE as _, 8).await.is_ok());
+	let err = io.read_some_at(8, 8).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	let header = Header::read(&mut io).await.unwrap();
+	assert_eq!(io.read_floor(), header.header_size.get());
+	let err = io.read_some_at(MIN_HEADER_SIZE as _, 8).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert_eq!(
+		err.to_string(),
+		format!("attempted to read into header region ({MIN_HEADER_SIZE} < {MAX_HEADER_SIZE})")
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_parse_from_bytes() {
+	use futures_util::io::Cursor;
+
+	use crate::objects::{ObjectHeader, ObjectType};
+
+	let header = Header::parse(HEADER_DATA).unwrap();
+	assert_eq!(
+		header,
+		Header::read(&mut Cursor::new(HEADER_DATA)).await.unwrap()
+	);
+
+	// trailing bytes are ignored, too few aren't
+	let mut data = HEADER_DATA.to_vec();
+	data.extend_from_slice(&[0xff; 1024]);
+	assert_eq!(Header::parse(&data).unwrap(), header);
+	let err = Header::parse(&HEADER_DATA[..MIN_HEADER_SIZE - 1]).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+	// same checks as when reading
+	let mut data = HEADER_DATA.to_vec();
+	data[88] = 0xff;
+	let err = Header::parse(&data).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	let object = ObjectHeader::parse(&[1, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0xff])
+		.unwrap()
+		.check_type(ObjectType::Data)
+		.unwrap();
+	assert_eq!(object.size, 64);
+	let err = ObjectHeader::parse(&[1, 0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0]).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	let err = ObjectHeader::parse(&[1, 0, 0, 0]).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(test)]
+#[test]
+fn test_item_sizes() {
+	let (_, mut header) = Header::from_bytes((HEADER_DATA, 0)).unwrap();
+	assert!(header.is_compact());
+	assert_eq!(header.sizeof_entry_object_item(), 4);
+	assert_eq!(header.sizeof_entry_array_item(), 4);
+
+	header.incompatible_flags -= IncompatibleFlag::Compact;
+	assert!(!header.is_compact());
+	assert_eq!(header.sizeof_entry_object_item(), 16);
+	assert_eq!(header.sizeof_entry_array_item(), 8);
+}
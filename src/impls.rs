@@ -1,12 +1,139 @@
+#[cfg(feature = "on-disk")]
+use std::path::{is_separator, Path, PathBuf};
+
+#[cfg(feature = "on-disk")]
+use async_stream::try_stream;
+#[cfg(feature = "on-disk")]
+use futures_util::Stream;
+
 #[cfg(feature = "on-disk")]
 pub use on_disk::JournalOnDisk;
 #[cfg(feature = "on-disk")]
 pub use read_whole::ReadWholeFile;
+pub use single_file::SingleFile;
 
 mod in_memory;
+mod single_file;
 
 #[cfg(feature = "on-disk")]
 mod on_disk;
 
 #[cfg(feature = "on-disk")]
 mod read_whole;
+
+/// Expand a leading `~` to the home directory, then canonicalize the root.
+///
+/// A root which can't be canonicalized (e.g. because it doesn't exist yet) is kept as is, and
+/// errors on use instead.
+#[cfg(feature = "on-disk")]
+fn resolve_root(root: PathBuf) -> PathBuf {
+	let home = std::env::home_dir();
+	let root = match (root.strip_prefix("~"), home) {
+		(Ok(rest), Some(home)) => home.join(rest),
+		_ => root,
+	};
+	std::fs::canonicalize(&root).unwrap_or(root)
+}
+
+/// Split a [`list_files`](crate::reader::AsyncFileRead::list_files) prefix into the directory to
+/// walk and the partial name its entries must start with.
+///
+/// A prefix ending with a separator names a whole directory, and has no partial name.
+#[cfg(feature = "on-disk")]
+fn split_prefix(root: &Path, prefix: Option<&Path>) -> (PathBuf, Option<String>) {
+	let Some(prefix) = prefix else {
+		return (root.into(), None);
+	};
+
+	let is_dir = prefix.as_os_str().to_string_lossy().ends_with(is_separator);
+	match (prefix.file_name(), prefix.parent()) {
+		(Some(name), Some(parent)) if !is_dir => {
+			(root.join(parent), Some(name.to_string_lossy().into_owned()))
+		}
+		_ => (root.join(prefix), None),
+	}
+}
+
+/// Whether a path found by walking `dir` matches the partial name.
+///
+/// This compares components rather than strings, so `journal` doesn't contain `journal2/a`.
+#[cfg(feature = "on-disk")]
+fn in_prefix(path: &Path, dir: &Path, partial: Option<&str>) -> bool {
+	let Ok(rest) = path.strip_prefix(dir) else {
+		return false;
+	};
+
+	partial.is_none_or(|partial| {
+		rest.components()
+			.next()
+			.is_some_and(|first| first.as_os_str().to_string_lossy().starts_with(partial))
+	})
+}
+
+/// Whether a path has this extension, or any (or none) if `None`.
+#[cfg(feature = "on-disk")]
+fn has_extension(path: &Path, extension: Option<&str>) -> bool {
+	extension.is_none_or(|extension| path.extension().is_some_and(|ext| ext == extension))
+}
+
+/// Walk the directory named by a [`list_files`](crate::reader::AsyncFileRead::list_files)
+/// prefix, yielding the paths of the files in it and below it which have the extension.
+///
+/// With `skip_unreadable`, errors reading directories below the root are logged and the
+/// directories skipped (see e.g. [`JournalOnDisk::with_skip_unreadable`]).
+#[cfg(feature = "on-disk")]
+fn walk_files(
+	root: &Path,
+	prefix: Option<&Path>,
+	extension: Option<String>,
+	skip_unreadable: bool,
+) -> impl Stream<Item = std::io::Result<PathBuf>> {
+	let (dir, partial) = split_prefix(root, prefix);
+	try_stream! {
+		let dir = tokio::fs::canonicalize(&dir).await?;
+
+		let mut todo = vec![dir.clone()];
+
+		while let Some(current) = todo.pop() {
+			let mut read_dir = match tokio::fs::read_dir(&current).await {
+				Ok(read_dir) => read_dir,
+				Err(err) if skip_unreadable && current != dir => {
+					tracing::warn!(dir = ?current, ?err, "skipping unreadable directory");
+					continue;
+				}
+				Err(err) => Err(err)?,
+			};
+
+			loop {
+				let entry = match read_dir.next_entry().await {
+					Ok(Some(entry)) => entry,
+					Ok(None) => break,
+					Err(err) if skip_unreadable => {
+						tracing::warn!(dir = ?current, ?err, "skipping the rest of unreadable directory");
+						break;
+					}
+					Err(err) => Err(err)?,
+				};
+
+				let path = entry.path();
+				let file_type = match entry.file_type().await {
+					Ok(file_type) => file_type,
+					Err(err) if skip_unreadable => {
+						tracing::warn!(?path, ?err, "skipping unreadable directory entry");
+						continue;
+					}
+					Err(err) => Err(err)?,
+				};
+
+				if file_type.is_dir() {
+					todo.push(path);
+				} else if file_type.is_file()
+					&& has_extension(&path, extension.as_deref())
+					&& in_prefix(&path, &dir, partial.as_deref())
+				{
+					yield path;
+				}
+			}
+		}
+	}
+}
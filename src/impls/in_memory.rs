@@ -5,21 +5,24 @@ use futures_util::{io::Cursor, Stream};
 use crate::reader::{AsyncFileRead, FilenameInfo};
 
 impl AsyncFileRead for Cursor<&[u8]> {
-	fn open(
-		&mut self,
-		_filename: &Path,
-	) -> impl std::future::Future<Output = std::io::Result<()>> + Send {
-		async move { Ok(()) }
+	async fn open(&mut self, _filename: &Path) -> std::io::Result<()> {
+		Ok(())
 	}
 
-	fn close(&mut self) -> impl std::future::Future<Output = ()> + Send {
-		async move {}
-	}
+	async fn close(&mut self) {}
 
 	fn current(&self) -> Option<&Path> {
 		None
 	}
 
+	fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(self.clone())
+	}
+
+	fn contiguous(&self) -> Option<&[u8]> {
+		Some(self.get_ref())
+	}
+
 	fn list_files(
 		&self,
 		_prefix: Option<&Path>,
@@ -1,30 +1,155 @@
+#[cfg(feature = "inotify")]
+use std::ffi::OsStr;
 use std::{
 	io,
 	path::{Path, PathBuf},
 	pin::Pin,
-	task::Poll,
+	task::{ready, Poll},
+	time::Duration,
 };
 
-use async_stream::try_stream;
 use futures_io::{AsyncRead, AsyncSeek};
-use futures_util::Stream;
+use futures_util::{Stream, TryStreamExt as _};
 use tokio::{fs::File, io::ReadBuf};
 
-use crate::reader::{AsyncFileRead, FilenameInfo};
+use super::{resolve_root, walk_files};
+use crate::{
+	header::MIN_HEADER_SIZE,
+	reader::{AsyncFileRead, FilenameInfo},
+};
 
 struct OpenFile {
 	path: PathBuf,
+	read_floor: u64,
+	read_ceiling: u64,
 	file: File,
+	/// Whether a seek was started and hasn't completed yet.
+	seeking: bool,
+}
+
+/// Notifications of changes in the directory of the open file.
+#[cfg(feature = "inotify")]
+struct Watcher {
+	dir: PathBuf,
+	events: inotify::EventStream<Vec<u8>>,
+}
+
+#[cfg(feature = "inotify")]
+impl Watcher {
+	/// Watch a directory for modified files, and for files created in or moved into it.
+	///
+	/// Watching the directory rather than the file itself keeps working when the file is replaced,
+	/// and also catches rotation, when a new file is created.
+	fn new(dir: &Path) -> io::Result<Self> {
+		use inotify::{Inotify, WatchMask};
+
+		let inotify = Inotify::init()?;
+		inotify.watches().add(
+			dir,
+			WatchMask::MODIFY | WatchMask::CREATE | WatchMask::MOVED_TO,
+		)?;
+		Ok(Self {
+			dir: dir.into(),
+			events: inotify.into_event_stream(vec![0; 4096])?,
+		})
+	}
+
+	/// Wait until the file with this name is modified, or any file is added to the directory.
+	async fn changed(&mut self, name: Option<&OsStr>) -> io::Result<()> {
+		use futures_util::{FutureExt as _, StreamExt as _};
+		use inotify::EventMask;
+
+		while let Some(event) = self.events.next().await {
+			let event = event?;
+			tracing::trace!(?event, "inotify event");
+			if event
+				.mask
+				.intersects(EventMask::CREATE | EventMask::MOVED_TO | EventMask::Q_OVERFLOW)
+				|| (event.mask.contains(EventMask::MODIFY) && event.name.as_deref() == name)
+			{
+				// a write is often several events, which this change covers too
+				while let Some(Some(event)) = self.events.next().now_or_never() {
+					tracing::trace!(?event, "inotify event");
+				}
+				return Ok(());
+			}
+		}
+
+		Err(io::Error::other("inotify stream ended"))
+	}
 }
 
 pub struct JournalOnDisk {
 	root: PathBuf,
+	extension: Option<String>,
+	skip_unreadable: bool,
 	open: Option<OpenFile>,
+	#[cfg(feature = "inotify")]
+	watcher: Option<Watcher>,
 }
 
 impl JournalOnDisk {
+	/// Read journals below this root directory.
+	///
+	/// A leading `~` is expanded to the home directory, and the root is canonicalized if it exists,
+	/// so relative roots and `.`/`..` segments are resolved once, here.
 	pub fn new(root: PathBuf) -> Self {
-		Self { root, open: None }
+		Self {
+			root: resolve_root(root),
+			extension: Some("journal".into()),
+			skip_unreadable: false,
+			open: None,
+			#[cfg(feature = "inotify")]
+			watcher: None,
+		}
+	}
+
+	/// Only list files with this extension.
+	///
+	/// Defaults to `journal`, as in the systemd on-disk scheme. Set to `None` to list all files,
+	/// e.g. when using a custom [`parse_filename`](AsyncFileRead::parse_filename) with another
+	/// scheme.
+	pub fn with_extension(mut self, extension: Option<&str>) -> Self {
+		self.extension = extension.map(Into::into);
+		self
+	}
+
+	/// Skip directories which can't be read when listing files, instead of erroring.
+	///
+	/// By default, listing files stops with the first error, so that a listing is complete or
+	/// fails. With this enabled, directories below the root which can't be read (e.g. for lack of
+	/// permissions, when reading the journals of other machines) are logged and skipped, and the
+	/// files which can be read are still listed. The root itself must be readable.
+	pub fn with_skip_unreadable(mut self, skip: bool) -> Self {
+		self.skip_unreadable = skip;
+		self
+	}
+
+	/// Watch the directory of a newly opened file, unless it's already watched.
+	///
+	/// If the watch can't be set up, waiting for changes falls back to polling.
+	#[cfg(feature = "inotify")]
+	fn watch(&mut self, path: &Path) {
+		let Some(dir) = path.parent() else {
+			return;
+		};
+		if self
+			.watcher
+			.as_ref()
+			.is_some_and(|watcher| watcher.dir == dir)
+		{
+			return;
+		}
+
+		self.watcher = Watcher::new(dir)
+			.inspect_err(|err| {
+				tracing::debug!(
+					?dir,
+					?err,
+					"can't watch for changes, falling back to polling"
+				);
+			})
+			.ok();
 	}
 }
 
@@ -35,9 +160,19 @@ impl AsyncFileRead for JournalOnDisk {
 		filename: &Path,
 	) -> impl std::future::Future<Output = io::Result<()>> + Send {
 		async move {
+			// close the current file first, so it's closed even if this one can't be opened
+			self.open = None;
 			let path = self.root.join(filename);
 			let file = File::open(&path).await?;
-			self.open = Some(OpenFile { path, file });
+			#[cfg(feature = "inotify")]
+			self.watch(&path);
+			self.open = Some(OpenFile {
+				path,
+				read_floor: MIN_HEADER_SIZE as _,
+				read_ceiling: u64::MAX,
+				file,
+				seeking: false,
+			});
 			Ok(())
 		}
 	}
@@ -54,42 +189,104 @@ impl AsyncFileRead for JournalOnDisk {
 		self.open.as_ref().map(|file| file.path.as_ref())
 	}
 
+	fn read_floor(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(MIN_HEADER_SIZE as _, |file| file.read_floor)
+	}
+
+	fn set_read_floor(&mut self, floor: u64) {
+		if let Some(file) = self.open.as_mut() {
+			file.read_floor = floor;
+		}
+	}
+
+	fn read_ceiling(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(u64::MAX, |file| file.read_ceiling)
+	}
+
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		if let Some(file) = self.open.as_mut() {
+			file.read_ceiling = ceiling;
+		}
+	}
+
+	/// Wait for the open file to change, with inotify.
+	///
+	/// This returns when the file is modified or when a file is created in (or moved into) its
+	/// directory, which happens when the journal is rotated. If inotify isn't available, this
+	/// sleeps for the whole timeout instead.
+	#[cfg(feature = "inotify")]
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn wait_for_change(
+		&mut self,
+		timeout: Duration,
+	) -> impl std::future::Future<Output = io::Result<()>> + Send {
+		async move {
+			if let Some(watcher) = self.watcher.as_mut() {
+				let name = self.open.as_ref().and_then(|open| open.path.file_name());
+				match tokio::time::timeout(timeout, watcher.changed(name)).await {
+					Ok(Ok(())) | Err(_) => return Ok(()),
+					Ok(Err(err)) => {
+						tracing::debug!(
+							?err,
+							"watching for changes failed, falling back to polling"
+						);
+						self.watcher = None;
+					}
+				}
+			}
+
+			tokio::time::sleep(timeout).await;
+			Ok(())
+		}
+	}
+
+	/// Wait for the timeout on the tokio timer, as there's no inotify to be notified of changes.
+	#[cfg(not(feature = "inotify"))]
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn wait_for_change(
+		&mut self,
+		timeout: Duration,
+	) -> impl std::future::Future<Output = io::Result<()>> + Send {
+		async move {
+			tokio::time::sleep(timeout).await;
+			Ok(())
+		}
+	}
+
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn try_clone(&self) -> io::Result<Self> {
+		Ok(Self::new(self.root.clone())
+			.with_extension(self.extension.as_deref())
+			.with_skip_unreadable(self.skip_unreadable))
+	}
+
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn file_size(
+		&self,
+		filename: &Path,
+	) -> impl std::future::Future<Output = io::Result<u64>> + Send {
+		let path = self.root.join(filename);
+		async move { Ok(tokio::fs::metadata(&path).await?.len()) }
+	}
+
 	#[tracing::instrument(level = "trace", skip(self))]
 	fn list_files(
 		&self,
 		prefix: Option<&Path>,
 	) -> impl Stream<Item = io::Result<FilenameInfo>> + Unpin {
-		Box::pin(try_stream! {
-			let root = match prefix {
-				Some(prefix) => self.root.join(prefix.parent().unwrap_or(prefix)),
-				None => self.root.clone(),
-			};
-
-			let mut todo = vec![root.clone()];
-
-			loop {
-				let Some(current) = todo.pop() else {
-					break;
-				};
-
-				let mut read_dir = tokio::fs::read_dir(&current).await?;
-				while let Some(entry) = read_dir.next_entry().await? {
-					let file_type = entry.file_type().await?;
-					if file_type.is_dir() {
-						todo.push(entry.path());
-					} else if file_type.is_file()
-						&& entry
-							.path()
-							.to_string_lossy()
-							.starts_with(root.to_string_lossy().as_ref())
-					{
-						if let Some(file) = Self::parse_filename(&entry.path()) {
-							yield file;
-						}
-					}
-				}
-			}
-		})
+		Box::pin(
+			walk_files(
+				&self.root,
+				prefix,
+				self.extension.clone(),
+				self.skip_unreadable,
+			)
+			.try_filter_map(|path| std::future::ready(Ok(Self::parse_filename(&path)))),
+		)
 	}
 }
 
@@ -109,12 +306,16 @@ impl AsyncSeek for JournalOnDisk {
 				)))
 			},
 			|open| {
-				let _ = Pin::new(&mut open.file).poll_complete(cx);
-				if let Err(err) = Pin::new(&mut open.file).start_seek(pos) {
-					return Poll::Ready(Err(err));
+				// when polled again while the seek is in flight, it must not be started again
+				if !open.seeking {
+					let _ = ready!(Pin::new(&mut open.file).poll_complete(cx));
+					Pin::new(&mut open.file).start_seek(pos)?;
+					open.seeking = true;
 				}
 
-				Pin::new(&mut open.file).poll_complete(cx)
+				let result = ready!(Pin::new(&mut open.file).poll_complete(cx));
+				open.seeking = false;
+				Poll::Ready(result)
 			},
 		)
 	}
@@ -219,3 +420,363 @@ fn test_make_prefix() {
 		PathBuf::from("c444c71c038d45b0af201444a83b91c9/system@"),
 	);
 }
+
+#[test]
+fn test_filename_namespace() {
+	use crate::reader::{
+		namespaced_filename, namespaced_prefix, parse_namespaced_filename, JournalSelection,
+	};
+
+	let info = FilenameInfo::Latest {
+		machine_id: 0xc444c71c038d45b0af201444a83b91c9,
+		scope: "system.foo".into(),
+	};
+	let selection = JournalSelection {
+		machine_id: 0xc444c71c038d45b0af201444a83b91c9,
+		scope: "system.foo".into(),
+	};
+
+	let path = PathBuf::from("c444c71c038d45b0af201444a83b91c9.foo/system.journal");
+	assert_eq!(namespaced_filename(&info), path);
+	assert_eq!(parse_namespaced_filename(&path), Some(info.clone()));
+	assert_eq!(
+		namespaced_prefix(&selection),
+		PathBuf::from("c444c71c038d45b0af201444a83b91c9.foo/system@"),
+	);
+
+	// the defaults don't know about namespaces
+	let path = PathBuf::from("c444c71c038d45b0af201444a83b91c9/system.foo.journal");
+	assert_eq!(JournalOnDisk::make_filename(&info), path);
+	assert_eq!(
+		JournalOnDisk::make_prefix(&selection),
+		PathBuf::from("c444c71c038d45b0af201444a83b91c9/system.foo@"),
+	);
+	assert_eq!(
+		JournalOnDisk::parse_filename(Path::new(
+			"c444c71c038d45b0af201444a83b91c9.foo/system.journal"
+		)),
+		None
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_list_files_extension() {
+	use futures_util::StreamExt as _;
+
+	let root = std::env::temp_dir().join(format!("journald-format-{}", uuid::Uuid::new_v4()));
+	let dir = root.join("c444c71c038d45b0af201444a83b91c9");
+	tokio::fs::create_dir_all(&dir).await.unwrap();
+	for name in [
+		"system.journal",
+		"system.journal~",
+		"user-1000@ae257a224b70405a9042a99aef057ce0-00000000002d5994-00062368053e1184.journal~",
+		"user-1000.log",
+		"fss",
+	] {
+		tokio::fs::write(dir.join(name), b"").await.unwrap();
+	}
+
+	let list = |disk: JournalOnDisk| async move {
+		disk.list_files(None)
+			.map(|file| file.unwrap())
+			.collect::<Vec<_>>()
+			.await
+	};
+	let system = FilenameInfo::Latest {
+		machine_id: 0xc444c71c038d45b0af201444a83b91c9,
+		scope: "system".into(),
+	};
+	assert_eq!(
+		list(JournalOnDisk::new(root.clone())).await,
+		vec![system.clone()]
+	);
+
+	let mut all = list(JournalOnDisk::new(root.clone()).with_extension(None)).await;
+	all.sort();
+	assert_eq!(
+		all,
+		vec![
+			system,
+			FilenameInfo::Latest {
+				machine_id: 0xc444c71c038d45b0af201444a83b91c9,
+				scope: "user-1000".into(),
+			},
+		]
+	);
+
+	tokio::fs::remove_dir_all(&root).await.unwrap();
+}
+
+#[test]
+fn test_parse_filename_nested() {
+	let system = |machine_id| FilenameInfo::Latest {
+		machine_id,
+		scope: "system".into(),
+	};
+
+	for path in [
+		// nested below the machine id directory
+		"/var/log/journal/c444c71c038d45b0af201444a83b91c9/archive/2024/system.journal",
+		// uppercase hex
+		"/var/log/journal/C444C71C038D45B0AF201444A83B91C9/system.journal",
+		// Windows-style separators, whatever the platform
+		r"C:\journal\c444c71c038d45b0af201444a83b91c9\system.journal",
+		// relative
+		"c444c71c038d45b0af201444a83b91c9/system.journal",
+	] {
+		assert_eq!(
+			JournalOnDisk::parse_filename(Path::new(path)),
+			Some(system(0xc444c71c038d45b0af201444a83b91c9)),
+			"{path}"
+		);
+	}
+
+	// the closest machine id directory wins
+	assert_eq!(
+		JournalOnDisk::parse_filename(Path::new(
+			"/backup/c444c71c038d45b0af201444a83b91c9/ae257a224b70405a9042a99aef057ce0/system.journal"
+		)),
+		Some(system(0xae257a224b70405a9042a99aef057ce0))
+	);
+
+	for path in [
+		"/var/log/journal/system.journal",
+		"/var/log/journal/c444c71c/system.journal",
+		"system.journal",
+	] {
+		assert_eq!(
+			JournalOnDisk::parse_filename(Path::new(path)),
+			None,
+			"{path}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_list_files_sibling_prefix() {
+	use futures_util::StreamExt as _;
+
+	let root = std::env::temp_dir().join(format!("journald-format-{}", uuid::Uuid::new_v4()));
+	for (dir, machine_id) in [
+		("journal", "c444c71c038d45b0af201444a83b91c9"),
+		("journal2", "ae257a224b70405a9042a99aef057ce0"),
+	] {
+		let dir = root.join(dir).join(machine_id);
+		tokio::fs::create_dir_all(&dir).await.unwrap();
+		tokio::fs::write(dir.join("system.journal"), b"")
+			.await
+			.unwrap();
+	}
+
+	let list = |disk: JournalOnDisk, prefix: Option<&'static str>| async move {
+		let mut files = disk
+			.list_files(prefix.map(Path::new))
+			.map(|file| file.unwrap())
+			.map(|file| match file {
+				FilenameInfo::Latest { machine_id, .. } => machine_id,
+				FilenameInfo::Archived { machine_id, .. } => machine_id,
+			})
+			.collect::<Vec<_>>()
+			.await;
+		files.sort();
+		files
+	};
+	let journal = 0xc444c71c038d45b0af201444a83b91c9;
+	let journal2 = 0xae257a224b70405a9042a99aef057ce0;
+
+	assert_eq!(
+		list(JournalOnDisk::new(root.join("journal")), None).await,
+		vec![journal]
+	);
+	assert_eq!(
+		list(JournalOnDisk::new(root.join("journal2/../journal")), None).await,
+		vec![journal]
+	);
+	assert_eq!(
+		list(JournalOnDisk::new(root.clone()), Some("journal/")).await,
+		vec![journal]
+	);
+	assert_eq!(
+		list(
+			JournalOnDisk::new(root.clone()),
+			Some("journal2/../journal/")
+		)
+		.await,
+		vec![journal]
+	);
+	assert_eq!(
+		list(JournalOnDisk::new(root.clone()), Some("journal")).await,
+		vec![journal2, journal]
+	);
+	assert_eq!(
+		list(
+			JournalOnDisk::new(root.clone()),
+			Some("journal/c444c71c038d45b0af201444a83b91c9/system@")
+		)
+		.await,
+		vec![]
+	);
+
+	tokio::fs::remove_dir_all(&root).await.unwrap();
+}
+
+#[cfg(all(test, feature = "inotify"))]
+#[tokio::test]
+async fn test_wait_for_change() {
+	use std::time::{Duration, Instant};
+
+	let root = std::env::temp_dir().join(format!("journald-format-{}", uuid::Uuid::new_v4()));
+	let dir = root.join("c444c71c038d45b0af201444a83b91c9");
+	tokio::fs::create_dir_all(&dir).await.unwrap();
+	tokio::fs::write(dir.join("system.journal"), b"")
+		.await
+		.unwrap();
+	tokio::fs::write(dir.join("user-1000.journal"), b"")
+		.await
+		.unwrap();
+
+	let mut disk = JournalOnDisk::new(root.clone());
+	disk.open(Path::new("c444c71c038d45b0af201444a83b91c9/system.journal"))
+		.await
+		.unwrap();
+	assert!(disk.watcher.is_some());
+
+	let timeout = Duration::from_secs(30);
+	let write = |name: &'static str| {
+		let path = dir.join(name);
+		tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(50)).await;
+			tokio::fs::write(path, b"more").await.unwrap();
+		})
+	};
+
+	// other files changing don't count, so this waits for the open file
+	let start = Instant::now();
+	write("user-1000.journal").await.unwrap();
+	let modified = write("system.journal");
+	disk.wait_for_change(timeout).await.unwrap();
+	modified.await.unwrap();
+	assert!(start.elapsed() < timeout);
+
+	// a new file is a rotation
+	let created = write("system@0000.journal");
+	disk.wait_for_change(timeout).await.unwrap();
+	created.await.unwrap();
+	assert!(start.elapsed() < timeout);
+
+	// nothing happening times out
+	let mut disk = JournalOnDisk::new(root.clone());
+	disk.open(Path::new("c444c71c038d45b0af201444a83b91c9/system.journal"))
+		.await
+		.unwrap();
+	let start = Instant::now();
+	let short = Duration::from_millis(20);
+	disk.wait_for_change(short).await.unwrap();
+	assert!(start.elapsed() >= short);
+
+	tokio::fs::remove_dir_all(&root).await.unwrap();
+}
+
+#[cfg(all(test, not(feature = "inotify")))]
+#[tokio::test]
+async fn test_wait_for_change_sleeps() {
+	use std::time::Instant;
+
+	// without inotify, this sleeps for the whole timeout on the tokio timer
+	let mut disk = JournalOnDisk::new(std::env::temp_dir());
+	let start = Instant::now();
+	let timeout = Duration::from_millis(20);
+	disk.wait_for_change(timeout).await.unwrap();
+	assert!(start.elapsed() >= timeout);
+}
+
+#[cfg(all(test, target_os = "linux"))]
+#[tokio::test]
+async fn test_list_files_skip_unreadable() {
+	use std::{collections::BTreeSet, process::Command};
+
+	use futures_util::TryStreamExt as _;
+
+	let root = std::env::temp_dir().join(format!("list-files-{}", std::process::id()));
+	for machine_id in [1_u128, 2] {
+		let dir = root.join(format!("{machine_id:032x}"));
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("system.journal"), b"").unwrap();
+	}
+
+	// a directory which can't be read even as root, as its path is longer than PATH_MAX: it's
+	// made by going down with relative paths, which aren't limited
+	let name = "d".repeat(250);
+	let status = Command::new("sh")
+		.current_dir(&root)
+		.arg("-c")
+		.arg(format!(
+			"for i in $(seq 20); do mkdir {name} && cd -P {name} || exit 1; done"
+		))
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	let strict = JournalOnDisk::new(root.clone());
+	assert!(strict
+		.list_files(None)
+		.try_collect::<BTreeSet<_>>()
+		.await
+		.is_err());
+
+	let lenient = JournalOnDisk::new(root.clone()).with_skip_unreadable(true);
+	let files = lenient
+		.try_clone()
+		.unwrap()
+		.list_files(None)
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+	assert_eq!(files.len(), 2);
+	for machine_id in [1, 2] {
+		assert!(files.contains(&FilenameInfo::Latest {
+			machine_id,
+			scope: "system".into(),
+		}));
+	}
+
+	Command::new("rm").arg("-rf").arg(&root).status().unwrap();
+}
+
+#[cfg(all(test, target_os = "linux"))]
+#[tokio::test]
+async fn test_open_closes_previous() {
+	let root = std::env::temp_dir().join(format!("journald-format-{}", uuid::Uuid::new_v4()));
+	tokio::fs::create_dir_all(&root).await.unwrap();
+	for name in ["a.journal", "b.journal"] {
+		tokio::fs::write(root.join(name), b"").await.unwrap();
+	}
+
+	// whether this process has a handle to the file
+	let is_open = |name: &str| {
+		let path = root.join(name);
+		std::fs::read_dir("/proc/self/fd")
+			.unwrap()
+			.filter_map(|fd| std::fs::read_link(fd.ok()?.path()).ok())
+			.any(|target| target == path)
+	};
+
+	let mut disk = JournalOnDisk::new(root.clone());
+	disk.open(Path::new("a.journal")).await.unwrap();
+	assert!(is_open("a.journal"));
+
+	disk.open(Path::new("b.journal")).await.unwrap();
+	assert!(!is_open("a.journal"));
+	assert!(is_open("b.journal"));
+	assert_eq!(disk.current(), Some(&*root.join("b.journal")));
+
+	// even when the new file can't be opened
+	let err = disk.open(Path::new("c.journal")).await.unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::NotFound);
+	assert!(!is_open("b.journal"));
+	assert_eq!(disk.current(), None);
+
+	tokio::fs::remove_dir_all(&root).await.unwrap();
+}
@@ -3,28 +3,64 @@ use std::{
 	path::{Path, PathBuf},
 	pin::Pin,
 	task::Poll,
+	time::Duration,
 };
 
-use async_stream::try_stream;
 use futures_io::{AsyncRead, AsyncSeek};
-use futures_util::{io::Cursor, Stream};
+use futures_util::{io::Cursor, Stream, TryStreamExt as _};
 use tokio::fs;
 
-use crate::reader::{AsyncFileRead, FilenameInfo};
+use super::{resolve_root, walk_files};
+use crate::{
+	header::MIN_HEADER_SIZE,
+	reader::{AsyncFileRead, FilenameInfo},
+};
 
 struct OpenFile {
 	path: PathBuf,
+	read_floor: u64,
+	read_ceiling: u64,
 	file: Cursor<Vec<u8>>,
 }
 
 pub struct ReadWholeFile {
 	root: PathBuf,
+	extension: Option<String>,
+	skip_unreadable: bool,
 	open: Option<OpenFile>,
 }
 
 impl ReadWholeFile {
+	/// Read journals below this root directory.
+	///
+	/// A leading `~` is expanded to the home directory, and the root is canonicalized if it exists,
+	/// so relative roots and `.`/`..` segments are resolved once, here.
 	pub fn new(root: PathBuf) -> Self {
-		Self { root, open: None }
+		Self {
+			root: resolve_root(root),
+			extension: Some("journal".into()),
+			skip_unreadable: false,
+			open: None,
+		}
+	}
+
+	/// Only list files with this extension.
+	///
+	/// Defaults to `journal`, as in the systemd on-disk scheme. Set to `None` to list all files,
+	/// e.g. when using a custom [`parse_filename`](AsyncFileRead::parse_filename) with another
+	/// scheme.
+	pub fn with_extension(mut self, extension: Option<&str>) -> Self {
+		self.extension = extension.map(Into::into);
+		self
+	}
+
+	/// Skip directories which can't be read when listing files, instead of erroring.
+	///
+	/// Files are listed as with [`JournalOnDisk`](super::JournalOnDisk), see
+	/// [`JournalOnDisk::with_skip_unreadable`](super::JournalOnDisk::with_skip_unreadable).
+	pub fn with_skip_unreadable(mut self, skip: bool) -> Self {
+		self.skip_unreadable = skip;
+		self
 	}
 }
 
@@ -35,9 +71,16 @@ impl AsyncFileRead for ReadWholeFile {
 		filename: &Path,
 	) -> impl std::future::Future<Output = io::Result<()>> + Send {
 		async move {
+			// close the current file first, so it's closed even if this one can't be opened
+			self.open = None;
 			let path = self.root.join(filename);
 			let file = Cursor::new(fs::read(&path).await?);
-			self.open = Some(OpenFile { path, file });
+			self.open = Some(OpenFile {
+				path,
+				read_floor: MIN_HEADER_SIZE as _,
+				read_ceiling: u64::MAX,
+				file,
+			});
 			Ok(())
 		}
 	}
@@ -54,42 +97,78 @@ impl AsyncFileRead for ReadWholeFile {
 		self.open.as_ref().map(|file| file.path.as_ref())
 	}
 
+	fn read_floor(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(MIN_HEADER_SIZE as _, |file| file.read_floor)
+	}
+
+	fn set_read_floor(&mut self, floor: u64) {
+		if let Some(file) = self.open.as_mut() {
+			file.read_floor = floor;
+		}
+	}
+
+	fn read_ceiling(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(u64::MAX, |file| file.read_ceiling)
+	}
+
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		if let Some(file) = self.open.as_mut() {
+			file.read_ceiling = ceiling;
+		}
+	}
+
+	fn contiguous(&self) -> Option<&[u8]> {
+		self.open
+			.as_ref()
+			.map(|file| file.file.get_ref().as_slice())
+	}
+
+	/// Wait for the timeout on the tokio timer, which files are read with anyway.
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn wait_for_change(
+		&mut self,
+		timeout: Duration,
+	) -> impl std::future::Future<Output = io::Result<()>> + Send {
+		async move {
+			tokio::time::sleep(timeout).await;
+			Ok(())
+		}
+	}
+
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn try_clone(&self) -> io::Result<Self> {
+		Ok(Self::new(self.root.clone())
+			.with_extension(self.extension.as_deref())
+			.with_skip_unreadable(self.skip_unreadable))
+	}
+
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn file_size(
+		&self,
+		filename: &Path,
+	) -> impl std::future::Future<Output = io::Result<u64>> + Send {
+		let path = self.root.join(filename);
+		async move { Ok(fs::metadata(&path).await?.len()) }
+	}
+
 	#[tracing::instrument(level = "trace", skip(self))]
 	fn list_files(
 		&self,
 		prefix: Option<&Path>,
 	) -> impl Stream<Item = io::Result<FilenameInfo>> + Unpin {
-		Box::pin(try_stream! {
-			let root = match prefix {
-				Some(prefix) => self.root.join(prefix.parent().unwrap_or(prefix)),
-				None => self.root.clone(),
-			};
-
-			let mut todo = vec![root.clone()];
-
-			loop {
-				let Some(current) = todo.pop() else {
-					break;
-				};
-
-				let mut read_dir = tokio::fs::read_dir(&current).await?;
-				while let Some(entry) = read_dir.next_entry().await? {
-					let file_type = entry.file_type().await?;
-					if file_type.is_dir() {
-						todo.push(entry.path());
-					} else if file_type.is_file()
-						&& entry
-							.path()
-							.to_string_lossy()
-							.starts_with(root.to_string_lossy().as_ref())
-					{
-						if let Some(file) = Self::parse_filename(&entry.path()) {
-							yield file;
-						}
-					}
-				}
-			}
-		})
+		Box::pin(
+			walk_files(
+				&self.root,
+				prefix,
+				self.extension.clone(),
+				self.skip_unreadable,
+			)
+			.try_filter_map(|path| std::future::ready(Ok(Self::parse_filename(&path)))),
+		)
 	}
 }
 
@@ -0,0 +1,196 @@
+use std::{
+	io,
+	path::{Path, PathBuf},
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures_io::{AsyncRead, AsyncSeek};
+use futures_util::Stream;
+
+use crate::{
+	header::MIN_HEADER_SIZE,
+	reader::{AsyncFileRead, FilenameInfo},
+};
+
+/// A single journal file read from any seekable stream.
+///
+/// This is for reading a journal without a filesystem, e.g. from a network stream or a decrypted
+/// pipe (made seekable), or from memory. The stream is presented as the only file there is, under
+/// the name given: [`list_files`](AsyncFileRead::list_files) lists just that, and
+/// [`open`](AsyncFileRead::open) and [`close`](AsyncFileRead::close) only track whether it's
+/// [`current`](AsyncFileRead::current), as there's nothing else to open. Other files are
+/// `NotFound`, so that the reader doesn't go round reading the stream again as the next file of
+/// the journal. Select the journal the name is of to read it, e.g. with
+/// [`JournalSelection::from`](crate::reader::JournalSelection).
+///
+/// As with journals on disk, the reader expects the latest file of a journal to be there once
+/// it's read its archived files, so a stream given an archived name is best read with
+/// [`JournalReader::open_file`](crate::reader::JournalReader::open_file).
+pub struct SingleFile<S> {
+	stream: S,
+	file: FilenameInfo,
+	path: PathBuf,
+	is_open: bool,
+	read_floor: u64,
+	read_ceiling: u64,
+}
+
+impl<S> SingleFile<S>
+where
+	S: AsyncRead + AsyncSeek + Unpin + Send,
+{
+	/// Read the journal file in this stream, under this name.
+	pub fn new(stream: S, file: FilenameInfo) -> Self {
+		Self {
+			stream,
+			path: Self::make_filename(&file),
+			file,
+			is_open: true,
+			read_floor: MIN_HEADER_SIZE as _,
+			read_ceiling: u64::MAX,
+		}
+	}
+
+	/// The name the file is listed under.
+	pub fn file(&self) -> &FilenameInfo {
+		&self.file
+	}
+
+	/// Get the stream back.
+	pub fn into_inner(self) -> S {
+		self.stream
+	}
+}
+
+impl<S> AsyncFileRead for SingleFile<S>
+where
+	S: AsyncRead + AsyncSeek + Unpin + Send,
+{
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn open(
+		&mut self,
+		filename: &Path,
+	) -> impl std::future::Future<Output = io::Result<()>> + Send {
+		self.is_open = filename == self.path;
+		let opened = if self.is_open {
+			self.read_floor = MIN_HEADER_SIZE as _;
+			self.read_ceiling = u64::MAX;
+			Ok(())
+		} else {
+			Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("only {:?} can be read from this stream", self.path),
+			))
+		};
+		std::future::ready(opened)
+	}
+
+	fn close(&mut self) -> impl std::future::Future<Output = ()> + Send {
+		self.is_open = false;
+		std::future::ready(())
+	}
+
+	fn current(&self) -> Option<&Path> {
+		self.is_open.then_some(&*self.path)
+	}
+
+	fn read_floor(&self) -> u64 {
+		self.read_floor
+	}
+
+	fn set_read_floor(&mut self, floor: u64) {
+		self.read_floor = floor;
+	}
+
+	fn read_ceiling(&self) -> u64 {
+		self.read_ceiling
+	}
+
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		self.read_ceiling = ceiling;
+	}
+
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn list_files(
+		&self,
+		prefix: Option<&Path>,
+	) -> impl Stream<Item = io::Result<FilenameInfo>> + Unpin {
+		let listed = prefix.is_none_or(|prefix| {
+			self.path
+				.to_string_lossy()
+				.starts_with(&*prefix.to_string_lossy())
+		});
+		futures_util::stream::iter(listed.then(|| Ok(self.file.clone())))
+	}
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for SingleFile<S> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.stream).poll_read(cx, buf)
+	}
+}
+
+impl<S: AsyncSeek + Unpin> AsyncSeek for SingleFile<S> {
+	fn poll_seek(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		pos: io::SeekFrom,
+	) -> Poll<io::Result<u64>> {
+		Pin::new(&mut self.stream).poll_seek(cx, pos)
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_single_file() {
+	use futures_util::{io::Cursor, StreamExt as _, TryStreamExt as _};
+
+	use crate::{
+		reader::{JournalReader, JournalSelection},
+		testing::{JournalBuilder, TestEntry},
+	};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let file = journal.latest_name();
+	let mut io = SingleFile::new(Cursor::new(journal.build()), file.clone());
+	assert_eq!(
+		io.list_files(None).try_collect::<Vec<_>>().await.unwrap(),
+		std::slice::from_ref(&file)
+	);
+	let other = JournalSelection {
+		scope: "user-1000".into(),
+		..JournalSelection::from(file.clone())
+	};
+	assert!(io
+		.list_files(Some(&SingleFile::<Cursor<Vec<u8>>>::make_prefix(&other)))
+		.next()
+		.await
+		.is_none());
+
+	let err = io
+		.open(&SingleFile::<Cursor<Vec<u8>>>::make_filename(
+			&journal.archived_name(),
+		))
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::NotFound);
+	assert_eq!(io.current(), None);
+
+	let mut reader = JournalReader::new(io);
+	reader.select(JournalSelection::from(file)).await.unwrap();
+	let seqnums = reader
+		.entries()
+		.map_ok(|entry| entry.seqnum().get())
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+	assert_eq!(seqnums, [1, 2]);
+	assert!(reader.verify_all().await.unwrap());
+}
@@ -0,0 +1,360 @@
+//! Import of entries from the textual journal formats.
+//!
+//! This is the reverse of [`export`](crate::export): entries in the [Journal Export Format] are
+//! parsed and written to a journal file.
+//!
+//! [Journal Export Format]: https://systemd.io/JOURNAL_EXPORT_FORMATS/#journal-export-format
+
+use std::{
+	io::{Error, ErrorKind, Result},
+	num::{NonZeroU128, NonZeroU64},
+	str::FromStr,
+};
+
+use bstr::BString;
+use futures_io::AsyncRead;
+use futures_util::io::{AsyncBufReadExt as _, AsyncReadExt as _, BufReader};
+use jiff::Timestamp;
+
+use crate::{
+	compression::MAX_DECOMPRESSED_SIZE,
+	monotonic::Monotonic,
+	writer::{AsyncFileWrite, EntryMetadata, JournalWriter},
+};
+
+/// Write entries in the [Journal Export Format] to a journal.
+///
+/// Fields can be either text (`KEY=value`) or binary (`KEY`, a little-endian 64-bit length, and
+/// the value), and entries are separated by blank lines.
+///
+/// The `__REALTIME_TIMESTAMP`, `__MONOTONIC_TIMESTAMP`, and `__SEQNUM` fields and the boot ID
+/// from the `_BOOT_ID` field go in the header of the written entry, as with
+/// [`JournalWriter::write_entry_with`]. Other fields starting with a double underscore, like
+/// `__CURSOR`, describe the entry's place in its original file, so they're discarded. Note that
+/// sequence numbers are kept as long as they increase, but they're then in the seqnum domain of
+/// the written file.
+///
+/// Returns the number of entries written.
+///
+/// [Journal Export Format]: https://systemd.io/JOURNAL_EXPORT_FORMATS/#journal-export-format
+#[tracing::instrument(level = "debug", skip(reader, writer))]
+pub async fn import_export_stream<R: AsyncRead + Unpin, W: AsyncFileWrite>(
+	reader: R,
+	writer: &mut JournalWriter<W>,
+) -> Result<u64> {
+	let mut reader = BufReader::new(reader);
+	let mut n_entries = 0;
+	let mut metadata = EntryMetadata::default();
+	let mut fields = Vec::new();
+	let mut line = Vec::new();
+	loop {
+		line.clear();
+		let read = reader.read_until(b'\n', &mut line).await?;
+		if read == 0 || line == b"\n" {
+			// an entry without fields isn't written, but its metadata mustn't carry over either
+			let metadata = std::mem::take(&mut metadata);
+			if !fields.is_empty() {
+				writer.write_entry_with(metadata, fields.drain(..)).await?;
+				n_entries += 1;
+			}
+			if read == 0 {
+				break;
+			}
+			continue;
+		}
+
+		let key;
+		let value;
+		if let Some(eq) = line.iter().position(|byte| *byte == b'=') {
+			key = &line[..eq];
+			value = BString::from(
+				line[eq + 1..]
+					.strip_suffix(b"\n")
+					.unwrap_or(&line[eq + 1..]),
+			);
+		} else {
+			key = line.strip_suffix(b"\n").ok_or_else(|| {
+				Error::new(
+					ErrorKind::UnexpectedEof,
+					format!("field in entry {n_entries} has no value"),
+				)
+			})?;
+
+			let mut size = [0; 8];
+			reader.read_exact(&mut size).await?;
+			let size = u64::from_le_bytes(size);
+			if size > MAX_DECOMPRESSED_SIZE {
+				return Err(Error::new(
+					ErrorKind::InvalidData,
+					format!("field in entry {n_entries} is too large ({size} bytes)"),
+				));
+			}
+
+			let mut bytes = vec![0; size as usize + 1];
+			reader.read_exact(&mut bytes).await?;
+			if bytes.pop() != Some(b'\n') {
+				return Err(Error::new(
+					ErrorKind::InvalidData,
+					format!("binary field in entry {n_entries} isn't followed by a newline"),
+				));
+			}
+			value = BString::from(bytes);
+		}
+
+		let key = String::from_utf8(key.to_vec()).map_err(|err| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!("field name in entry {n_entries} is not UTF-8: {err}"),
+			)
+		})?;
+		match key.as_str() {
+			"__REALTIME_TIMESTAMP" => {
+				let micros = parse(&key, &value, n_entries)?;
+				metadata.realtime = Some(Timestamp::from_microsecond(micros).map_err(|err| {
+					Error::new(
+						ErrorKind::InvalidData,
+						format!("invalid {key} in entry {n_entries}: {err}"),
+					)
+				})?);
+			}
+			"__MONOTONIC_TIMESTAMP" => {
+				metadata.monotonic = Monotonic::new(parse(&key, &value, n_entries)?);
+			}
+			"__SEQNUM" => {
+				metadata.seqnum = NonZeroU64::new(parse(&key, &value, n_entries)?);
+			}
+			"_BOOT_ID" => {
+				let boot_id = std::str::from_utf8(&value)
+					.ok()
+					.and_then(|value| u128::from_str_radix(value, 16).ok())
+					.and_then(NonZeroU128::new);
+				metadata.boot_id = Some(boot_id.ok_or_else(|| {
+					Error::new(
+						ErrorKind::InvalidData,
+						format!("invalid {key} in entry {n_entries}: {value}"),
+					)
+				})?);
+				fields.push((key, value));
+			}
+			key if key.starts_with("__") => {
+				tracing::trace!(?key, "skipping address field");
+			}
+			_ => fields.push((key, value)),
+		}
+	}
+
+	Ok(n_entries)
+}
+
+/// Parse the value of a numeric field.
+fn parse<T: FromStr>(key: &str, value: &[u8], entry: u64) -> Result<T> {
+	std::str::from_utf8(value)
+		.ok()
+		.and_then(|value| value.parse().ok())
+		.ok_or_else(|| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!(
+					"invalid {key} in entry {entry}: {}",
+					String::from_utf8_lossy(value)
+				),
+			)
+		})
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_import_export_stream() {
+	use std::collections::BTreeSet;
+
+	use futures_util::{StreamExt as _, TryStreamExt as _};
+
+	use crate::{
+		export::write_export_field,
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+		writer::CreateOptions,
+	};
+
+	let boot_id = format!("{:032x}", BOOT_ID + 1);
+	let entries: [&[(&str, &[u8])]; 3] = [
+		&[
+			("__CURSOR", b"s=0123;i=7"),
+			("__REALTIME_TIMESTAMP", b"1000"),
+			("__MONOTONIC_TIMESTAMP", b"500"),
+			("__SEQNUM", b"7"),
+			("_BOOT_ID", boot_id.as_bytes()),
+			("MESSAGE", b"hello"),
+			("PRIORITY", b"6"),
+		],
+		&[
+			("__REALTIME_TIMESTAMP", b"2000"),
+			("__MONOTONIC_TIMESTAMP", b"1500"),
+			("__SEQNUM", b"8"),
+			("_BOOT_ID", boot_id.as_bytes()),
+			("MESSAGE", b"two\nlines"),
+			("BINARY", b"nul\0byte"),
+			("PRIORITY", b"6"),
+		],
+		&[
+			("__REALTIME_TIMESTAMP", b"3000"),
+			("__MONOTONIC_TIMESTAMP", b"2500"),
+			("__SEQNUM", b"9"),
+			("_BOOT_ID", boot_id.as_bytes()),
+			("MESSAGE", b"hello"),
+		],
+	];
+	let mut export = Vec::new();
+	for entry in entries {
+		for (key, value) in entry {
+			write_export_field(&mut export, key.as_bytes(), value).unwrap();
+		}
+		export.push(b'\n');
+	}
+	// the last separator is optional
+	export.pop();
+
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system"),
+	);
+	let n = import_export_stream(&export[..], &mut writer)
+		.await
+		.unwrap();
+	assert_eq!(n, 3);
+	writer.close().await.unwrap();
+
+	let mut reader = JournalReader::new(writer.io().try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let read = reader
+		.entries()
+		.map(Result::unwrap)
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(read.len(), 3);
+	for (entry, expected) in read.iter().zip(entries) {
+		assert_eq!(entry.boot_id(), BOOT_ID + 1);
+		let fields = reader
+			.entry_data(entry)
+			.map_ok(|data| (data.key.to_string(), data.value.to_vec()))
+			.try_collect::<BTreeSet<_>>()
+			.await
+			.unwrap();
+		let expected = expected
+			.iter()
+			.filter(|(key, _)| !key.starts_with("__"))
+			.map(|(key, value)| (key.to_string(), value.to_vec()))
+			.collect::<BTreeSet<_>>();
+		assert_eq!(fields, expected);
+	}
+	let headers = read
+		.iter()
+		.map(|entry| {
+			(
+				entry.seqnum().get(),
+				entry.realtime().as_microsecond(),
+				entry.header.monotonic.0.get(),
+			)
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(
+		headers,
+		vec![(7, 1000, 500), (8, 2000, 1500), (9, 3000, 2500)]
+	);
+	assert!(reader.verify_all().await.unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_import_unterminated_field() {
+	use futures_util::{StreamExt as _, TryStreamExt as _};
+
+	use crate::{
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+		writer::CreateOptions,
+	};
+
+	// the stream ends in the middle of the last line, without a newline
+	let export = format!(
+		"__REALTIME_TIMESTAMP=1000\n__MONOTONIC_TIMESTAMP=500\n_BOOT_ID={:032x}\nPRIORITY=6\nMESSAGE=last",
+		BOOT_ID
+	);
+
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system"),
+	);
+	let n = import_export_stream(export.as_bytes(), &mut writer)
+		.await
+		.unwrap();
+	assert_eq!(n, 1);
+	writer.close().await.unwrap();
+
+	let mut reader = JournalReader::new(writer.io().try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	let fields = reader
+		.entry_data(&entry)
+		.map_ok(|data| (data.key.to_string(), data.value.to_string()))
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+	assert!(fields.contains(&("MESSAGE".into(), "last".into())));
+	assert!(fields.contains(&("PRIORITY".into(), "6".into())));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_import_entry_without_fields() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+		writer::CreateOptions,
+	};
+
+	// the first entry only has address fields, so it's not written, and the second has none
+	let export =
+		"__REALTIME_TIMESTAMP=5000\n__MONOTONIC_TIMESTAMP=9000\n__SEQNUM=50\n\nMESSAGE=hello\n";
+
+	let now = Timestamp::from_microsecond(1_700_000_000_000_000).unwrap();
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system"),
+	)
+	.with_clock(move || (now, Monotonic::new(1).unwrap(), BOOT_ID));
+	let n = import_export_stream(export.as_bytes(), &mut writer)
+		.await
+		.unwrap();
+	assert_eq!(n, 1);
+	writer.close().await.unwrap();
+
+	let mut reader = JournalReader::new(writer.io().try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	// the metadata of the skipped entry doesn't carry over
+	assert_eq!(entry.seqnum().get(), 1);
+	assert_eq!(entry.realtime(), now);
+	assert_eq!(entry.header.monotonic, Monotonic::new(1).unwrap());
+	assert_eq!(entry.boot_id(), BOOT_ID);
+}
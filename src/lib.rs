@@ -6,6 +6,21 @@ pub mod reader;
 pub mod writer;
 
 pub mod impls;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod monotonic;
 
+pub mod compact;
+pub mod compression;
+pub mod export;
+pub mod hash;
+pub mod import;
+pub mod record;
+
+pub use compression::supported_compression;
+
 pub(crate) mod deku_helpers;
+
+#[cfg(test)]
+pub(crate) mod testing;
@@ -1,7 +1,7 @@
 use futures_util::StreamExt as _;
 use journald_format::{
 	impls::ReadWholeFile,
-	reader::{JournalReader, Seek},
+	reader::{JournalReader, JournalSelection, Seek},
 };
 use tracing_subscriber::{
 	fmt::format::FmtSpan, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter,
@@ -20,12 +20,9 @@ async fn main() -> std::io::Result<()> {
 
 	let mut reader = JournalReader::new(ReadWholeFile::new("/var/log/journal".into()));
 
-	let system = dbg!(reader.list().await?)
-		.into_iter()
-		.find(|s| s.scope == "system")
-		.unwrap();
-
-	reader.select(system).await?;
+	reader
+		.select(JournalSelection::current_machine("system")?)
+		.await?;
 	reader.seek(Seek::Oldest).await?;
 
 	let mut last = None;
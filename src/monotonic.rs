@@ -7,7 +7,7 @@ use jiff::Timestamp;
 ///
 /// On Linux, the epoch is the start of the system (boot). Corresponds to
 /// [`CLOCK_MONOTONIC`](https://man7.org/linux/man-pages/man2/clock_gettime.2.html).
-#[derive(Debug, Copy, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, DekuRead, DekuWrite)]
 #[deku(endian = "endian", ctx = "endian: deku::ctx::Endian")]
 pub struct Monotonic(pub NonZeroU64);
 
@@ -1,8 +1,6 @@
-use std::io::SeekFrom;
-
 use deku::prelude::*;
 
-use crate::{header::MIN_HEADER_SIZE, reader::AsyncFileRead};
+use crate::reader::AsyncFileRead;
 
 pub use self::data::*;
 pub use self::entry::*;
@@ -35,11 +33,9 @@ pub(crate) trait SimpleRead: for<'a> DekuContainerRead<'a> {
 	where
 		Self: Sized,
 	{
-		debug_assert!(
-			offset >= MIN_HEADER_SIZE as u64,
-			"small seek protection! ({offset})"
-		);
-		io.seek(SeekFrom::Start(offset)).await?;
-		Self::read(io).await
+		let data = io.read_some_at(offset, std::mem::size_of::<Self>()).await?;
+		Self::from_bytes((&data, 0))
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+			.map(|(_, d)| d)
 	}
 }
@@ -1,9 +1,10 @@
-use std::num::NonZeroU64;
+use std::{borrow::Cow, num::NonZeroU64};
 
-use bstr::BString;
+use bstr::{BStr, BString, ByteSlice as _};
 use deku::prelude::*;
 
 use crate::{
+	compression::{decompress, MAX_DECOMPRESSED_SIZE},
 	objects::{DataCompression, ObjectHeader, ObjectType, OBJECT_HEADER_SIZE},
 	reader::AsyncFileRead,
 };
@@ -16,8 +17,15 @@ pub struct DataObjectHeader {
 	pub hash: u64,
 	pub next_hash_offset: u64,
 	pub next_field_offset: u64,
+
+	/// Offset of the first entry which references this object, or 0 if there's none.
 	pub entry_offset: u64,
+
+	/// Offset of the first of the chain of entry arrays listing the other entries which reference
+	/// this object, or 0 if there's none.
 	pub entry_array_offset: u64,
+
+	/// How many entries reference this object, counting the one at `entry_offset`.
 	pub n_entries: u64,
 }
 
@@ -39,18 +47,6 @@ const _: [(); DATA_OBJECT_COMPACT_PAYLOAD_HEADER_SIZE as _] = [(); 8];
 
 impl SimpleRead for DataObjectCompactPayloadHeader {}
 
-#[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
-struct DataPayload {
-	#[deku(
-		until = "|v: &u8| *v == b'='",
-		map = "|mut field: Vec<u8>| -> Result<_, DekuError> { Ok({ field.pop(); field }) }"
-	)]
-	pub key: Vec<u8>,
-
-	#[deku(read_all)]
-	pub value: Vec<u8>,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Data {
 	pub offset: NonZeroU64,
@@ -58,15 +54,69 @@ pub struct Data {
 	pub entry_array: Option<DataObjectCompactPayloadHeader>,
 	pub key: BString,
 	pub value: BString,
+
+	/// How the payload is stored on disk.
+	///
+	/// The `key` and `value` are always decompressed.
+	pub compression: DataCompression,
+
+	/// Size of the payload as stored on disk, which is compressed if `compression` is not `None`.
+	pub compressed_size: u64,
+
+	/// Size of the payload (`key=value`) once decompressed.
+	pub uncompressed_size: u64,
 }
 
 impl Data {
+	/// How many entries reference this object.
+	///
+	/// That's the entry at [`entry_offset`](Self::entry_offset) and those in the chain of arrays at
+	/// [`entry_array_offset`](Self::entry_array_offset).
+	pub fn n_entries(&self) -> u64 {
+		self.header.n_entries
+	}
+
+	/// Offset of the first entry which references this object.
+	///
+	/// This is the oldest entry with this field, which can be read with
+	/// [`JournalReader::read_entry_at`](crate::reader::JournalReader::read_entry_at). It's only
+	/// `None` for objects which aren't referenced by any entry.
+	pub fn entry_offset(&self) -> Option<NonZeroU64> {
+		NonZeroU64::new(self.header.entry_offset)
+	}
+
+	/// Offset of the entry arrays listing the other entries which reference this object.
+	///
+	/// Entries after the [first](Self::entry_offset) are listed in order in a chain of entry
+	/// arrays starting here, separate from the file's main chain, so together they're the
+	/// entries matching this field. It's `None` if only one entry references this object.
+	pub fn entry_array_offset(&self) -> Option<NonZeroU64> {
+		NonZeroU64::new(self.header.entry_array_offset)
+	}
+
 	#[tracing::instrument(level = "trace", skip(io))]
 	pub(crate) async fn read_at<R: AsyncFileRead + Unpin>(
 		io: &mut R,
 		offset: u64,
 		is_compact: bool,
 	) -> std::io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Self::read_at_limited(io, offset, is_compact, None).await
+	}
+
+	/// Read a Data object, erroring if its payload expands more than `max_ratio` times when
+	/// decompressed.
+	///
+	/// See [`ReadOptions::max_expansion_ratio`](crate::reader::ReadOptions::max_expansion_ratio).
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn read_at_limited<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: u64,
+		is_compact: bool,
+		max_ratio: Option<NonZeroU64>,
+	) -> std::io::Result<Self>
 	where
 		Self: Sized,
 	{
@@ -76,49 +126,484 @@ impl Data {
 			.check_type(ObjectType::Data)?;
 		tracing::trace!(?object, "read object header");
 
-		assert_eq!(
-			object.compression,
-			DataCompression::None,
-			"TODO: uncompress"
-		);
+		// read the rest of the object in one go, rather than each header and the payload separately
+		let payload_size = Self::checked_payload_size(offset, &object, is_compact)?;
+		tracing::trace!(?payload_size, "reading data payload");
+		let payload = io
+			.read_some_at(offset + OBJECT_HEADER_SIZE, payload_size as _)
+			.await?;
+		Self::parse(offset, object, &payload, is_compact, max_ratio).map(DataRef::into_owned)
+	}
 
-		let header_offset = offset + OBJECT_HEADER_SIZE;
-		tracing::trace!(offset=?header_offset, "reading data header");
-		let header = DataObjectHeader::read_at(io, header_offset).await?;
-		tracing::trace!(?header, "read data header");
+	/// Read a Data object, borrowing its key and value from the file where possible.
+	///
+	/// If the implementation holds the file in memory (see
+	/// [`AsyncFileRead::contiguous`]) and the payload isn't compressed, the key and value borrow
+	/// from it, without copying. Otherwise this reads an owned [`Data`], as
+	/// [`read_at_limited`](Self::read_at_limited) does.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn read_ref<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: u64,
+		is_compact: bool,
+		max_ratio: Option<NonZeroU64>,
+	) -> std::io::Result<DataRef<'_>> {
+		if io.contiguous().is_none() {
+			return Self::read_at_limited(io, offset, is_compact, max_ratio)
+				.await
+				.map(DataRef::from);
+		}
+
+		let floor = io.read_floor();
+		// UNWRAP: checked above
+		let file = io.contiguous().unwrap();
+		if offset < floor {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("attempted to read into header region ({offset} < {floor})"),
+			));
+		}
+
+		let ceiling = io.read_ceiling();
+		let slice = |start: u64, size: u64| {
+			if start.saturating_add(size) > ceiling {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!("Data:{offset} extends past the end of the arena ({ceiling})"),
+				));
+			}
+			start
+				.checked_add(size)
+				.and_then(|end| file.get(usize::try_from(start).ok()?..usize::try_from(end).ok()?))
+				.ok_or_else(|| {
+					std::io::Error::new(
+						std::io::ErrorKind::UnexpectedEof,
+						format!("Data:{offset} extends past the end of the file"),
+					)
+				})
+		};
+
+		let (_, object) = ObjectHeader::from_bytes((slice(offset, OBJECT_HEADER_SIZE)?, 0))
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		let object = object.check_type(ObjectType::Data)?;
+		let payload_size = Self::checked_payload_size(offset, &object, is_compact)?;
+		let payload = slice(offset + OBJECT_HEADER_SIZE, payload_size)?;
+		Self::parse(offset, object, payload, is_compact, max_ratio)
+	}
+
+	/// Get the size of a Data object past its object header, checking that its headers fit.
+	fn checked_payload_size(
+		offset: u64,
+		object: &ObjectHeader,
+		is_compact: bool,
+	) -> std::io::Result<u64> {
+		let headers_size = DATA_OBJECT_HEADER_SIZE
+			+ if is_compact {
+				DATA_OBJECT_COMPACT_PAYLOAD_HEADER_SIZE
+			} else {
+				0
+			};
+		let payload_size = object.payload_size();
+		if payload_size < headers_size {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"Data:{offset} is too small ({} bytes) for its headers",
+					object.size
+				),
+			));
+		}
+
+		Ok(payload_size)
+	}
+
+	/// Parse a Data object from its bytes past the object header.
+	///
+	/// The key and value borrow from the bytes if the payload isn't compressed.
+	fn parse<'a>(
+		offset: u64,
+		object: ObjectHeader,
+		bytes: &'a [u8],
+		is_compact: bool,
+		max_ratio: Option<NonZeroU64>,
+	) -> std::io::Result<DataRef<'a>> {
+		let parse_error = |err| std::io::Error::new(std::io::ErrorKind::InvalidData, err);
 
-		let (payload_rel_offset, entry_array) = if is_compact {
-			let compact_header_offset = header_offset + DATA_OBJECT_COMPACT_PAYLOAD_HEADER_SIZE;
-			tracing::trace!(offset=?compact_header_offset, "reading compact data header");
-			let compact_header =
-				DataObjectCompactPayloadHeader::read_at(io, compact_header_offset).await?;
+		let ((rest, _), header) = DataObjectHeader::from_bytes((bytes, 0)).map_err(parse_error)?;
+		tracing::trace!(?header, "read data header");
+		let (payload, entry_array) = if is_compact {
+			let ((rest, _), compact_header) =
+				DataObjectCompactPayloadHeader::from_bytes((rest, 0)).map_err(parse_error)?;
 			tracing::trace!(?compact_header, "read compact data header");
-			(
-				OBJECT_HEADER_SIZE
-					+ DATA_OBJECT_HEADER_SIZE
-					+ DATA_OBJECT_COMPACT_PAYLOAD_HEADER_SIZE,
-				Some(compact_header),
-			)
+			(rest, Some(compact_header))
 		} else {
-			(OBJECT_HEADER_SIZE + DATA_OBJECT_HEADER_SIZE, None)
+			(rest, None)
 		};
 
-		let payload_offset = offset + payload_rel_offset;
-		let payload_size = object.payload_size() - payload_rel_offset + OBJECT_HEADER_SIZE;
-		tracing::trace!(offset=?payload_offset, size=?payload_size, "reading payload");
-		let payload = io.read_some_at(payload_offset, payload_size as _).await?;
-		tracing::trace!(?payload, "read payload");
-		let payload = DataPayload::from_bytes((&payload, 0))
-			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-			.map(|(_, d)| d)?;
-		tracing::trace!(?payload, "parsed payload");
-
-		Ok(Self {
+		let payload = Payload::parse(offset, &object.compression, payload, max_ratio)?;
+		Ok(DataRef {
+			// UNWRAP: offsets are past the header, so non-zero
 			offset: offset.try_into().unwrap(),
 			header,
 			entry_array,
-			key: BString::new(payload.key),
-			value: BString::new(payload.value),
+			key: payload.key,
+			value: payload.value,
+			compression: object.compression,
+			compressed_size: payload.compressed_size,
+			uncompressed_size: payload.uncompressed_size,
 		})
 	}
 }
+
+/// A [`Data`] object whose key and value may borrow from the file, from
+/// [`JournalReader::data_ref`](crate::reader::JournalReader::data_ref).
+///
+/// The key and value are borrowed when the payload isn't compressed and the file is held in memory
+/// (see [`AsyncFileRead::contiguous`]), and owned otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRef<'a> {
+	pub offset: NonZeroU64,
+	pub header: DataObjectHeader,
+	pub entry_array: Option<DataObjectCompactPayloadHeader>,
+	pub key: Cow<'a, BStr>,
+	pub value: Cow<'a, BStr>,
+
+	/// How the payload is stored on disk.
+	///
+	/// The `key` and `value` are always decompressed.
+	pub compression: DataCompression,
+
+	/// Size of the payload as stored on disk, which is compressed if `compression` is not `None`.
+	pub compressed_size: u64,
+
+	/// Size of the payload (`key=value`) once decompressed.
+	pub uncompressed_size: u64,
+}
+
+impl DataRef<'_> {
+	/// Whether the key and value borrow from the file.
+	pub fn is_borrowed(&self) -> bool {
+		matches!(
+			(&self.key, &self.value),
+			(Cow::Borrowed(_), Cow::Borrowed(_))
+		)
+	}
+
+	/// Copy the key and value if they're borrowed.
+	pub fn into_owned(self) -> Data {
+		Data {
+			offset: self.offset,
+			header: self.header,
+			entry_array: self.entry_array,
+			key: self.key.into_owned(),
+			value: self.value.into_owned(),
+			compression: self.compression,
+			compressed_size: self.compressed_size,
+			uncompressed_size: self.uncompressed_size,
+		}
+	}
+}
+
+impl From<Data> for DataRef<'static> {
+	fn from(data: Data) -> Self {
+		Self {
+			offset: data.offset,
+			header: data.header,
+			entry_array: data.entry_array,
+			key: Cow::Owned(data.key),
+			value: Cow::Owned(data.value),
+			compression: data.compression,
+			compressed_size: data.compressed_size,
+			uncompressed_size: data.uncompressed_size,
+		}
+	}
+}
+
+/// The key and value of a Data payload, decompressed if needed.
+struct Payload<'a> {
+	key: Cow<'a, BStr>,
+	value: Cow<'a, BStr>,
+	compressed_size: u64,
+	uncompressed_size: u64,
+}
+
+impl<'a> Payload<'a> {
+	/// Split a payload into its key and value, which borrow from it if it isn't compressed.
+	fn parse(
+		offset: u64,
+		compression: &DataCompression,
+		payload: &'a [u8],
+		max_ratio: Option<NonZeroU64>,
+	) -> std::io::Result<Self> {
+		let compressed_size = payload.len() as u64;
+		let missing_separator = || {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("Data:{offset}: data object missing field separator"),
+			)
+		};
+
+		if *compression == DataCompression::None {
+			let (key, value) = payload.split_once_str("=").ok_or_else(missing_separator)?;
+			return Ok(Self {
+				key: Cow::Borrowed(key.as_bstr()),
+				value: Cow::Borrowed(value.as_bstr()),
+				compressed_size,
+				uncompressed_size: compressed_size,
+			});
+		}
+
+		let payload = decompress(compression, payload, MAX_DECOMPRESSED_SIZE, max_ratio)?;
+		let uncompressed_size = payload.len() as u64;
+		tracing::trace!(
+			?compression,
+			?compressed_size,
+			?uncompressed_size,
+			"decompressed payload"
+		);
+		let (key, value) = payload.split_once_str("=").ok_or_else(missing_separator)?;
+		Ok(Self {
+			key: Cow::Owned(key.into()),
+			value: Cow::Owned(value.into()),
+			compressed_size,
+			uncompressed_size,
+		})
+	}
+}
+
+#[cfg(test)]
+fn test_data_object(compression: DataCompression, payload: &[u8], compact: bool) -> Vec<u8> {
+	use crate::header::MAX_HEADER_SIZE;
+
+	let mut object = vec![0; MAX_HEADER_SIZE];
+	let header_size = if compact {
+		OBJECT_HEADER_SIZE + DATA_OBJECT_HEADER_SIZE + DATA_OBJECT_COMPACT_PAYLOAD_HEADER_SIZE
+	} else {
+		OBJECT_HEADER_SIZE + DATA_OBJECT_HEADER_SIZE
+	};
+	object.extend(
+		ObjectHeader {
+			r#type: ObjectType::Data,
+			compression,
+			size: header_size + payload.len() as u64,
+		}
+		.to_bytes()
+		.unwrap(),
+	);
+	object.extend(
+		DataObjectHeader {
+			hash: 0x1234,
+			next_hash_offset: 0,
+			next_field_offset: 0,
+			entry_offset: 0x5678,
+			entry_array_offset: 0,
+			n_entries: 1,
+		}
+		.to_bytes()
+		.unwrap(),
+	);
+	if compact {
+		object.extend(
+			DataObjectCompactPayloadHeader {
+				tail_entry_array_offset: 0x9abc,
+				tail_entry_array_n_entries: 3,
+			}
+			.to_bytes()
+			.unwrap(),
+		);
+	}
+	object.extend(payload);
+	object
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_data_read_uncompressed() {
+	use futures_util::io::Cursor;
+
+	let object = test_data_object(DataCompression::None, b"MESSAGE=hello world", true);
+	let data = Data::read_at(&mut Cursor::new(&object[..]), 272, true)
+		.await
+		.unwrap();
+	assert_eq!(data.key, "MESSAGE");
+	assert_eq!(data.value, "hello world");
+	assert_eq!(data.header.entry_offset, 0x5678);
+	assert_eq!(
+		data.entry_array,
+		Some(DataObjectCompactPayloadHeader {
+			tail_entry_array_offset: 0x9abc,
+			tail_entry_array_n_entries: 3,
+		})
+	);
+	assert_eq!(data.compression, DataCompression::None);
+	assert_eq!(data.compressed_size, 19);
+	assert_eq!(data.uncompressed_size, 19);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_data_read_missing_separator() {
+	use futures_util::io::Cursor;
+
+	for payload in [&b"MESSAGE"[..], b""] {
+		let object = test_data_object(DataCompression::None, payload, false);
+		let err = Data::read_at(&mut Cursor::new(&object[..]), 272, false)
+			.await
+			.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{payload:?}");
+		assert!(
+			err.to_string().contains("missing field separator"),
+			"{payload:?}: {err}"
+		);
+	}
+
+	// an empty value is fine
+	let object = test_data_object(DataCompression::None, b"MESSAGE=", false);
+	let data = Data::read_at(&mut Cursor::new(&object[..]), 272, false)
+		.await
+		.unwrap();
+	assert_eq!(data.key, "MESSAGE");
+	assert_eq!(data.value, "");
+}
+
+#[cfg(all(test, feature = "zstd"))]
+#[tokio::test]
+async fn test_data_read_zstd() {
+	use futures_util::io::Cursor;
+
+	let payload = format!("MESSAGE={}", "hello world ".repeat(100));
+	let compressed = zstd::bulk::compress(payload.as_bytes(), 0).unwrap();
+	let object = test_data_object(DataCompression::Zstd, &compressed, false);
+	let data = Data::read_at(&mut Cursor::new(&object[..]), 272, false)
+		.await
+		.unwrap();
+	assert_eq!(data.key, "MESSAGE");
+	assert_eq!(data.value, "hello world ".repeat(100));
+	assert_eq!(data.entry_array, None);
+	assert_eq!(data.compression, DataCompression::Zstd);
+	assert_eq!(data.compressed_size, compressed.len() as u64);
+	assert_eq!(data.uncompressed_size, payload.len() as u64);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_data_read_ref() {
+	use futures_util::io::Cursor;
+
+	for compact in [true, false] {
+		let object = test_data_object(DataCompression::None, b"MESSAGE=hello=world", compact);
+		let mut io = Cursor::new(&object[..]);
+		let data = Data::read_ref(&mut io, 272, compact, None).await.unwrap();
+		assert!(data.is_borrowed(), "compact {compact}");
+		assert_eq!(data.key.as_ref(), "MESSAGE");
+		assert_eq!(data.value.as_ref(), "hello=world");
+		let owned = Data::read_at(&mut Cursor::new(&object[..]), 272, compact)
+			.await
+			.unwrap();
+		assert_eq!(data.into_owned(), owned, "compact {compact}");
+
+		// truncated
+		let mut io = Cursor::new(&object[..object.len() - 1]);
+		assert_eq!(
+			Data::read_ref(&mut io, 272, compact, None)
+				.await
+				.unwrap_err()
+				.kind(),
+			std::io::ErrorKind::UnexpectedEof
+		);
+	}
+
+	let object = test_data_object(DataCompression::None, b"MESSAGE", false);
+	let mut io = Cursor::new(&object[..]);
+	assert!(Data::read_ref(&mut io, 272, false, None)
+		.await
+		.unwrap_err()
+		.to_string()
+		.contains("missing field separator"));
+	assert_eq!(
+		Data::read_ref(&mut io, 8, false, None)
+			.await
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::InvalidData
+	);
+
+	#[cfg(feature = "zstd")]
+	{
+		let compressed = zstd::bulk::compress(b"MESSAGE=hello world", 0).unwrap();
+		let object = test_data_object(DataCompression::Zstd, &compressed, false);
+		let mut io = Cursor::new(&object[..]);
+		let data = Data::read_ref(&mut io, 272, false, None).await.unwrap();
+		assert!(!data.is_borrowed());
+		assert_eq!(data.value.as_ref(), "hello world");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_data_entry_links() {
+	use crate::{
+		header::Header,
+		objects::EntryArray,
+		reader::{AsyncFileRead as _, FilenameInfo, JournalReader, JournalSelection},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+		writer::{CreateOptions, EntryMetadata, JournalWriter},
+	};
+
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system"),
+	);
+	let mut entries = Vec::new();
+	for n in 1..=4 {
+		let mut fields = vec![("MESSAGE".to_string(), format!("entry {n}").into())];
+		if n != 2 {
+			fields.push(("PRIORITY".to_string(), "6".into()));
+		}
+		entries.push(
+			writer
+				.write_entry_with(EntryMetadata::default(), fields.into_iter())
+				.await
+				.unwrap(),
+		);
+	}
+	writer.close().await.unwrap();
+
+	let mut reader = JournalReader::new(writer.io().try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+
+	let once = reader.find_data(b"MESSAGE=entry 2").await.unwrap().unwrap();
+	assert_eq!(once.n_entries(), 1);
+	assert_eq!(once.entry_offset(), Some(entries[1]));
+	assert_eq!(once.entry_array_offset(), None);
+
+	let priority = reader.find_data(b"PRIORITY=6").await.unwrap().unwrap();
+	assert_eq!(priority.n_entries(), 3);
+	assert_eq!(priority.entry_offset(), Some(entries[0]));
+	let first = reader
+		.read_entry_at(priority.entry_offset().unwrap().get())
+		.await
+		.unwrap();
+	assert_eq!(first.seqnum().get(), 1);
+
+	// the rest are in the data object's own entry arrays
+	let mut io = writer.io().try_clone().unwrap();
+	io.open(&MemoryFiles::make_filename(&FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	}))
+	.await
+	.unwrap();
+	let header = Header::read(&mut io).await.unwrap();
+	let array = EntryArray::read_at(&mut io, priority.entry_array_offset().unwrap(), &header)
+		.await
+		.unwrap();
+	assert_eq!(array.item_at(&mut io, 0).await.unwrap(), Some(entries[2]));
+	assert_eq!(array.item_at(&mut io, 1).await.unwrap(), Some(entries[3]));
+}
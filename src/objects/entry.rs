@@ -1,4 +1,7 @@
-use std::num::{NonZeroU128, NonZeroU32, NonZeroU64};
+use std::{
+	cmp::Ordering,
+	num::{NonZeroU128, NonZeroU32, NonZeroU64},
+};
 
 use deku::prelude::*;
 use futures_util::Stream;
@@ -45,6 +48,23 @@ pub struct EntryObjectRegularItem {
 
 impl SimpleRead for EntryObjectRegularItem {}
 
+/// The identity of an entry, which is the same in every copy of it, from [`Entry::identity`].
+///
+/// Sequence numbers only identify entries within their seqnum domain, and a copy of an entry in
+/// another journal (e.g. one that was exported and imported again, or compacted into a new file)
+/// may be in another domain. The boot, the monotonic time within that boot, and the `xor_hash` of
+/// the entry's fields don't depend on the file, so they identify an entry wherever it is.
+///
+/// This isn't cryptographically unique: the `xor_hash` is a 64-bit hash of the fields, so distinct
+/// entries could in theory collide, but they'd also have to be written at the same microsecond of
+/// the same boot, so it's unique in practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntryIdentity {
+	pub boot_id: NonZeroU128,
+	pub monotonic: Monotonic,
+	pub xor_hash: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Entry {
 	pub offset: NonZeroU64,
@@ -53,6 +73,64 @@ pub struct Entry {
 }
 
 impl Entry {
+	/// The sequence number of the entry, within its file's seqnum domain.
+	pub fn seqnum(&self) -> NonZeroU64 {
+		self.header.seqnum
+	}
+
+	/// The wallclock time at which the entry was created.
+	pub fn realtime(&self) -> Timestamp {
+		self.header.realtime
+	}
+
+	/// The monotonic time of the entry as a timestamp, given the epoch of its boot.
+	///
+	/// See [`Monotonic::to_timestamp`].
+	pub fn monotonic_as(&self, epoch: Timestamp) -> Timestamp {
+		self.header.monotonic.to_timestamp(epoch)
+	}
+
+	/// The ID of the boot the entry was created in.
+	pub fn boot_id(&self) -> u128 {
+		self.header.boot_id.get()
+	}
+
+	/// What identifies the entry regardless of the file it's in.
+	///
+	/// See [`EntryIdentity`].
+	pub fn identity(&self) -> EntryIdentity {
+		EntryIdentity {
+			boot_id: self.header.boot_id,
+			monotonic: self.header.monotonic,
+			xor_hash: self.header.xor_hash,
+		}
+	}
+
+	/// Compare entries by sequence number.
+	///
+	/// This is the order of entries within a file, and across the files of a seqnum domain (see
+	/// [`Header::seqnum_id`]). Sequence numbers of different domains have nothing to do with each
+	/// other, so this is meaningless for entries of different journals, or of files of a journal
+	/// that were written with different seqnum IDs.
+	///
+	/// `Entry` isn't `Ord`, as entries can only be ordered within a domain, and its equality is of
+	/// the whole entry rather than of its sequence number.
+	pub fn cmp_by_seqnum(&self, other: &Self) -> Ordering {
+		self.header.seqnum.cmp(&other.header.seqnum)
+	}
+
+	/// Compare entries by realtime, then sequence number.
+	///
+	/// This is the order entries of several journals are merged in, e.g. by
+	/// [`JournalReader::merged_entries`](crate::reader::JournalReader::merged_entries): wallclock
+	/// times are comparable across journals (as far as their clocks agree), and the sequence
+	/// number only breaks ties. Within a seqnum domain, this is the same as
+	/// [`cmp_by_seqnum`](Self::cmp_by_seqnum) unless the wallclock went backwards.
+	pub fn cmp_by_time(&self, other: &Self) -> Ordering {
+		(self.header.realtime, self.header.seqnum)
+			.cmp(&(other.header.realtime, other.header.seqnum))
+	}
+
 	#[tracing::instrument(level = "trace", skip(io, file_header))]
 	pub(crate) async fn read_at<R: AsyncFileRead + Unpin>(
 		io: &mut R,
@@ -67,38 +145,48 @@ impl Entry {
 			.await?
 			.check_type(ObjectType::Entry)?;
 		tracing::trace!(?object, "read object header");
+		file_header.check_compact_addressable()?;
+
+		// read the rest of the object in one go, rather than the header and each item separately
+		let payload_size = object.payload_size();
+		if payload_size < ENTRY_OBJECT_HEADER_SIZE {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"Entry:{offset} is too small ({} bytes) for its header",
+					object.size
+				),
+			));
+		}
+		tracing::trace!(?payload_size, "reading entry payload");
+		let payload = io
+			.read_some_at(offset + OBJECT_HEADER_SIZE, payload_size as _)
+			.await?;
 
-		tracing::trace!(?offset, "reading entry header");
-		let header_offset = offset + OBJECT_HEADER_SIZE;
-		let header = EntryObjectHeader::read_at(io, header_offset).await?;
+		let (_, header) = EntryObjectHeader::from_bytes((&payload, 0))
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 		tracing::trace!(?header, "read entry header");
 
-		let array_offset = header_offset + ENTRY_OBJECT_HEADER_SIZE;
-		let array_size = object.payload_size() - ENTRY_OBJECT_HEADER_SIZE;
-
-		let size = file_header.sizeof_entry_object_item();
-		let capacity = array_size / size;
-		tracing::trace!(
-			?size,
-			?capacity,
-			?array_offset,
-			?array_size,
-			"initialising object vec"
-		);
-		let mut objects = Vec::with_capacity(capacity as _);
-		for n in 0..capacity {
-			let object_offset = if file_header.is_compact() {
-				let item = EntryObjectCompactItem::read_at(io, array_offset + n * size).await?;
-				item.object_offset
-			} else {
-				let item = EntryObjectRegularItem::read_at(io, array_offset + n * size).await?;
-				u32::try_from(item.object_offset).map_err(|err| {
-					std::io::Error::new(
+		let items = &payload[ENTRY_OBJECT_HEADER_SIZE as usize..];
+		let size = file_header.sizeof_entry_object_item() as usize;
+		tracing::trace!(?size, capacity = items.len() / size, "parsing entry items");
+		let mut objects = Vec::with_capacity(items.len() / size);
+		for (n, item) in items.chunks_exact(size).enumerate() {
+			let object_offset =
+				if file_header.is_compact() {
+					let (_, item) = EntryObjectCompactItem::from_bytes((item, 0))
+						.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+					item.object_offset
+				} else {
+					let (_, item) = EntryObjectRegularItem::from_bytes((item, 0))
+						.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+					u32::try_from(item.object_offset).map_err(|err| {
+						std::io::Error::new(
 						std::io::ErrorKind::InvalidData,
-						format!("object offset of item {n} in EntryArray:{offset} is larger than u32: {err}")
+						format!("object offset of item {n} in Entry:{offset} is larger than u32: {err}")
 					)
-				})?
-			};
+					})?
+				};
 
 			if let Some(object_offset) = NonZeroU32::new(object_offset) {
 				objects.push(object_offset);
@@ -115,20 +203,158 @@ impl Entry {
 		})
 	}
 
+	/// Read the data objects of the entry.
+	///
+	/// Each object is read independently, so the stream continues after an error. Payloads which
+	/// expand more than `max_ratio` times when decompressed are errors.
 	#[tracing::instrument(level = "trace", skip(self, io, file_header))]
 	pub(crate) fn data<'io, R: AsyncFileRead + Unpin>(
 		&'io self,
 		io: &'io mut R,
 		file_header: &'io Header,
+		max_ratio: Option<NonZeroU64>,
 	) -> impl Stream<Item = std::io::Result<Data>> + Unpin + 'io
 	where
 		Self: Sized,
 	{
-		Box::pin(async_stream::try_stream! {
+		Box::pin(async_stream::stream! {
 			let is_compact = file_header.is_compact();
 			for offset in &self.objects {
-				yield Data::read_at(io, offset.get().into(), is_compact).await?;
+				yield Data::read_at_limited(io, offset.get().into(), is_compact, max_ratio).await;
 			}
 		})
 	}
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_read_at_reads() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	for compact in [true, false] {
+		let journal = JournalBuilder::new()
+			.compact(compact)
+			.entry(TestEntry::new(1, 1000).with_field("A=1").with_field("B=2"));
+		let mut io = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+		io.open(&MemoryFiles::make_filename(&journal.latest_name()))
+			.await
+			.unwrap();
+		let header = Header::read(&mut io).await.unwrap();
+
+		io.reads = 0;
+		let entry = Entry::read_at(&mut io, header.tail_entry_offset.unwrap().get(), &header)
+			.await
+			.unwrap();
+		assert_eq!(entry.objects.len(), 3);
+		// one for the object header, one for the rest of the object
+		assert_eq!(io.reads, 2, "compact: {compact}");
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn test_entry_accessors() {
+	let entry = Entry {
+		offset: NonZeroU64::new(8).unwrap(),
+		header: EntryObjectHeader {
+			seqnum: NonZeroU64::new(3).unwrap(),
+			realtime: Timestamp::from_microsecond(5_000_000).unwrap(),
+			monotonic: Monotonic::new(2_000_000).unwrap(),
+			boot_id: NonZeroU128::new(0xb007).unwrap(),
+			xor_hash: 0,
+		},
+		objects: Vec::new(),
+	};
+
+	assert_eq!(entry.seqnum().get(), 3);
+	assert_eq!(entry.realtime().as_microsecond(), 5_000_000);
+	assert_eq!(entry.boot_id(), 0xb007);
+	let epoch = Timestamp::from_microsecond(1_000_000).unwrap();
+	assert_eq!(entry.monotonic_as(epoch).as_microsecond(), 3_000_000);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_ordering() {
+	use futures_util::TryStreamExt as _;
+
+	use crate::{
+		reader::{JournalReader, JournalSelection},
+		testing::{JournalBuilder, MemoryFiles, TestEntry},
+	};
+
+	// the wallclock goes back between the second and third entries
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 3000))
+		.entry(TestEntry::new(3, 2000))
+		.entry(TestEntry::new(4, 2000));
+	let mut reader = JournalReader::new(
+		MemoryFiles::default().with_file(&journal.latest_name(), journal.build()),
+	);
+	reader
+		.select(JournalSelection::from(journal.latest_name()))
+		.await
+		.unwrap();
+	let entries = reader.entries().try_collect::<Vec<_>>().await.unwrap();
+	assert_eq!(entries.len(), 4);
+
+	// iteration order is seqnum order
+	for pair in entries.windows(2) {
+		assert_eq!(pair[0].cmp_by_seqnum(&pair[1]), Ordering::Less);
+	}
+	assert_eq!(entries[0].cmp_by_seqnum(&entries[0]), Ordering::Equal);
+
+	// but not time order, where seqnums only break ties
+	let mut by_time = entries.clone();
+	by_time.sort_by(Entry::cmp_by_time);
+	let seqnums = by_time.iter().map(|e| e.seqnum().get()).collect::<Vec<_>>();
+	assert_eq!(seqnums, [1, 3, 4, 2]);
+	assert_eq!(entries[2].cmp_by_time(&entries[3]), Ordering::Less);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_identity() {
+	use futures_util::TryStreamExt as _;
+
+	use crate::{
+		reader::{JournalReader, JournalSelection},
+		testing::{JournalBuilder, MemoryFiles, TestEntry},
+	};
+
+	let entry = || TestEntry::new(7, 1000).with_field("PRIORITY=6");
+	let original = JournalBuilder::new().entry(entry());
+	// a copy in another seqnum domain, where it has another seqnum and is at another offset
+	let copy = JournalBuilder::new()
+		.seqnum_id(0x5eb6a35d3a5d4c5f8fb7e0c7a4d6b8e1)
+		.compact(false)
+		.entry(TestEntry::new(1, 500).with_field("OTHER=1"))
+		.entry(TestEntry {
+			seqnum: 2,
+			..entry()
+		});
+
+	let mut entries = Vec::new();
+	for journal in [original, copy] {
+		let mut reader = JournalReader::new(
+			MemoryFiles::default().with_file(&journal.latest_name(), journal.build()),
+		);
+		reader
+			.select(JournalSelection::from(journal.latest_name()))
+			.await
+			.unwrap();
+		entries.push(reader.entries().try_collect::<Vec<_>>().await.unwrap());
+	}
+
+	let (original, copy) = (&entries[0][0], &entries[1][1]);
+	assert_ne!(original.seqnum(), copy.seqnum());
+	assert_ne!(original.offset, copy.offset);
+	assert_eq!(original.identity(), copy.identity());
+	assert_ne!(entries[1][0].identity(), copy.identity());
+
+	// the fields are part of the identity
+	let mut other = copy.clone();
+	other.header.xor_hash ^= 1;
+	assert_ne!(other.identity(), copy.identity());
+}
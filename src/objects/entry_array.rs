@@ -2,8 +2,14 @@ use std::num::NonZeroU64;
 
 use deku::prelude::*;
 
-use super::SimpleRead;
+use crate::{header::Header, reader::AsyncFileRead};
 
+use super::{ObjectHeader, ObjectType, SimpleRead, OBJECT_HEADER_SIZE};
+
+/// The header of an Entry Array object, before its items.
+///
+/// Unlike the items, the offset of the next array is 64-bit in compact files too: systemd's
+/// `EntryArrayObject` only has a union over the items.
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
 #[deku(endian = "little")]
 pub struct EntryArrayObjectHeader {
@@ -31,3 +37,184 @@ pub struct EntryArrayCompactItem {
 }
 
 impl SimpleRead for EntryArrayCompactItem {}
+
+/// An entry array in a file, for reading its items.
+///
+/// Items are the offsets of entries, in order. Arrays are filled from the start, so the first
+/// empty (zero) item marks the end of the entries in an array, and only the last array of a chain
+/// is expected to be partially filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryArray {
+	/// Offset of the entry array object.
+	pub offset: NonZeroU64,
+
+	/// Offset of the next entry array in the chain.
+	pub next_offset: Option<NonZeroU64>,
+
+	capacity: u64,
+	compact: bool,
+}
+
+impl EntryArray {
+	/// Read the entry array at an offset.
+	#[tracing::instrument(level = "trace", skip(io, file_header))]
+	pub(crate) async fn read_at<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: NonZeroU64,
+		file_header: &Header,
+	) -> std::io::Result<Self> {
+		file_header.check_compact_addressable()?;
+		Self::read(io, offset, file_header.is_compact()).await
+	}
+
+	async fn read<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: NonZeroU64,
+		compact: bool,
+	) -> std::io::Result<Self> {
+		let object = ObjectHeader::read_at(io, offset.get())
+			.await?
+			.check_type(ObjectType::EntryArray)?;
+		let header = EntryArrayObjectHeader::read_at(io, offset.get() + OBJECT_HEADER_SIZE).await?;
+
+		// items are 4 bytes in compact files and 8 otherwise, so a leftover means the flag is wrong
+		let items_size = object.payload_size() - ENTRY_ARRAY_HEADER_SIZE;
+		if !items_size.is_multiple_of(item_size(compact)) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"EntryArray:{offset} has {items_size} bytes of items, which doesn't fit {}-byte items of a {} file",
+					item_size(compact),
+					if compact { "compact" } else { "regular" },
+				),
+			));
+		}
+
+		Ok(Self {
+			offset,
+			next_offset: header.next_entry_array_offset,
+			capacity: items_size / item_size(compact),
+			compact,
+		})
+	}
+
+	/// The number of items the array has room for, filled or not.
+	pub fn capacity(&self) -> u64 {
+		self.capacity
+	}
+
+	/// Read the entry offset in an item.
+	///
+	/// Returns `None` if the item is empty. Errors with `InvalidInput` if the index is past the
+	/// capacity.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn item_at<R: AsyncFileRead + Unpin>(
+		&self,
+		io: &mut R,
+		index: u64,
+	) -> std::io::Result<Option<NonZeroU64>> {
+		if index >= self.capacity {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!(
+					"index {index} is out of bounds of EntryArray:{} (capacity {})",
+					self.offset, self.capacity
+				),
+			));
+		}
+
+		let item_offset = self.offset.get()
+			+ OBJECT_HEADER_SIZE
+			+ ENTRY_ARRAY_HEADER_SIZE
+			+ index * item_size(self.compact);
+		let offset = if self.compact {
+			EntryArrayCompactItem::read_at(io, item_offset)
+				.await?
+				.offset
+				.into()
+		} else {
+			EntryArrayRegularItem::read_at(io, item_offset)
+				.await?
+				.offset
+		};
+		Ok(NonZeroU64::new(offset))
+	}
+
+	/// The number of filled items.
+	///
+	/// This does a binary search for the first empty item.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn len<R: AsyncFileRead + Unpin>(&self, io: &mut R) -> std::io::Result<u64> {
+		let (mut low, mut high) = (0, self.capacity);
+		while low < high {
+			let mid = low + (high - low) / 2;
+			if self.item_at(io, mid).await?.is_none() {
+				high = mid;
+			} else {
+				low = mid + 1;
+			}
+		}
+
+		Ok(low)
+	}
+
+	/// Read the next entry array in the chain, if there's one.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn next<R: AsyncFileRead + Unpin>(
+		&self,
+		io: &mut R,
+	) -> std::io::Result<Option<Self>> {
+		match self.next_offset {
+			Some(next) => Self::read(io, next, self.compact).await.map(Some),
+			None => Ok(None),
+		}
+	}
+}
+
+fn item_size(compact: bool) -> u64 {
+	if compact {
+		std::mem::size_of::<EntryArrayCompactItem>() as _
+	} else {
+		std::mem::size_of::<EntryArrayRegularItem>() as _
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_array_chain() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	for compact in [true, false] {
+		let journal = JournalBuilder::new()
+			.compact(compact)
+			.array_capacity(2)
+			.entry(TestEntry::new(1, 1000))
+			.entry(TestEntry::new(2, 2000))
+			.entry(TestEntry::new(3, 3000));
+		let mut io = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+		io.open(&MemoryFiles::make_filename(&journal.latest_name()))
+			.await
+			.unwrap();
+		let header = Header::read(&mut io).await.unwrap();
+
+		let first = EntryArray::read_at(&mut io, header.entry_array_offset, &header)
+			.await
+			.unwrap();
+		assert_eq!(first.capacity(), 2, "compact: {compact}");
+		assert_eq!(first.len(&mut io).await.unwrap(), 2);
+		assert!(first.item_at(&mut io, 0).await.unwrap().is_some());
+		assert_eq!(
+			first.item_at(&mut io, 2).await.unwrap_err().kind(),
+			std::io::ErrorKind::InvalidInput
+		);
+
+		let second = first.next(&mut io).await.unwrap().unwrap();
+		assert_eq!(second.len(&mut io).await.unwrap(), 1);
+		assert_eq!(second.item_at(&mut io, 1).await.unwrap(), None);
+		assert_eq!(
+			second.item_at(&mut io, 0).await.unwrap(),
+			header.tail_entry_offset
+		);
+		assert_eq!(second.next(&mut io).await.unwrap(), None);
+	}
+}
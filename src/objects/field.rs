@@ -1,5 +1,15 @@
+use std::num::NonZeroU64;
+
+use bstr::BString;
 use deku::prelude::*;
 
+use crate::{
+	objects::{DataCompression, ObjectHeader, ObjectType, OBJECT_HEADER_SIZE},
+	reader::AsyncFileRead,
+};
+
+use super::SimpleRead;
+
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
 #[deku(endian = "little")]
 pub struct FieldObjectHeader {
@@ -7,3 +17,118 @@ pub struct FieldObjectHeader {
 	pub next_hash_offset: u64,
 	pub next_data_offset: u64,
 }
+
+pub const FIELD_OBJECT_HEADER_SIZE: u64 = std::mem::size_of::<FieldObjectHeader>() as _;
+const _: [(); FIELD_OBJECT_HEADER_SIZE as _] = [(); 24];
+
+impl SimpleRead for FieldObjectHeader {}
+
+/// A Field object: the name of a field, e.g. `MESSAGE`, which links to the Data objects of its
+/// values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+	pub offset: NonZeroU64,
+	pub header: FieldObjectHeader,
+	pub name: BString,
+}
+
+impl Field {
+	/// Read a Field object.
+	///
+	/// The name is the rest of the object's payload after the header. Unlike Data, Field objects
+	/// are never compressed, so one which says it is errors with `InvalidData`.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn read_at<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: u64,
+	) -> std::io::Result<Self> {
+		tracing::trace!(?offset, "reading object header");
+		let object = ObjectHeader::read_at(io, offset)
+			.await?
+			.check_type(ObjectType::Field)?;
+		tracing::trace!(?object, "read object header");
+		if object.compression != DataCompression::None {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"Field:{offset} is compressed ({:?}), but only Data objects can be",
+					object.compression
+				),
+			));
+		}
+
+		let header_offset = offset + OBJECT_HEADER_SIZE;
+		let header = FieldObjectHeader::read_at(io, header_offset).await?;
+		tracing::trace!(?header, "read field header");
+
+		// check_type() checks that the object is large enough for its header
+		let name_size = object.payload_size() - FIELD_OBJECT_HEADER_SIZE;
+		let name = io
+			.read_some_at(header_offset + FIELD_OBJECT_HEADER_SIZE, name_size as _)
+			.await?;
+
+		Ok(Self {
+			// UNWRAP: offsets are past the header, so non-zero
+			offset: offset.try_into().unwrap(),
+			header,
+			name: name.into(),
+		})
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_field_read_at() {
+	use futures_util::io::Cursor;
+
+	use crate::header::MAX_HEADER_SIZE;
+
+	let object = |compression: DataCompression, name: &[u8]| {
+		let mut object = vec![0; MAX_HEADER_SIZE];
+		object.extend(
+			ObjectHeader {
+				r#type: ObjectType::Field,
+				compression,
+				size: OBJECT_HEADER_SIZE + FIELD_OBJECT_HEADER_SIZE + name.len() as u64,
+			}
+			.to_bytes()
+			.unwrap(),
+		);
+		object.extend(
+			FieldObjectHeader {
+				hash: 0x1234,
+				next_hash_offset: 0,
+				next_data_offset: 0x5678,
+			}
+			.to_bytes()
+			.unwrap(),
+		);
+		object.extend(name);
+		// padding to the next object, which isn't part of the name
+		object.resize(object.len().next_multiple_of(8), 0);
+		object
+	};
+
+	let bytes = object(DataCompression::None, b"MESSAGE");
+	let field = Field::read_at(&mut Cursor::new(&bytes[..]), 272)
+		.await
+		.unwrap();
+	assert_eq!(field.offset.get(), 272);
+	assert_eq!(field.name, "MESSAGE");
+	assert_eq!(field.header.hash, 0x1234);
+	assert_eq!(field.header.next_data_offset, 0x5678);
+
+	let bytes = object(DataCompression::Zstd, b"MESSAGE");
+	let err = Field::read_at(&mut Cursor::new(&bytes[..]), 272)
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	// a Data object isn't a Field
+	let mut bytes = object(DataCompression::None, b"MESSAGE");
+	bytes[272] = 1;
+	let err = Field::read_at(&mut Cursor::new(&bytes[..]), 272)
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -1,6 +1,16 @@
+use std::num::NonZeroU64;
+
 use deku::prelude::*;
 
-use super::SimpleRead;
+use crate::{
+	header::{Header, IncompatibleFlag},
+	reader::AsyncFileRead,
+};
+
+use super::{
+	SimpleRead, TagObjectHeader, DATA_OBJECT_HEADER_SIZE, ENTRY_ARRAY_HEADER_SIZE,
+	ENTRY_OBJECT_HEADER_SIZE, FIELD_OBJECT_HEADER_SIZE,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
 #[deku(id_type = "u8", endian = "endian", ctx = "endian: deku::ctx::Endian")]
@@ -42,11 +52,30 @@ pub enum ObjectType {
 	Unknown(u8),
 }
 
+impl ObjectType {
+	/// The size of the fixed part of objects of this type, including the object header.
+	///
+	/// Objects smaller than this are corrupt. This is a lower bound: Data objects in compact files
+	/// have a larger fixed part.
+	pub const fn min_size(&self) -> u64 {
+		OBJECT_HEADER_SIZE
+			+ match self {
+				Self::Data => DATA_OBJECT_HEADER_SIZE,
+				Self::Field => FIELD_OBJECT_HEADER_SIZE,
+				Self::Entry => ENTRY_OBJECT_HEADER_SIZE,
+				Self::EntryArray => ENTRY_ARRAY_HEADER_SIZE,
+				Self::Tag => std::mem::size_of::<TagObjectHeader>() as _,
+				Self::DataHashTable | Self::FieldHashTable | Self::Unknown(_) => 0,
+			}
+	}
+}
+
 /// Compression algorithm used for a Data object.
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
 #[deku(id_type = "u8", endian = "endian", ctx = "endian: deku::ctx::Endian")]
 #[repr(u8)]
 #[rustfmt::skip]
+#[allow(clippy::unusual_byte_groupings)]
 pub enum DataCompression {
 	/// No compression.
 	None = 0b000,
@@ -61,6 +90,20 @@ pub enum DataCompression {
 	Zstd = 0b100,
 }
 
+impl DataCompression {
+	/// The header flag a file must have to contain objects with this compression.
+	///
+	/// Returns `None` for uncompressed objects.
+	pub fn incompatible_flag(&self) -> Option<IncompatibleFlag> {
+		match self {
+			Self::None => None,
+			Self::Xz => Some(IncompatibleFlag::CompressedXz),
+			Self::Lz4 => Some(IncompatibleFlag::CompressedLz4),
+			Self::Zstd => Some(IncompatibleFlag::CompressedZstd),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
 #[deku(endian = "little")]
 pub struct ObjectHeader {
@@ -81,18 +124,109 @@ const _: [(); OBJECT_HEADER_SIZE as _] = [(); 16];
 impl SimpleRead for ObjectHeader {}
 
 impl ObjectHeader {
+	/// Parse the header at the start of an object's bytes.
+	///
+	/// Only the first [`OBJECT_HEADER_SIZE`] bytes are looked at. The object's size must at least
+	/// cover its header, but isn't otherwise checked: use [`check_type`](Self::check_type) for that.
+	pub fn parse(bytes: &[u8]) -> std::io::Result<Self> {
+		let head = bytes.get(..OBJECT_HEADER_SIZE as usize).ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::UnexpectedEof,
+				format!(
+					"object header is too short ({} bytes, expected {OBJECT_HEADER_SIZE})",
+					bytes.len()
+				),
+			)
+		})?;
+		let (_, object) = Self::from_bytes((head, 0))
+			.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+		if object.size < OBJECT_HEADER_SIZE {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("object is too small ({} bytes) for its header", object.size),
+			));
+		}
+
+		Ok(object)
+	}
+
 	pub const fn payload_size(&self) -> u64 {
 		self.size.saturating_sub(OBJECT_HEADER_SIZE as _)
 	}
 
+	/// Check that the object is of the expected type, and large enough for that type.
+	///
+	/// The size is checked so that computing sizes from [`payload_size`](Self::payload_size)
+	/// doesn't silently come out as zero or underflow for corrupt objects.
 	pub fn check_type(self, check: ObjectType) -> std::io::Result<Self> {
 		if self.r#type != check {
 			Err(std::io::Error::new(
 				std::io::ErrorKind::InvalidData,
 				format!("expected object of type {check:?}, found {:?}", self.r#type),
 			))
+		} else if self.size < check.min_size() {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"{check:?} object is too small ({} bytes, expected at least {})",
+					self.size,
+					check.min_size()
+				),
+			))
 		} else {
 			Ok(self)
 		}
 	}
 }
+
+/// Walks the objects of a file in order, from the start of the arena to the tail object.
+///
+/// Objects are laid out back to back, each aligned to 8 bytes, so this only reads object headers.
+#[derive(Debug, Clone)]
+pub(crate) struct ObjectWalker {
+	next: Option<u64>,
+	tail: u64,
+}
+
+impl ObjectWalker {
+	pub fn new(file_header: &Header) -> Self {
+		Self {
+			next: Some(file_header.header_size.get()),
+			tail: file_header.tail_object_offset.get(),
+		}
+	}
+
+	/// Read the header of the next object, and its offset.
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub async fn next<R: AsyncFileRead + Unpin>(
+		&mut self,
+		io: &mut R,
+	) -> std::io::Result<Option<(NonZeroU64, ObjectHeader)>> {
+		let Some(offset) = self.next.and_then(NonZeroU64::new) else {
+			return Ok(None);
+		};
+		if offset.get() > self.tail {
+			self.next = None;
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("walked past the tail object at {} to {offset}", self.tail),
+			));
+		}
+
+		let object = ObjectHeader::read_at(io, offset.get()).await?;
+		if object.size < OBJECT_HEADER_SIZE {
+			self.next = None;
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"object at {offset} is too small ({} bytes) for its header",
+					object.size
+				),
+			));
+		}
+
+		self.next = (offset.get() != self.tail)
+			.then(|| offset.get().saturating_add(object.size).next_multiple_of(8));
+		Ok(Some((offset, object)))
+	}
+}
@@ -2,6 +2,10 @@ use std::num::NonZeroU64;
 
 use deku::prelude::*;
 
+use crate::reader::AsyncFileRead;
+
+use super::{ObjectHeader, ObjectType, SimpleRead, OBJECT_HEADER_SIZE};
+
 pub const TAG_LENGTH: u64 = 256 / 8;
 
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite)]
@@ -11,3 +15,28 @@ pub struct TagObjectHeader {
 	pub epoch: u64,
 	pub tag: [u8; TAG_LENGTH as _],
 }
+
+impl SimpleRead for TagObjectHeader {}
+
+/// A Tag object, which seals the file up to it with Forward Secure Sealing.
+///
+/// This is only the structure of the seal: the tag itself isn't verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+	pub offset: NonZeroU64,
+	pub header: TagObjectHeader,
+}
+
+impl Tag {
+	#[tracing::instrument(level = "trace", skip(io))]
+	pub(crate) async fn read_at<R: AsyncFileRead + Unpin>(
+		io: &mut R,
+		offset: NonZeroU64,
+	) -> std::io::Result<Self> {
+		ObjectHeader::read_at(io, offset.get())
+			.await?
+			.check_type(ObjectType::Tag)?;
+		let header = TagObjectHeader::read_at(io, offset.get() + OBJECT_HEADER_SIZE).await?;
+		Ok(Self { offset, header })
+	}
+}
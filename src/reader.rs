@@ -1,21 +1,39 @@
 use std::{
-	collections::{BTreeSet, HashSet},
-	num::NonZeroU64,
-	path::PathBuf,
+	cmp::Reverse,
+	collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque},
+	num::{NonZeroU128, NonZeroU32, NonZeroU64},
+	ops::{Bound, RangeBounds},
+	path::{Path, PathBuf},
 };
 
-pub use file_read::{AsyncFileRead, FilenameInfo};
-use futures_util::{Stream, StreamExt as _};
+use bstr::BString;
+#[cfg(test)]
+pub(crate) use file_read::sleep;
+pub use file_read::{
+	namespaced_filename, namespaced_prefix, parse_namespaced_filename, AsyncFileRead, FilenameInfo,
+};
+use futures_util::{stream::FuturesUnordered, Stream, StreamExt as _, TryStreamExt as _};
+use jiff::Timestamp;
+pub use options::ReadOptions;
+use prefetch::Prefetch;
 
 use crate::{
-	header::Header,
+	compression::{check_supported, supported_compression},
+	hash::jenkins_hash64,
+	header::{CompatibleFlag, Header, State},
+	monotonic::Monotonic,
 	objects::{
-		Data, Entry, EntryArrayCompactItem, EntryArrayObjectHeader, EntryArrayRegularItem,
-		ObjectHeader, ObjectType, SimpleRead, ENTRY_ARRAY_HEADER_SIZE, OBJECT_HEADER_SIZE,
+		Data, DataObjectHeader, DataRef, Entry, EntryArray, EntryObjectHeader, FieldObjectHeader,
+		ObjectHeader, ObjectType, ObjectWalker, SimpleRead, Tag, FIELD_OBJECT_HEADER_SIZE,
+		OBJECT_HEADER_SIZE,
 	},
+	record::LogRecord,
+	tables::{ChainWalk, HASH_ITEM_SIZE},
 };
 
 mod file_read;
+mod options;
+mod prefetch;
 
 // pub(crate) const READ_SIZE: usize = 4096;
 
@@ -25,6 +43,121 @@ pub struct JournalSelection {
 	pub scope: String,
 }
 
+impl JournalSelection {
+	/// Select a journal by machine ID as text, as it's written in `/etc/machine-id`.
+	///
+	/// The machine ID is 32 hexadecimal digits, or a UUID with dashes, and surrounding whitespace
+	/// is ignored. Errors with `InvalidInput` if it's malformed or all zeros.
+	pub fn from_machine_id_str(
+		machine_id: &str,
+		scope: impl Into<String>,
+	) -> std::io::Result<Self> {
+		Ok(Self {
+			machine_id: parse_machine_id(machine_id)?,
+			scope: scope.into(),
+		})
+	}
+
+	/// Select a journal of the machine this runs on, from `/etc/machine-id`.
+	#[cfg(target_os = "linux")]
+	pub fn current_machine(scope: impl Into<String>) -> std::io::Result<Self> {
+		Self::from_machine_id_file(Path::new("/etc/machine-id"), scope)
+	}
+
+	#[cfg(target_os = "linux")]
+	fn from_machine_id_file(path: &Path, scope: impl Into<String>) -> std::io::Result<Self> {
+		let machine_id = std::fs::read_to_string(path)?;
+		Self::from_machine_id_str(&machine_id, scope).map_err(|err| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("{}: {err}", path.display()),
+			)
+		})
+	}
+
+	/// Parse the scope of the journal.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn as_scope(&self) -> Scope {
+		Scope::from(self.scope.as_str())
+	}
+}
+
+/// Parse a 128-bit ID written as 32 hexadecimal digits or as a UUID.
+fn parse_machine_id(machine_id: &str) -> std::io::Result<u128> {
+	let trimmed = machine_id.trim();
+	let digits = match trimmed.len() {
+		32 => trimmed.to_string(),
+		36 if [8, 13, 18, 23]
+			.iter()
+			.all(|at| trimmed.as_bytes()[*at] == b'-') =>
+		{
+			trimmed.replace('-', "")
+		}
+		_ => String::new(),
+	};
+
+	// from_str_radix accepts a leading sign, so check the digits first
+	let id = (digits.len() == 32 && digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+		.then(|| u128::from_str_radix(&digits, 16).ok())
+		.flatten()
+		.filter(|id| *id != 0);
+	id.ok_or_else(|| {
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			format!("invalid machine ID {trimmed:?}, expected 32 hexadecimal digits"),
+		)
+	})
+}
+
+/// The scope of a journal, parsed from [`JournalSelection::scope`].
+///
+/// This is how systemd splits journals: `system` for the system-wide journal, and `user-$UID` for
+/// each user's journal (`journalctl --system` and `--user`). Journals in a namespace
+/// (`journalctl --namespace`) are kept in their own directories by systemd, and have their scope
+/// suffixed with `.$NAMESPACE` when listed by implementations which opt into that layout (see
+/// [`namespaced_filename`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Scope {
+	/// The system journal.
+	System,
+
+	/// A user's journal, by UID.
+	User(u32),
+
+	/// The system journal of a namespace.
+	Namespace(String),
+
+	/// Any other scope, as-is.
+	Other(String),
+}
+
+impl From<&str> for Scope {
+	fn from(scope: &str) -> Self {
+		match scope.split_once('.') {
+			Some(("system", namespace)) if !namespace.is_empty() => {
+				Self::Namespace(namespace.into())
+			}
+			Some(_) => Self::Other(scope.into()),
+			None if scope == "system" => Self::System,
+			None => scope
+				.strip_prefix("user-")
+				.and_then(|uid| uid.parse().ok())
+				.map_or_else(|| Self::Other(scope.into()), Self::User),
+		}
+	}
+}
+
+impl std::fmt::Display for Scope {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::System => write!(f, "system"),
+			Self::User(uid) => write!(f, "user-{uid}"),
+			Self::Namespace(namespace) => write!(f, "system.{namespace}"),
+			Self::Other(scope) => write!(f, "{scope}"),
+		}
+	}
+}
+
 impl From<FilenameInfo> for JournalSelection {
 	fn from(info: FilenameInfo) -> Self {
 		match info {
@@ -38,8 +171,13 @@ impl From<FilenameInfo> for JournalSelection {
 
 #[derive(Debug)]
 struct CurrentFile {
+	/// Path of the file, to detect when another one was opened without being loaded.
+	path: Option<PathBuf>,
 	header: Header,
 	position: Position,
+
+	/// Seqnum of the last entry read from this file, if any.
+	last_seqnum: Option<NonZeroU64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -49,39 +187,60 @@ struct Position {
 	// Some(n) is "next read will be n", None is "next read will be the chained array"
 }
 
-impl CurrentFile {
-	/// Get the offset of the current item in the current entry array.
-	///
-	/// None if position.index is None
-	#[tracing::instrument(level = "trace", skip(self))]
-	fn entry_index_and_offset(&self) -> Option<(u64, u64)> {
-		tracing::trace!(?self.position, "position");
-		self.position
-			.index
-			.map(|index| {
-				(
-					index,
-					self.position.entry_array_offset.get()
-						+ OBJECT_HEADER_SIZE as u64
-						+ ENTRY_ARRAY_HEADER_SIZE as u64
-						+ index * self.header.sizeof_entry_array_item(),
-				)
-			})
-			.inspect(|(_, offset)| tracing::trace!(?offset, "calculated entry array item offset"))
-	}
-}
-
 pub struct JournalReader<T> {
 	io: T,
+	data_io: Option<T>,
+	options: ReadOptions,
 	select: Option<JournalSelection>,
 	current: Option<CurrentFile>,
+	epochs: HashMap<u128, Timestamp>,
+	max_chain_depth: u64,
+
+	/// Where the last entry read is, cleared by seeking.
+	location: Option<EntryLocation>,
+
+	/// The file opened with [`open_file`](Self::open_file), if reading a single file.
+	single_file: Option<PathBuf>,
+
+	/// Gaps found between files by [`entries`](Self::entries), until taken.
+	gaps: Vec<SeqnumGap>,
+
+	/// Handlers for objects of custom types, by type id.
+	object_handlers: HashMap<u8, ObjectHandler>,
+
+	/// Handler for the progress of scans, see [`set_progress_handler`](Self::set_progress_handler).
+	progress_handler: Option<ProgressHandler>,
+
+	/// Only read entries with a `PRIORITY` up to this, see [`set_max_priority`](Self::set_max_priority).
+	max_priority: Option<u8>,
+
+	/// Offsets of the Data objects of the priorities allowed by `max_priority`, by the file ID and
+	/// tail object offset of the file they were found in.
+	priority_data: Option<((u128, NonZeroU64), HashSet<u64>)>,
 }
 
+/// A handler for objects of a custom type, see [`JournalReader::set_object_handler`].
+///
+/// It's called with the offset of the object, its header, and its payload (what follows the
+/// header). An error stops the walk of the file, and is returned from it.
+pub type ObjectHandler =
+	Box<dyn FnMut(NonZeroU64, &ObjectHeader, &[u8]) -> std::io::Result<()> + Send>;
+
+/// A handler for the progress of scans, see [`JournalReader::set_progress_handler`].
+///
+/// Files may be scanned concurrently, so it may be called for several files in turn.
+pub type ProgressHandler = Box<dyn Fn(&Progress) + Send + Sync>;
+
 impl<T> std::fmt::Debug for JournalReader<T> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("JournalReader")
 			.field("io", &std::any::type_name::<T>())
+			.field("options", &self.options)
 			.field("select", &self.select)
+			.field(
+				"object_handlers",
+				&self.object_handlers.keys().collect::<BTreeSet<_>>(),
+			)
 			.finish()
 	}
 }
@@ -92,13 +251,89 @@ where
 {
 	/// Initialize a new journal reader.
 	pub fn new(io: T) -> Self {
+		Self::with_options(io, ReadOptions::default())
+	}
+
+	/// Initialize a new journal reader with custom options.
+	pub fn with_options(io: T, options: ReadOptions) -> Self {
 		Self {
 			io,
+			data_io: None,
+			options,
 			select: None,
 			current: None,
+			epochs: HashMap::new(),
+			max_chain_depth: 0,
+			location: None,
+			single_file: None,
+			gaps: Vec::new(),
+			object_handlers: HashMap::new(),
+			progress_handler: None,
+			max_priority: None,
+			priority_data: None,
 		}
 	}
 
+	/// Only read entries with a syslog priority up to a level.
+	///
+	/// This is like `journalctl --priority`: [`entries`](Self::entries) and the streams built on
+	/// it skip entries which don't have a `PRIORITY` field with a value between 0 (`emerg`) and the
+	/// level, which is capped to 7 (`debug`). Entries without a `PRIORITY` are skipped too.
+	///
+	/// The Data objects of the allowed priorities are looked up in the hash table of each file
+	/// read, so entries are matched by the offsets they reference, without reading their data.
+	pub fn set_max_priority(&mut self, level: u8) {
+		self.max_priority = Some(level.min(7));
+		self.priority_data = None;
+	}
+
+	/// Read entries of all priorities again, see [`set_max_priority`](Self::set_max_priority).
+	pub fn clear_max_priority(&mut self) {
+		self.max_priority = None;
+		self.priority_data = None;
+	}
+
+	/// Handle objects of a custom type when walking the objects of a file.
+	///
+	/// Objects of types which this library doesn't know are skipped. With a handler registered
+	/// for their type id, it's called for each such object found when walking a file, by
+	/// [`walk_objects`](Self::walk_objects) or [`tags`](Self::tags). This is for custom journal
+	/// schemes which add their own objects. Known types are never passed to handlers.
+	///
+	/// Returns the handler previously registered for the type, if any.
+	pub fn set_object_handler(
+		&mut self,
+		type_id: u8,
+		handler: impl FnMut(NonZeroU64, &ObjectHeader, &[u8]) -> std::io::Result<()> + Send + 'static,
+	) -> Option<ObjectHandler> {
+		self.object_handlers.insert(type_id, Box::new(handler))
+	}
+
+	/// Stop handling objects of a custom type, returning the handler.
+	pub fn remove_object_handler(&mut self, type_id: u8) -> Option<ObjectHandler> {
+		self.object_handlers.remove(&type_id)
+	}
+
+	/// Report the progress of scans which read whole files.
+	///
+	/// [`verify_all_report`](Self::verify_all_report) (and so [`verify_all`](Self::verify_all))
+	/// and [`walk_objects`](Self::walk_objects) can take a long time on large journals. With a
+	/// handler, they call it as they go through each file, e.g. to show a progress bar. See
+	/// [`Progress`] for what's reported.
+	///
+	/// Returns the handler previously registered, if any.
+	pub fn set_progress_handler(
+		&mut self,
+		handler: impl Fn(&Progress) + Send + Sync + 'static,
+	) -> Option<ProgressHandler> {
+		self.progress_handler.replace(Box::new(handler))
+	}
+
+	/// Stop reporting progress, returning the handler.
+	pub fn remove_progress_handler(&mut self) -> Option<ProgressHandler> {
+		self.progress_handler.take()
+	}
+
 	/// List all available journals (machine ID, scope).
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub async fn list(&self) -> std::io::Result<HashSet<JournalSelection>> {
@@ -117,6 +352,102 @@ where
 		self.select.as_ref()
 	}
 
+	/// The header of the currently loaded file.
+	///
+	/// Returns `None` if no file is loaded yet.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn current_header(&self) -> Option<&Header> {
+		self.current.as_ref().map(|current| &current.header)
+	}
+
+	/// The name of the currently open file.
+	///
+	/// Returns `None` if no file is open, or if its name can't be parsed.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn current_file(&self) -> Option<FilenameInfo> {
+		self.io.current().and_then(T::parse_filename)
+	}
+
+	/// Whether reading has reached the latest file of the journal.
+	///
+	/// [`entries`](Self::entries) reads the archived files of the journal first, and then moves on
+	/// to the latest file, which is the one still being written to. Once this is true, reading has
+	/// caught up with the archive, and the entries from there on are live: this is when to switch
+	/// to [`follow`](Self::follow) for new entries, for example.
+	///
+	/// Returns `false` if no file is loaded yet, e.g. just after [`select`](Self::select).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn reading_live(&self) -> bool {
+		self.current.is_some() && self.current_file().is_some_and(|file| file.is_latest())
+	}
+
+	/// Whether the currently loaded file is marked online.
+	///
+	/// A file is online while a writer has it open; if it is online and nothing is writing to it,
+	/// its writer likely crashed and the tail of the file may be incompletely written.
+	///
+	/// Returns `None` if no file is loaded yet.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn is_dirty(&self) -> Option<bool> {
+		self.current
+			.as_ref()
+			.map(|current| current.header.state == State::Online)
+	}
+
+	/// The sequence number domain of the currently loaded file.
+	///
+	/// Sequence numbers of entries are only comparable between files with the same domain.
+	///
+	/// Returns `None` if no file is loaded yet.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn current_seqnum_id(&self) -> Option<NonZeroU128> {
+		self.current
+			.as_ref()
+			.map(|current| current.header.seqnum_id)
+	}
+
+	/// Where the last entry read from [`entries`](Self::entries) is.
+	///
+	/// This is the entry the reader is "on": the next entry read will be the one after it. Pass it
+	/// to [`seek`](Self::seek) (as a [`Seek::Absolute`]) to come back to it later.
+	///
+	/// Returns `None` if no entry was read since the last seek or selection.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn position(&self) -> Option<&EntryLocation> {
+		self.location.as_ref()
+	}
+
+	/// Where an entry of the current file is.
+	///
+	/// The entry must have been read from the current file, e.g. the entry just yielded by
+	/// [`entries`](Self::entries) or one from [`read_entry_at`](Self::read_entry_at); this isn't
+	/// checked.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self, entry))]
+	pub fn location_of(&self, entry: &Entry) -> std::io::Result<EntryLocation> {
+		let current = self.current.as_ref().ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::NotConnected,
+				"no journal file open, select a journal first",
+			)
+		})?;
+		let file = current.path.as_deref().and_then(T::parse_filename);
+		Ok(EntryLocation::new(entry, current.header.seqnum_id, file))
+	}
+
+	/// Take the gaps in sequence numbers found so far while reading.
+	///
+	/// When [`entries`](Self::entries) moves on to the next file of the journal, it checks that
+	/// the file starts right after the last entry of the previous one. If it doesn't, entries are
+	/// missing (e.g. an archived file was deleted), which is recorded here and logged as a warning.
+	///
+	/// Gaps are kept until taken, or until another journal is selected.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn take_gaps(&mut self) -> Vec<SeqnumGap> {
+		std::mem::take(&mut self.gaps)
+	}
+
 	/// Select a journal to read from.
 	///
 	/// If the journal does not exist, this will return an error and will also have unselected the
@@ -126,8 +457,12 @@ where
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub async fn select(&mut self, journal: JournalSelection) -> std::io::Result<()> {
 		self.io.close().await;
+		self.data_io = None;
 		self.select = None;
 		self.current = None;
+		self.location = None;
+		self.single_file = None;
+		self.gaps.clear();
 
 		let latest = T::make_filename(&FilenameInfo::Latest {
 			machine_id: journal.machine_id,
@@ -157,23 +492,110 @@ where
 		Ok(())
 	}
 
+	/// Read a single journal file, like `journalctl --file`.
+	///
+	/// The path is passed to [`AsyncFileRead::open`] as-is, so it's relative to wherever the
+	/// implementation reads from (absolute paths also work with the on-disk implementations). The
+	/// file doesn't have to follow the file scheme.
+	///
+	/// The selection is derived from the file: the machine ID from its header, and the scope from
+	/// its name. Unlike with [`select`](Self::select), other files of that journal aren't read:
+	/// [`entries`](Self::entries) stops at the end of this file, and seeking stays within it.
+	///
+	/// This invalidates the current position.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn open_file(&mut self, path: &Path) -> std::io::Result<()> {
+		self.io.close().await;
+		self.data_io = None;
+		self.select = None;
+		self.current = None;
+		self.location = None;
+		self.single_file = None;
+		self.gaps.clear();
+
+		self.io.open(path).await?;
+		self.load().await?;
+
+		// UNWRAP: load() sets current
+		let machine_id = self.current.as_ref().unwrap().header.machine_id;
+		let scope = match T::parse_filename(path) {
+			Some(file) => JournalSelection::from(file).scope,
+			None => path
+				.file_name()
+				.and_then(|name| name.to_str())
+				.and_then(|name| name.split(['@', '.']).next())
+				.unwrap_or_default()
+				.to_string(),
+		};
+
+		self.select = Some(JournalSelection { machine_id, scope });
+		self.single_file = Some(path.to_path_buf());
+		Ok(())
+	}
+
+	/// Load the current open file with a header that was already read, instead of reading it.
+	///
+	/// This is for when the header is already at hand, e.g. when processing many files whose
+	/// headers were read beforehand, and as a hook for testing the reader with synthetic headers.
+	/// The position is set to the first entry of the file, as when a file is loaded normally.
+	///
+	/// The header is trusted to be the file's: apart from the sizes that offset computations rely
+	/// on and the compact layout, which are validated from the header alone, nothing is checked
+	/// against the file, and reading with a header which doesn't match will give wrong results or
+	/// errors. In debug builds only, the file is checked to start with the journal file signature.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self, header))]
+	pub async fn load_with_header(&mut self, header: Header) -> std::io::Result<()> {
+		if self.io.current().is_none() {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::NotConnected,
+				"no journal file open, select a journal first",
+			));
+		}
+
+		header.validate()?;
+		header.check_compact_layout()?;
+		check_supported(header.incompatible_flags, supported_compression())?;
+		#[cfg(debug_assertions)]
+		{
+			let mut signature = [0; 8];
+			self.io.seek(std::io::SeekFrom::Start(0)).await?;
+			self.io.read_exact(&mut signature).await?;
+			debug_assert_eq!(
+				&signature,
+				crate::header::SIGNATURE,
+				"loading a header for a file which isn't a journal"
+			);
+		}
+
+		self.io.set_read_floor(header.header_size.get());
+		self.location = None;
+		self.load_header(header);
+		Ok(())
+	}
+
 	/// Seek to a position in the journal.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub async fn seek(&mut self, seek: Seek) -> std::io::Result<()> {
-		let (selected, prefix) = self.selected_journal()?;
+		self.location = None;
+		if let Some(path) = self.single_file.clone() {
+			return self.seek_single_file(&path, seek).await;
+		}
+
+		let (selected, _) = self.selected_journal()?;
 
 		match seek {
 			Seek::Oldest => {
 				let oldest = self
-					.io
-					.list_files_sorted(Some(&prefix))
+					.selected_files()
+					.await?
+					.into_iter()
 					.next()
-					.await
 					.ok_or_else(|| {
 						std::io::Error::new(std::io::ErrorKind::NotFound, "no files found")
-					})??;
-				self.io.open(&T::make_filename(&oldest)).await?;
-				self.load().await?;
+					})?;
+				self.open_and_load(&oldest).await?;
 				Ok(())
 			}
 			Seek::Newest => {
@@ -186,7 +608,17 @@ where
 				self.skip_to_end().await?;
 				Ok(())
 			}
-			_ => todo!(),
+			Seek::Timestamp(realtime) => self.seek_closest(realtime, SeekKey::Realtime).await,
+			Seek::At(time) => {
+				self.seek_closest(at_realtime(time), SeekKey::Realtime)
+					.await
+			}
+			Seek::Seqnum(seqnum) => self.seek_closest(seqnum, SeekKey::Seqnum).await,
+			Seek::Absolute { seqnum_id, seqnum } => self.seek_absolute(seqnum_id, seqnum).await,
+			Seek::Fraction(fraction) => self.seek_fraction(fraction).await,
+			Seek::Offset(offset) => self.seek_offset_in_current(offset).await,
+			Seek::BootId(boot_id) => self.seek_boot(boot_id).await,
+			Seek::Entries(n) => Err(entries_unsupported(n)),
 		}
 	}
 
@@ -196,52 +628,76 @@ where
 	///
 	/// If there's nothing to read, return an empty stream.
 	///
-	/// Updates the [`Position`] of the reader as it goes.
+	/// Updates the [`position`](Self::position) of the reader as it goes. The position is moved
+	/// past an entry once it's read and before it's yielded, so the stream can be dropped at any
+	/// point (e.g. to read the entry's data with [`entry_data`](Self::entry_data)) and a new one
+	/// will resume with the next entry that wasn't yielded.
+	///
+	/// If an entry can't be read, the stream ends with the error. If the entry is corrupt (the
+	/// error is `InvalidData`), the position is moved past it, so a new stream skips it and goes on
+	/// with the next one; on other errors a new stream tries that entry again. Either way,
+	/// [`position`](Self::position) is still the last entry that was read.
 	#[tracing::instrument(level = "debug", skip(self))]
 	pub fn entries(&mut self) -> impl Stream<Item = std::io::Result<Entry>> + Unpin + '_ {
 		Box::pin(async_stream::try_stream! {
 			self.load_if_needed().await?;
 
-			let mut current_seqnum = None;
-
 			loop { // files
+				self.load_priority_data().await?;
 				loop { // entry arrays
 					let current = self.current.as_mut().unwrap();
-					let array_object = ObjectHeader::read_at(&mut self.io, current.position.entry_array_offset.get())
-						.await?
-						.check_type(ObjectType::EntryArray)?;
+					let array = EntryArray::read_at(&mut self.io, current.position.entry_array_offset, &current.header).await?;
+					let array_size = array.capacity();
+					tracing::trace!(?array, "entry array");
 
-					let payload_size = array_object.payload_size() - ENTRY_ARRAY_HEADER_SIZE as u64;
-					let array_size = payload_size / current.header.sizeof_entry_array_item();
-					tracing::trace!(?payload_size, ?array_size, "entry array calculations");
-
-					while let Some((entry_index, array_offset)) = current.entry_index_and_offset() {
-						let entry_offset = if current.header.is_compact() {
-							u64::from(EntryArrayCompactItem::read_at(&mut self.io, array_offset).await?.offset)
-						} else {
-							EntryArrayRegularItem::read_at(&mut self.io, array_offset).await?.offset
-						};
-						tracing::trace!(?entry_offset, "got entry offset");
-						if entry_offset == 0 {
+					while let Some(entry_index) = current.position.index {
+						let Some(entry_offset) = array.item_at(&mut self.io, entry_index).await? else {
 							tracing::trace!("bumping to next entry array (zero)");
-							// we're at the end of the entry array
-							current.position.index = None;
+							// we're at the end of the entries of the array: stay on the empty
+							// item, in case it's filled later on (see follow())
 							break;
-						}
+						};
+						tracing::trace!(?entry_offset, "got entry offset");
 
-						let entry = Entry::read_at(&mut self.io, entry_offset, &current.header).await?;
-						current_seqnum = Some(entry.header.seqnum);
-						yield entry;
-						if entry_index + 1 < array_size {
+						let next_index = if entry_index + 1 < array_size {
 							tracing::trace!(?entry_index, ?array_size, "bumping to next array entry");
-							*(current.position.index.as_mut().unwrap()) += 1;
-							continue;
+							Some(entry_index + 1)
 						} else {
 							tracing::trace!(?entry_index, ?array_size, "bumping to next entry array (bounds)");
 							// we're at the end of the entry array
-							current.position.index = None;
-							break;
+							None
+						};
+
+						// advance only once the entry is read, so that a stream dropped while
+						// reading it doesn't lose it, and before yielding it, so the position is
+						// right if the stream is dropped (or data is read) while it's paused on it
+						let entry = match Entry::read_at(&mut self.io, entry_offset.get(), &current.header).await {
+							Ok(entry) => entry,
+							Err(err) => {
+								// a corrupt entry won't read any better next time, so a new stream
+								// goes on past it; other errors may be transient, so it's retried
+								if err.kind() == std::io::ErrorKind::InvalidData {
+									current.position.index = next_index;
+								}
+								Err(err)?
+							}
+						};
+						current.position.index = next_index;
+						current.last_seqnum = Some(entry.header.seqnum);
+
+						if let Some((_, allowed)) = &self.priority_data {
+							if !entry.objects.iter().any(|offset| allowed.contains(&u64::from(offset.get()))) {
+								tracing::trace!(?entry_offset, "skipping entry of another priority");
+								continue;
+							}
 						}
+
+						self.location = Some(EntryLocation::new(
+							&entry,
+							current.header.seqnum_id,
+							self.io.current().and_then(T::parse_filename),
+						));
+						yield entry;
 					}
 
 					// we're at the end of the entry array, either from the above loop, or because index was already None
@@ -251,30 +707,41 @@ where
 					}
 				}
 
-				if let Some(seqnum) = current_seqnum {
+				if self.single_file.is_some() {
+					tracing::debug!("reading a single file, we're done");
+					break;
+				}
+
+				// UNWRAP: we've loaded the file above or when switching files
+				let current = self.current.as_ref().unwrap();
+				if let Some(seqnum) = current.last_seqnum.or(current.header.tail_entry_seqnum) {
+					let seqnum_id = current.header.seqnum_id;
 					let (selected, prefix) = self.selected_journal()?;
 
+					// seqnums are only comparable within a seqnum_id domain
 					if let Some(next_file) = self.io.list_files(Some(&prefix)).filter_map(|file| async move { match file {
-						Ok(file @ FilenameInfo::Archived { head_seqnum, .. }) if head_seqnum > seqnum => Some(file)
+						Ok(file @ FilenameInfo::Archived { file_seqnum, head_seqnum, .. }) if file_seqnum == seqnum_id && head_seqnum > seqnum => Some(file)
 						, _ => None
 					} }).collect::<BTreeSet<_>>().await.first() {
 						self.io.open(&T::make_filename(next_file)).await?;
 						self.load().await?;
+						self.check_seqnum_gap(seqnum_id, seqnum);
 						continue;
 					}
 
-					let current_file_is_archived = self.io.current().and_then(|path| T::parse_filename(path)).map_or(false, |file| file.is_archived());
+					let current_file_is_archived = self.io.current().and_then(|path| T::parse_filename(path)).is_some_and(|file| file.is_archived());
 					if current_file_is_archived {
 						tracing::debug!("moving on to the current/latest file");
 						self.io.open(&T::make_filename(&FilenameInfo::Latest { machine_id: selected.machine_id, scope: selected.scope.clone() })).await?;
 						self.load().await?;
+						self.check_seqnum_gap(seqnum_id, seqnum);
 						continue;
 					}
 
 					tracing::debug!("no next file, we're done");
 					break;
 				} else {
-					// we iterated no entries, so we're probably at the end?
+					// the file has no entries, so we're probably at the end?
 					tracing::debug!("no more entries probably");
 					break;
 				}
@@ -282,126 +749,5674 @@ where
 		})
 	}
 
-	/// Read the data of an entry.
+	/// Read the last entries of the selected journal, oldest first.
 	///
-	/// Panics if a file isn't loaded.
-	#[tracing::instrument(level = "trace", skip(self))]
-	pub fn entry_data<'e>(
-		&'e mut self,
-		entry: &'e Entry,
-	) -> impl Stream<Item = std::io::Result<Data>> + Unpin + '_ {
-		let CurrentFile { header, .. } = self
-			.current
-			.as_ref()
-			.expect("tried to read entry without a loaded file");
-		entry.data(&mut self.io, header)
-	}
-
-	/// Verify all data in all available journals.
+	/// This is like `journalctl --lines`: it returns the newest `n` entries, or all of them if
+	/// there are fewer. The reader is positioned by the counts of entries in the file headers (as
+	/// for [`Seek::Fraction`]), so only the entries returned are read. Afterwards, the position
+	/// is after the newest entry, so [`follow`](Self::follow) goes on with new entries as
+	/// `journalctl --lines --follow` does.
 	///
-	/// This will check every hash, every sealing tag, and every entry. It
-	/// should be used to detect tampering; when reading the journal normally,
-	/// only the data that is actually read is verified.
-	#[tracing::instrument(level = "trace", skip(self))]
-	pub async fn verify_all(&mut self) -> std::io::Result<bool> {
-		todo!()
-	}
-
-	// == Internal ==
+	/// Entries which don't match the priority filter (see
+	/// [`set_max_priority`](Self::set_max_priority)) are skipped among the last `n`, rather than
+	/// looking further back for more.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn tail(&mut self, n: usize) -> std::io::Result<Vec<Entry>> {
+		self.location = None;
+		let back = |total: u64| total.saturating_sub(n as u64);
+		if let Some(path) = self.single_file.clone() {
+			self.io.open(&path).await?;
+			self.load().await?;
+			// UNWRAP: load() sets current
+			let total = self.current.as_ref().unwrap().header.n_entries;
+			if total == 0 || n == 0 {
+				self.skip_to_end().await?;
+			} else {
+				self.seek_index_in_current(back(total)).await?;
+			}
+		} else if n == 0 {
+			self.seek(Seek::Newest).await?;
+		} else {
+			self.seek_index(back).await?;
+		}
 
-	/// Get the selected journal and its prefix, failing if no journal is selected.
-	#[tracing::instrument(level = "trace", skip(self))]
-	fn selected_journal(&self) -> std::io::Result<(&JournalSelection, PathBuf)> {
-		self.select
-			.as_ref()
-			.ok_or_else(|| {
-				std::io::Error::new(std::io::ErrorKind::NotConnected, "no journal selected")
-			})
-			.map(|j| (j, T::make_prefix(j)))
+		// the header counts may be behind, so keep the last n of what's there
+		let mut tail = VecDeque::with_capacity(n);
+		let mut entries = self.entries();
+		while let Some(entry) = entries.next().await {
+			if tail.len() == n {
+				tail.pop_front();
+			}
+			tail.push_back(entry?);
+		}
+		Ok(tail.into())
 	}
 
-	/// Load the header and base structures of the current open file into memory.
+	/// Read entries from the current position, reading ahead of the consumer.
 	///
-	/// Also set the position to the first entry.
-	#[tracing::instrument(level = "trace", skip(self))]
-	async fn load(&mut self) -> std::io::Result<()> {
-		let header = Header::read(&mut self.io).await?;
-		let position = Position {
-			entry_array_offset: header.entry_array_offset,
-			index: Some(0),
-		};
-		self.current = Some(CurrentFile { header, position });
-		Ok(())
+	/// This yields the same entries as [`entries`](Self::entries), in the same order, but reads
+	/// up to [`prefetch`](ReadOptions::prefetch) entries past the one being processed whenever
+	/// it's polled, and yields a read entry rather than wait for the next read. Reads are only
+	/// driven while the stream is polled, so this overlaps reading with the consumer's work only
+	/// when the implementation's IO runs in the background, like tokio's file IO. Errors are
+	/// yielded after the entries before them, and end the stream.
+	///
+	/// Entries which were read ahead are lost if the stream is dropped: the reader's position is
+	/// then after the last entry read, not the last one yielded.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub fn prefetched_entries(
+		&mut self,
+	) -> impl Stream<Item = std::io::Result<Entry>> + Unpin + '_ {
+		let ahead = self.options.prefetch;
+		Prefetch::new(self.entries(), ahead)
 	}
 
-	/// load() only if needed.
+	/// Read entries from the current position, and then wait for new ones.
 	///
-	/// You can unwrap self.current after calling this.
-	#[tracing::instrument(level = "trace", skip(self))]
-	async fn load_if_needed(&mut self) -> std::io::Result<()> {
-		if self.current.is_none() {
-			self.load().await?;
-		}
+	/// This is like `journalctl --follow`: once it reaches the end of the journal, this waits
+	/// with [`AsyncFileRead::wait_for_change`] and re-reads the header of the current file to see
+	/// if entries were added. Waits get longer while nothing new comes in, between the
+	/// [`follow_min_interval`](ReadOptions::follow_min_interval) and the
+	/// [`follow_max_interval`](ReadOptions::follow_max_interval) options, and go back to the
+	/// shortest when there are new entries. Seek to [`Seek::Newest`] first to only get new
+	/// entries.
+	///
+	/// If the current file is replaced by another (e.g. the journal was rotated), following
+	/// continues from the start of the new file. The stream never ends on its own.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub fn follow(&mut self) -> impl Stream<Item = std::io::Result<Entry>> + Unpin + '_ {
+		let min = self.options.follow_min_interval;
+		let max = self.options.follow_max_interval.max(min);
+		Box::pin(async_stream::try_stream! {
+			let mut interval = min;
+			loop {
+				let mut found = false;
+				{
+					// a new entries() stream resumes where the last one left off
+					let mut entries = self.entries();
+					while let Some(entry) = entries.next().await {
+						found = true;
+						yield entry?;
+					}
+				}
 
-		Ok(())
+				if found {
+					interval = min;
+				}
+				tracing::trace!(?interval, "waiting for the journal to change");
+				self.io.wait_for_change(interval).await?;
+				interval = (interval * 2).min(max);
+				self.refresh().await?;
+			}
+		})
 	}
 
-	/// Jump to the next entry array, at index 0.
+	/// Read entries within a range of realtime timestamps (microseconds since the Unix epoch).
 	///
-	/// If we're already at the end, does nothing and returns false.
-	#[tracing::instrument(level = "trace", skip(self))]
-	async fn next_entry_array(&mut self) -> std::io::Result<bool> {
-		self.load_if_needed().await?;
-		let current = self.current.as_mut().unwrap();
+	/// This seeks to the start of the range (or the oldest entry if it's unbounded), and stops at
+	/// the first entry after the end of the range instead of reading to the end of the journal. As
+	/// that entry is read to know to stop, the reader's position is left after it.
+	#[tracing::instrument(level = "debug", skip(self, range))]
+	pub fn entries_between(
+		&mut self,
+		range: impl RangeBounds<u64>,
+	) -> impl Stream<Item = std::io::Result<Entry>> + Unpin + '_ {
+		let start = range.start_bound().cloned();
+		let end = range.end_bound().cloned();
+		tracing::debug!(?start, ?end, "range");
 
-		// just checking that we're in the right place
-		ObjectHeader::read_at(&mut self.io, current.position.entry_array_offset.get())
-			.await?
-			.check_type(ObjectType::EntryArray)?;
+		Box::pin(async_stream::try_stream! {
+			match start {
+				Bound::Included(start) | Bound::Excluded(start) => {
+					self.seek(Seek::Timestamp(start)).await?;
+				}
+				Bound::Unbounded => self.seek(Seek::Oldest).await?,
+			}
 
-		let entry_array = EntryArrayObjectHeader::read_at(
-			&mut self.io,
-			current.position.entry_array_offset.get() + OBJECT_HEADER_SIZE as u64,
-		)
-		.await?;
-		if let Some(next) = entry_array.next_entry_array_offset {
-			current.position.entry_array_offset = next;
-			current.position.index = Some(0);
-			Ok(true)
-		} else {
-			Ok(false)
-		}
-	}
+			let mut entries = self.entries();
+			while let Some(entry) = entries.next().await {
+				let entry = entry?;
+				let realtime = u64::try_from(entry.header.realtime.as_microsecond()).unwrap_or(0);
 
-	/// Follow the chain of primary entry arrays until the last, and set position.
-	#[tracing::instrument(level = "trace", skip(self))]
-	async fn skip_to_end(&mut self) -> std::io::Result<()> {
-		while self.next_entry_array().await? {}
+				// seeking goes to the closest entry, which may be before the start
+				let after_start = match start {
+					Bound::Included(start) => realtime >= start,
+					Bound::Excluded(start) => realtime > start,
+					Bound::Unbounded => true,
+				};
+				if !after_start {
+					continue;
+				}
 
-		// UNWRAP: next_entry_array() depends on current being Some()
-		self.current.as_mut().unwrap().position.index = None;
+				let before_end = match end {
+					Bound::Included(end) => realtime <= end,
+					Bound::Excluded(end) => realtime < end,
+					Bound::Unbounded => true,
+				};
+				if !before_end {
+					tracing::debug!(?realtime, "reached the end of the range");
+					break;
+				}
 
-		Ok(())
-	}
+				yield entry;
+			}
+		})
+	}
+
+	/// Read the entries of one boot.
+	///
+	/// This is like `journalctl --boot`: it seeks to the first entry of the boot (see
+	/// [`Seek::BootId`]), and stops at the first entry of another boot instead of reading to the
+	/// end of the journal. A boot's entries may span several files, which are read through as
+	/// usual. If there are no entries of the boot, the stream is empty.
+	///
+	/// As the entry of the next boot is read to know to stop, the reader's position is left after
+	/// it.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub fn entries_for_boot(
+		&mut self,
+		boot_id: u128,
+	) -> impl Stream<Item = std::io::Result<Entry>> + Unpin + '_ {
+		Box::pin(async_stream::try_stream! {
+			let found = match self.seek(Seek::BootId(boot_id)).await {
+				Ok(()) => true,
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => false,
+				Err(err) => Err(err)?,
+			};
+
+			if found {
+				let mut entries = self.entries();
+				while let Some(entry) = entries.next().await {
+					let entry = entry?;
+					if entry.header.boot_id.get() != boot_id {
+						tracing::debug!(boot_id = ?entry.header.boot_id, "reached another boot");
+						break;
+					}
+
+					yield entry;
+				}
+			}
+		})
+	}
+
+	/// Read entries from several journals at once, merged into one stream.
+	///
+	/// This is like `journalctl --merge`: each selected journal is read from its oldest entry, and
+	/// entries are yielded in order of their realtime timestamp, then their sequence number. As
+	/// journals have independent sequence number domains, the seqnum only breaks ties, and entries
+	/// which are still tied are yielded in the order of the selections.
+	///
+	/// Each journal is read through its own handle from [`AsyncFileRead::try_clone`], so this
+	/// returns an error if the implementation doesn't support cloning. The reader's own selection
+	/// and position are left untouched, and so entries from this stream can't be passed to
+	/// [`entry_data`](Self::entry_data) unless their journal is selected and seeked to separately.
+	///
+	/// If [`dedup_merged`](ReadOptions::dedup_merged) is enabled, entries which are in several of
+	/// the journals are only yielded once, as the first of their copies in that order: the copy
+	/// with the lowest seqnum, or from the earliest selection if several have the same seqnum.
+	///
+	/// The journals' handles count against [`max_open_files`](ReadOptions::max_open_files), along
+	/// with the reader's main handle. When there are more journals than that, their files are
+	/// closed between reads and reopened when they're next read from. Errors with `InvalidInput`
+	/// if the reader's main handle already takes the only file allowed.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub fn merged_entries(
+		&mut self,
+		selections: &[JournalSelection],
+	) -> impl Stream<Item = std::io::Result<(JournalSelection, Entry)>> + Unpin + '_ {
+		let selections = selections.to_vec();
+		let dedup = self.options.dedup_merged;
+		Box::pin(async_stream::try_stream! {
+			// the data handle reopens its file when it's next needed
+			if let Some(data_io) = &mut self.data_io {
+				data_io.close().await;
+			}
+			let limit = self
+				.options
+				.max_open_files
+				.get()
+				.saturating_sub(usize::from(self.io.current().is_some()));
+			if limit == 0 {
+				Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidInput,
+					"no room to open the merged journals' files besides the reader's own, raise max_open_files",
+				))?;
+			}
+
+			// one sub-reader per journal, with the next entry it has to offer
+			let mut heads: Vec<(JournalSelection, Self, Entry, NonZeroU128)> =
+				Vec::with_capacity(selections.len());
+			for selection in selections {
+				Self::close_merge_heads(heads.iter_mut().map(|(_, reader, _, _)| reader), limit).await;
+				let mut reader = Self::with_options(self.io.try_clone()?, self.options.clone());
+				reader.max_priority = self.max_priority;
+				reader.select(selection.clone()).await?;
+				reader.seek(Seek::Oldest).await?;
+				let first = reader.entries().next().await.transpose()?;
+				if let Some(entry) = first {
+					let seqnum_id = merge_seqnum_id(&reader)?;
+					heads.push((selection, reader, entry, seqnum_id));
+				}
+			}
+
+			// identical entries have the same realtime, so they all come up in the merge before it
+			// moves past that time: only the entries yielded there need remembering
+			let mut yielded_at = None;
+			let mut yielded = HashSet::new();
+			let mut yielded_identities = HashSet::new();
+
+			// heads stay in the order of the selections, and min_by() picks the first of equal
+			// entries, so ties go to the earliest selection
+			while let Some(index) = heads
+				.iter()
+				.enumerate()
+				.min_by(|(_, (_, _, a, _)), (_, (_, _, b, _))| a.cmp_by_time(b))
+				.map(|(index, _)| index)
+			{
+				Self::close_merge_heads(
+					heads
+						.iter_mut()
+						.enumerate()
+						.filter(|(other, _)| *other != index)
+						.map(|(_, (_, reader, _, _))| reader),
+					limit,
+				)
+				.await;
+				let (selection, reader, entry, seqnum_id) = &mut heads[index];
+				let next = reader.entries().next().await.transpose()?;
+				let (selection, entry, seqnum_id) = if let Some(next) = next {
+					let next_seqnum_id = merge_seqnum_id(reader)?;
+					let entry = std::mem::replace(entry, next);
+					let seqnum_id = std::mem::replace(seqnum_id, next_seqnum_id);
+					(selection.clone(), entry, seqnum_id)
+				} else {
+					let (selection, _, entry, seqnum_id) = heads.remove(index);
+					tracing::debug!(?selection, "journal exhausted");
+					(selection, entry, seqnum_id)
+				};
+
+				if dedup {
+					let at = entry.header.realtime;
+					if yielded_at != Some(at) {
+						yielded_at = Some(at);
+						yielded.clear();
+						yielded_identities.clear();
+					}
+					// copies in another seqnum domain are only recognisable by their identity
+					let identity = entry.identity();
+					if !yielded.insert((seqnum_id, entry.header.seqnum))
+						| !yielded_identities.insert(identity)
+					{
+						tracing::trace!(?selection, seqnum = ?entry.header.seqnum, ?identity, "skipping duplicate entry");
+						continue;
+					}
+				}
+
+				yield (selection, entry);
+			}
+		})
+	}
+
+	/// Iterate over entries from the current position, with some of their fields.
+	///
+	/// This is like [`entries`](Self::entries), but also resolves the values of the requested
+	/// fields of each entry, keyed by field name. The entry's Data objects are read in order
+	/// until all of the fields are found, so this is much cheaper than reading all of the data
+	/// when only a few fields are needed. Fields the entry doesn't have are absent from the map;
+	/// if a field appears more than once in an entry, its first value is kept.
+	///
+	/// Data objects are read as with [`entry_data`](Self::entry_data), including skipping those
+	/// which can't be read if the reader is [`tolerant`](ReadOptions::tolerant).
+	#[tracing::instrument(level = "debug", skip(self, fields))]
+	pub fn entries_with_fields(
+		&mut self,
+		fields: &[&[u8]],
+	) -> impl Stream<Item = std::io::Result<(Entry, BTreeMap<BString, BString>)>> + Unpin + '_ {
+		let wanted = fields
+			.iter()
+			.map(|field| BString::from(*field))
+			.collect::<BTreeSet<_>>();
+
+		Box::pin(async_stream::try_stream! {
+			loop {
+				// a new entries() stream resumes where the last one left off
+				let entry = self.entries().next().await;
+				let Some(entry) = entry else {
+					break;
+				};
+				let entry = entry?;
+
+				let mut values = BTreeMap::new();
+				if !wanted.is_empty() {
+					let mut data = self.entry_data(&entry);
+					while let Some(datum) = data.next().await {
+						let datum = datum?;
+						if wanted.contains(&datum.key) && !values.contains_key(&datum.key) {
+							values.insert(datum.key, datum.value);
+							if values.len() == wanted.len() {
+								break;
+							}
+						}
+					}
+				}
+
+				yield (entry, values);
+			}
+		})
+	}
+
+	/// Read the entry at an offset in the current file.
+	///
+	/// This is for random access when the offset of an entry is already known, e.g. from a Data
+	/// object's `entry_offset`, and doesn't change the reader's position. The entry's data can
+	/// then be read with [`entry_data`](Self::entry_data) as usual.
+	///
+	/// Errors with `InvalidInput` if the offset isn't 8-byte aligned or is outside of the arena, and
+	/// with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn read_entry_at(&mut self, offset: u64) -> std::io::Result<Entry> {
+		self.load_open().await?;
+		// UNWRAP: load_open() sets current
+		let header = &self.current.as_ref().unwrap().header;
+
+		let arena_start = header.header_size.get();
+		let arena_end = arena_start.saturating_add(header.arena_size.get());
+		if !offset.is_multiple_of(8) || !(arena_start..arena_end).contains(&offset) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!(
+					"entry offset {offset} is not 8-byte aligned within the arena [{arena_start}, {arena_end})"
+				),
+			));
+		}
+
+		Entry::read_at(&mut self.io, offset, header).await
+	}
+
+	/// Read the header of the Data object at an offset in the current file.
+	///
+	/// This reads only the fixed part of the object, not its payload, so it's much cheaper than
+	/// reading the whole Data (which decompresses the payload) when the counts and links are all
+	/// that's needed, e.g. when scanning a whole file. The offset is usually one of
+	/// [`Entry::objects`], or from the links between Data objects.
+	///
+	/// Errors with `InvalidInput` if the offset isn't 8-byte aligned or is outside of the arena,
+	/// with `InvalidData` if there isn't a Data object there, and with `NotConnected` if no file is
+	/// open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn read_data_header_at(&mut self, offset: u64) -> std::io::Result<DataObjectHeader> {
+		self.load_open().await?;
+		// UNWRAP: load_open() sets current
+		let header = &self.current.as_ref().unwrap().header;
+
+		let arena_start = header.header_size.get();
+		let arena_end = header.arena_end();
+		if !offset.is_multiple_of(8) || !(arena_start..arena_end).contains(&offset) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!(
+					"data offset {offset} is not 8-byte aligned within the arena [{arena_start}, {arena_end})"
+				),
+			));
+		}
+
+		ObjectHeader::read_at(&mut self.io, offset)
+			.await?
+			.check_type(ObjectType::Data)?;
+		DataObjectHeader::read_at(&mut self.io, offset + OBJECT_HEADER_SIZE).await
+	}
+
+	/// Read an entry as a log record, with its well-known fields parsed.
+	///
+	/// All of the entry's data is read, as with [`entry_data`](Self::entry_data), and the fields
+	/// are collected by name, keeping the first value of fields which appear more than once. Then
+	/// `PRIORITY`, `MESSAGE`, `_SYSTEMD_UNIT`, and `_PID` are parsed (see
+	/// [`LogRecord::from_fields`]): those which are missing or malformed are `None` in the record.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn read_record(&mut self, entry: &Entry) -> std::io::Result<LogRecord> {
+		let mut fields = BTreeMap::new();
+		let mut data = self.entry_data(entry);
+		while let Some(datum) = data.next().await {
+			let datum = datum?;
+			fields.entry(datum.key).or_insert(datum.value);
+		}
+
+		Ok(LogRecord::from_fields(entry, fields))
+	}
+
+	/// Read all of the fields of an entry, by name.
+	///
+	/// Entries can have several values for the same field (e.g. one `GROUP=` per group), so each
+	/// name maps to all of its values, in the order the entry references them. All of the entry's
+	/// data is read, as with [`entry_data`](Self::entry_data).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn entry_multimap(
+		&mut self,
+		entry: &Entry,
+	) -> std::io::Result<BTreeMap<BString, Vec<BString>>> {
+		let mut fields = BTreeMap::<_, Vec<_>>::new();
+		let mut data = self.entry_data(entry);
+		while let Some(datum) = data.next().await {
+			let datum = datum?;
+			fields.entry(datum.key).or_default().push(datum.value);
+		}
+
+		Ok(fields)
+	}
+
+	/// Find the Data object for a `FIELD=value` payload in the current file.
+	///
+	/// This looks the payload up in the data hash table. Returns `None` if it's not in the file.
+	///
+	/// With [`ReadOptions::diagnostics`], the length of the hash chain walked is recorded (see
+	/// [`observed_max_chain_depth`](Self::observed_max_chain_depth)), and a warning is logged if
+	/// it's longer than the header says chains can be.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn find_data(&mut self, payload: &[u8]) -> std::io::Result<Option<Data>> {
+		self.load_open().await?;
+		let Self {
+			io,
+			options,
+			current,
+			max_chain_depth,
+			..
+		} = self;
+		// UNWRAP: load_open() sets current
+		let header = &current.as_ref().unwrap().header;
+
+		let hash = header.hash(payload);
+		let head = header
+			.data_hash_table()
+			.slot(io, hash)
+			.await?
+			.head_hash_offset;
+		let mut walk = ChainWalk::new(head, header.n_objects);
+		let mut depth = 0;
+		let mut found = None;
+		while let Some(offset) = walk.next()? {
+			depth += 1;
+			let object = DataObjectHeader::read_at(io, offset.get() + OBJECT_HEADER_SIZE).await?;
+			if object.hash == hash {
+				let data = Data::read_at_limited(
+					io,
+					offset.get(),
+					header.is_compact(),
+					options.max_expansion_ratio,
+				)
+				.await?;
+				if data.key.len() + 1 + data.value.len() == payload.len()
+					&& payload.starts_with(&data.key)
+					&& payload[data.key.len()] == b'='
+					&& payload.ends_with(&data.value)
+				{
+					found = Some(data);
+					break;
+				}
+			}
+
+			walk.link(object.next_hash_offset)?;
+		}
+
+		if options.diagnostics {
+			*max_chain_depth = (*max_chain_depth).max(depth);
+			if header
+				.data_hash_chain_depth
+				.is_some_and(|expected| depth > expected)
+			{
+				tracing::warn!(
+					?depth,
+					expected = ?header.data_hash_chain_depth,
+					"data hash chain is deeper than the header says, the file may be corrupt"
+				);
+			}
+		}
+
+		Ok(found)
+	}
+
+	/// The longest data hash chain walked so far by [`find_data`](Self::find_data).
+	///
+	/// Compare with the header's
+	/// [`data_hash_chain_depth`](crate::header::Header::data_hash_chain_depth) to see how often
+	/// lookups hit long chains, which is a sign that the data hash table is too small.
+	///
+	/// Returns `None` unless [`ReadOptions::diagnostics`] is enabled.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn observed_max_chain_depth(&self) -> Option<u64> {
+		self.options.diagnostics.then_some(self.max_chain_depth)
+	}
+
+	/// Find the most common data in the current file.
+	///
+	/// This walks the data hash table and returns the `FIELD=value` payloads which are referenced
+	/// by the most entries, with their number of entries, most common first. Only object headers
+	/// are read while walking the table; payloads are only read for the results.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn most_common_data(&mut self, limit: usize) -> std::io::Result<Vec<(BString, u64)>> {
+		self.load_open().await?;
+		let Self {
+			io,
+			options,
+			current,
+			..
+		} = self;
+		// UNWRAP: load_open() sets current
+		let header = &current.as_ref().unwrap().header;
+
+		let table = header.data_hash_table();
+		let heads = table
+			.items(io)
+			.try_filter_map(|item| std::future::ready(Ok(item.head_hash_offset)))
+			.try_collect::<Vec<_>>()
+			.await?;
+
+		// min-heap of the top (n_entries, offset) so far
+		let mut top = BinaryHeap::with_capacity(limit + 1);
+		for head in heads {
+			let mut walk = ChainWalk::new(Some(head), header.n_objects);
+			while let Some(offset) = walk.next()? {
+				let data = DataObjectHeader::read_at(io, offset.get() + OBJECT_HEADER_SIZE).await?;
+				top.push(Reverse((data.n_entries, offset.get())));
+				if top.len() > limit {
+					top.pop();
+				}
+
+				walk.link(data.next_hash_offset)?;
+			}
+		}
+
+		let mut results = Vec::with_capacity(top.len());
+		for Reverse((n_entries, offset)) in top.into_sorted_vec() {
+			let data =
+				Data::read_at_limited(io, offset, header.is_compact(), options.max_expansion_ratio)
+					.await?;
+			let mut payload = data.key;
+			payload.push(b'=');
+			payload.extend_from_slice(&data.value);
+			results.push((payload, n_entries));
+		}
+
+		Ok(results)
+	}
+
+	/// Read the data of an entry.
+	///
+	/// This reads through a separate handle to the current file (see
+	/// [`AsyncFileRead::try_clone`]), so it doesn't disturb the handle used for iteration. If the
+	/// implementation doesn't support cloning, the reader's own handle is used instead.
+	///
+	/// If a data object can't be read, the stream ends with an error, unless the reader is
+	/// [`tolerant`](ReadOptions::tolerant), in which case the object is skipped.
+	///
+	/// Panics if a file isn't loaded.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn entry_data<'e>(
+		&'e mut self,
+		entry: &'e Entry,
+	) -> impl Stream<Item = std::io::Result<Data>> + Unpin + 'e {
+		self.entry_data_with_offsets(entry).map_ok(|(_, data)| data)
+	}
+
+	/// Read the data of an entry, along with the offsets the entry refers to them by.
+	///
+	/// This is like [`entry_data`](Self::entry_data), but pairs each Data object with its item in
+	/// [`Entry::objects`]. The offset of the Data object is always the item's offset: an item that
+	/// points at something other than a Data object is an error (or is skipped if the reader is
+	/// [`tolerant`](ReadOptions::tolerant)), never resolved elsewhere.
+	///
+	/// Panics if a file isn't loaded.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn entry_data_with_offsets<'e>(
+		&'e mut self,
+		entry: &'e Entry,
+	) -> impl Stream<Item = std::io::Result<(NonZeroU32, Data)>> + Unpin + 'e {
+		let Self {
+			io,
+			data_io,
+			options,
+			current,
+			..
+		} = self;
+		let CurrentFile { header, .. } = current
+			.as_ref()
+			.expect("tried to read entry without a loaded file");
+
+		Box::pin(async_stream::try_stream! {
+			if data_io.is_none() && options.max_open_files.get() < 2 {
+				tracing::trace!("no room for another open file, reading data through the main handle");
+			} else if data_io.is_none() {
+				match io.try_clone() {
+					Ok(clone) => *data_io = Some(clone),
+					Err(err) if err.kind() == std::io::ErrorKind::Unsupported => {
+						tracing::trace!("handle can't be cloned, reading data through the main one");
+					}
+					Err(err) => Err(err)?,
+				}
+			}
+
+			let io = match data_io {
+				Some(data_io) => {
+					if let Some(path) = io.current().filter(|path| data_io.current() != Some(*path)) {
+						data_io.open(path).await?;
+					}
+					data_io.set_read_ceiling(header.arena_end());
+					data_io
+				}
+				None => io,
+			};
+
+			// Entry::data yields exactly one result per item, in order
+			let mut data = futures_util::stream::iter(entry.objects.iter().copied())
+				.zip(entry.data(io, header, options.max_expansion_ratio));
+			while let Some((offset, datum)) = data.next().await {
+				match datum {
+					Ok(datum) => {
+						debug_assert_eq!(datum.offset.get(), u64::from(offset.get()));
+						yield (offset, datum);
+					}
+					Err(err) if options.tolerant => {
+						tracing::warn!(entry=?entry.offset, ?offset, ?err, "skipping unreadable data object");
+					}
+					Err(err) => Err(err)?,
+				}
+			}
+		})
+	}
+
+	/// Read a Data object of the current file, borrowing its key and value where possible.
+	///
+	/// This is for scanning a lot of data without allocating for each object: if the
+	/// implementation holds the file in memory (see [`AsyncFileRead::contiguous`]) and the payload
+	/// isn't compressed, the key and value borrow from the file. Otherwise they're owned, as with
+	/// [`entry_data`](Self::entry_data). The offset is usually one of [`Entry::objects`].
+	///
+	/// This reads through the reader's own handle, which doesn't disturb iteration.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn data_ref(&mut self, offset: NonZeroU64) -> std::io::Result<DataRef<'_>> {
+		self.load_open().await?;
+		// UNWRAP: load_open() sets current
+		let is_compact = self.current.as_ref().unwrap().header.is_compact();
+		Data::read_ref(
+			&mut self.io,
+			offset.get(),
+			is_compact,
+			self.options.max_expansion_ratio,
+		)
+		.await
+	}
+
+	/// Read an object of the current file as it is on disk.
+	///
+	/// This returns the object header and the exact bytes of its payload (what follows the
+	/// header), without decompressing or parsing them, so that objects can be copied verbatim,
+	/// e.g. when converting files. Offsets can come from [`walk_objects`](Self::walk_objects)
+	/// handlers, [`Entry::objects`], or the links between objects.
+	///
+	/// Errors with `InvalidInput` if the offset isn't 8-byte aligned or is outside of the arena, and
+	/// with `InvalidData` if the object doesn't fit in the arena, before reading the payload.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn read_object_raw(
+		&mut self,
+		offset: u64,
+	) -> std::io::Result<(ObjectHeader, Vec<u8>)> {
+		self.load_open().await?;
+		// UNWRAP: load_open() sets current
+		let header = &self.current.as_ref().unwrap().header;
+
+		let arena_start = header.header_size.get();
+		let arena_end = header.arena_end();
+		let offset = NonZeroU64::new(offset)
+			.filter(|offset| {
+				offset.get().is_multiple_of(8) && (arena_start..arena_end).contains(&offset.get())
+			})
+			.ok_or_else(|| {
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidInput,
+					format!(
+						"object offset {offset} is not 8-byte aligned within the arena [{arena_start}, {arena_end})"
+					),
+				)
+			})?;
+
+		let object = ObjectHeader::read_at(&mut self.io, offset.get()).await?;
+		let payload = Self::read_payload(&mut self.io, header, offset, &object).await?;
+		Ok((object, payload))
+	}
+
+	/// Get the epoch of the monotonic clock for a boot.
+	///
+	/// This is the wallclock time at which the monotonic clock of that boot started, derived from
+	/// any entry of the boot as its realtime minus its monotonic timestamp. Use it with
+	/// [`Monotonic::to_timestamp`](crate::monotonic::Monotonic::to_timestamp) to convert monotonic
+	/// timestamps of that boot to wallclock.
+	///
+	/// Epochs are cached on the reader, so only the first call for a boot does any IO. The header's
+	/// tail entry fields are used when they can be trusted (see
+	/// [`CompatibleFlag::TailEntryBootId`]), otherwise the files of the selected journal are scanned
+	/// for an entry of the boot. The current file is restored afterwards, so the position is not
+	/// disturbed.
+	///
+	/// Returns `None` if the selected journal has no entries for that boot.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn monotonic_epoch(&mut self, boot_id: u128) -> std::io::Result<Option<Timestamp>> {
+		if let Some(epoch) = self.epochs.get(&boot_id) {
+			return Ok(Some(*epoch));
+		}
+
+		let files = self.selected_files().await?;
+		let previous = self.io.current().map(Path::to_path_buf);
+		let found = self.find_epoch(&files, boot_id).await;
+
+		self.restore_open(previous).await?;
+
+		found
+	}
+
+	/// Summarise the selected journal from the headers of its files.
+	///
+	/// This only reads the header of each file, so it's fast regardless of the size of the
+	/// journal, but it relies on the headers being accurate: if a writer crashed, the latest file's
+	/// header may lag behind its entries (see [`is_dirty`](Self::is_dirty)). The current file is
+	/// restored afterwards, so the position is not disturbed.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn summary(&mut self) -> std::io::Result<SelectionSummary> {
+		let files = self.selected_files().await?;
+		let previous = self.io.current().map(Path::to_path_buf);
+		let summaries = async {
+			let mut summaries = Vec::with_capacity(files.len());
+			for file in files {
+				self.io.open(&T::make_filename(&file)).await?;
+				let header = Header::read(&mut self.io).await?;
+				summaries.push(FileSummary {
+					file,
+					seqnum_id: header.seqnum_id,
+					n_entries: header.n_entries,
+					head_seqnum: header.head_entry_seqnum,
+					tail_seqnum: header.tail_entry_seqnum,
+					head_realtime: header.head_entry_realtime,
+					tail_realtime: header.tail_entry_realtime,
+				});
+			}
+			std::io::Result::Ok(summaries)
+		}
+		.await;
+
+		self.restore_open(previous).await?;
+
+		let files = summaries?;
+		Ok(SelectionSummary {
+			n_entries: files.iter().map(|file| file.n_entries).sum(),
+			head_seqnum: files.iter().filter_map(|file| file.head_seqnum).min(),
+			tail_seqnum: files.iter().filter_map(|file| file.tail_seqnum).max(),
+			head_realtime: files.iter().filter_map(|file| file.head_realtime).min(),
+			tail_realtime: files.iter().filter_map(|file| file.tail_realtime).max(),
+			files,
+		})
+	}
+
+	/// Measure the space the files of a journal take, in bytes.
+	///
+	/// This is like `journalctl --disk-usage`: it's the physical size of the files, including
+	/// the space preallocated for entries which aren't written yet, rather than what the entries
+	/// take. Only the files of the given journal are counted, whether or not it's the selected
+	/// one.
+	///
+	/// Sizes are taken from [`AsyncFileRead::file_size`]. If the implementation doesn't support
+	/// that, each file is opened and measured by seeking to its end instead, and the current file
+	/// is restored afterwards.
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn disk_usage(&mut self, journal: &JournalSelection) -> std::io::Result<u64> {
+		let files = self.files_of(journal, &T::make_prefix(journal)).await?;
+		let previous = self.io.current().map(Path::to_path_buf);
+		let mut reopened = false;
+		let total = async {
+			let mut total = 0_u64;
+			for file in files {
+				let filename = T::make_filename(&file);
+				let size = match self.io.file_size(&filename).await {
+					Err(err) if err.kind() == std::io::ErrorKind::Unsupported => {
+						reopened = true;
+						self.io.open(&filename).await?;
+						self.io.seek(std::io::SeekFrom::End(0)).await?
+					}
+					size => size?,
+				};
+				total = total.saturating_add(size);
+			}
+			std::io::Result::Ok(total)
+		}
+		.await;
+
+		if reopened {
+			self.restore_open(previous).await?;
+		}
+
+		total
+	}
+
+	/// Read the Tag objects of the current file, in order.
+	///
+	/// Tags are written when the file is sealed with Forward Secure Sealing; this shows the
+	/// structure of the sealing (which epochs are sealed, up to which seqnum) without verifying the
+	/// tags themselves. The whole arena is walked, but only object headers are read for objects
+	/// which aren't tags.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub fn tags(&mut self) -> impl Stream<Item = std::io::Result<Tag>> + Unpin + '_ {
+		Box::pin(async_stream::try_stream! {
+			self.load_open().await?;
+			// UNWRAP: load_open() sets current
+			let header = &self.current.as_ref().unwrap().header;
+			let mut walker = ObjectWalker::new(header);
+			while let Some((offset, object)) = walker.next(&mut self.io).await? {
+				if object.r#type == ObjectType::Tag {
+					yield Tag::read_at(&mut self.io, offset).await?;
+				} else {
+					Self::handle_object(&mut self.io, &mut self.object_handlers, header, offset, &object).await?;
+				}
+			}
+		})
+	}
+
+	/// Walk all the objects of the current file, passing those of custom types to their handlers.
+	///
+	/// See [`set_object_handler`](Self::set_object_handler). Only object headers are read for the
+	/// other objects. Returns the number of objects walked.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn walk_objects(&mut self) -> std::io::Result<u64> {
+		self.load_open().await?;
+		let file = self.current_file();
+		// UNWRAP: load_open() sets current
+		let header = &self.current.as_ref().unwrap().header;
+		let mut progress = ProgressReporter::start(self.progress_handler.as_ref(), file, header);
+		let mut walker = ObjectWalker::new(header);
+		let mut count = 0;
+		while let Some((offset, object)) = walker.next(&mut self.io).await? {
+			count += 1;
+			progress.advance(1);
+			Self::handle_object(
+				&mut self.io,
+				&mut self.object_handlers,
+				header,
+				offset,
+				&object,
+			)
+			.await?;
+		}
+
+		progress.finish();
+		Ok(count)
+	}
+
+	/// Fingerprint the contents of the current file with SHA-256.
+	///
+	/// This is for cheaply detecting whether a file has changed, e.g. to skip shipping archived
+	/// files which were already backed up. It's not the Forward Secure Sealing of the file and
+	/// says nothing about its authenticity.
+	///
+	/// Only the header and the used part of the arena are hashed, up to the end of the tail
+	/// object: the rest of the arena is preallocated space which is usually all zeros.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	///
+	/// Only available with the `digest` feature (enabled by default).
+	#[cfg(feature = "digest")]
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn file_digest(&mut self) -> std::io::Result<[u8; 32]> {
+		use sha2::{Digest as _, Sha256};
+
+		self.load_open().await?;
+		// UNWRAP: load_open() sets current
+		let header = &self.current.as_ref().unwrap().header;
+		let arena_end = header.header_size.get() + header.arena_size.get();
+		let tail_offset = header.tail_object_offset.get();
+		let tail = ObjectHeader::read_at(&mut self.io, tail_offset).await?;
+		let end = tail_offset.saturating_add(tail.size).next_multiple_of(8);
+		if end > arena_end {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"tail object at {tail_offset} ends at {end}, past the arena end {arena_end}"
+				),
+			));
+		}
+
+		let mut hasher = Sha256::new();
+		let mut buf = vec![0; 64 * 1024];
+		self.io.seek(std::io::SeekFrom::Start(0)).await?;
+		let mut remaining = end;
+		while remaining > 0 {
+			let chunk = &mut buf[..remaining.min(64 * 1024) as usize];
+			self.io.read_exact(chunk).await?;
+			hasher.update(&*chunk);
+			remaining -= chunk.len() as u64;
+		}
+
+		Ok(hasher.finalize().into())
+	}
+
+	/// Verify all data in all available journals.
+	///
+	/// This is [`verify_all_report`](Self::verify_all_report) reduced to whether every file is
+	/// clean: it returns `false` if any file is corrupt. Other IO errors are returned as-is.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn verify_all(&mut self) -> std::io::Result<bool> {
+		Ok(self.verify_all_report().await?.is_clean())
+	}
+
+	/// Verify all data in all available journals, reporting what was found in each file.
+	///
+	/// This will check every entry and the hash of all of its data, the `xor_hash` of entries,
+	/// that monotonic times don't go backwards within a boot, and the field hash chains (as
+	/// [`verify_field_chains`](Self::verify_field_chains) does). It should be used to detect
+	/// tampering; when reading the journal normally, only the data that is actually read is
+	/// verified. Sealed files are reported as such, but their tags aren't verified yet.
+	///
+	/// Files are independent, so up to [`ReadOptions::concurrency`] of them are verified at once,
+	/// each through its own handle (see [`AsyncFileRead::try_clone`]). The current file and
+	/// position are not disturbed. Progress is reported to the
+	/// [progress handler](Self::set_progress_handler) as each file is verified.
+	///
+	/// Corruption is recorded in the report. Other IO errors are returned as-is.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn verify_all_report(&mut self) -> std::io::Result<VerifyReport> {
+		let mut files = Vec::new();
+		{
+			let mut listing = self.io.list_files_sorted(None);
+			while let Some(file) = listing.next().await {
+				files.push(file?);
+			}
+		}
+
+		let max_ratio = self.options.max_expansion_ratio;
+		let progress = self.progress_handler.as_ref();
+		let Some(handles) = self.clone_handles(files.len())? else {
+			tracing::debug!(files = files.len(), "verifying files sequentially");
+			let previous = self.io.current().map(Path::to_path_buf);
+			let mut reports = Vec::with_capacity(files.len());
+			let mut result = Ok(());
+			for file in &files {
+				match Self::verify_file(&mut self.io, file, max_ratio, progress).await {
+					Ok(report) => reports.push(report),
+					Err(err) => {
+						result = Err(err);
+						break;
+					}
+				}
+			}
+
+			self.restore_open(previous).await?;
+
+			return result.map(|()| VerifyReport { files: reports });
+		};
+
+		tracing::debug!(
+			files = files.len(),
+			handles = handles.len(),
+			"verifying files concurrently"
+		);
+		let verify = |mut io: T, index: usize, file: FilenameInfo| async move {
+			let result = Self::verify_file(&mut io, &file, max_ratio, progress).await;
+			(io, index, result)
+		};
+
+		let mut reports = Vec::with_capacity(files.len());
+		let mut files = files.into_iter().enumerate();
+		let mut running = FuturesUnordered::new();
+		for io in handles {
+			if let Some((index, file)) = files.next() {
+				running.push(verify(io, index, file));
+			}
+		}
+
+		while let Some((io, index, result)) = running.next().await {
+			reports.push((index, result?));
+			if let Some((index, file)) = files.next() {
+				running.push(verify(io, index, file));
+			}
+		}
+
+		// keep the reports in the order of the files
+		reports.sort_by_key(|(index, _)| *index);
+		Ok(VerifyReport {
+			files: reports.into_iter().map(|(_, report)| report).collect(),
+		})
+	}
+
+	/// Check the chains of the field hash table of the current file.
+	///
+	/// Every Field object is linked from its slot in the field hash table through the
+	/// `next_hash_offset` of the Fields before it, and links to its Data objects through its
+	/// `next_data_offset` and their `next_field_offset`. This walks both chains for all slots, and
+	/// reports links which point outside the arena ([`ChainError::Dangling`]), to the wrong type of
+	/// object ([`ChainError::WrongType`]), or to Data of another field
+	/// ([`ChainError::WrongField`]). A chain with more links than there are objects in the file
+	/// must loop, and is reported as a [`ChainError::Cycle`].
+	///
+	/// A chain is not followed further after a problem. IO errors are returned as-is.
+	///
+	/// Errors with `NotConnected` if no file is open (select a journal first).
+	#[tracing::instrument(level = "debug", skip(self))]
+	pub async fn verify_field_chains(&mut self) -> std::io::Result<Vec<ChainError>> {
+		self.load_open().await?;
+		let Self {
+			io,
+			options,
+			current,
+			..
+		} = self;
+		// UNWRAP: load_open() sets current
+		let header = &current.as_ref().unwrap().header;
+		let errors = Self::field_chain_errors(io, header, options.max_expansion_ratio).await?;
+		if !errors.is_empty() {
+			tracing::warn!(errors = errors.len(), file = ?io.current(), "field hash chains are corrupt");
+		}
+		Ok(errors)
+	}
+
+	// == Internal ==
+
+	/// Close the files of merged journals until fewer than `limit` are open, to make room for one.
+	///
+	/// Their readers reopen the files when they're next read from.
+	#[tracing::instrument(level = "trace", skip(readers))]
+	async fn close_merge_heads<'r>(readers: impl Iterator<Item = &'r mut Self>, limit: usize)
+	where
+		T: 'r,
+	{
+		let mut open = readers
+			.filter(|reader| reader.io.current().is_some())
+			.collect::<Vec<_>>();
+		while open.len() >= limit {
+			// UNWRAP: limit is at least 1, so there's at least one open
+			let reader = open.pop().unwrap();
+			tracing::trace!(file = ?reader.io.current(), "closing merged journal file");
+			reader.io.close().await;
+		}
+	}
+
+	/// Walk the chains of the field hash table of a file, for [`verify_field_chains`](Self::verify_field_chains).
+	#[tracing::instrument(level = "trace", skip(io, header))]
+	async fn field_chain_errors(
+		io: &mut T,
+		header: &Header,
+		max_ratio: Option<NonZeroU64>,
+	) -> std::io::Result<Vec<ChainError>> {
+		let table = header.field_hash_table();
+		let items = table.items(io).try_collect::<Vec<_>>().await?;
+
+		let mut errors = Vec::new();
+		for (slot, item) in items.into_iter().enumerate() {
+			let slot_offset = table.offset.get() + slot as u64 * HASH_ITEM_SIZE as u64;
+			let mut from = slot_offset;
+			// links are checked below, and reported rather than erroring
+			let mut walk = ChainWalk::unordered(item.head_hash_offset, header.n_objects);
+			loop {
+				let field_offset = match walk.next() {
+					Ok(Some(offset)) => offset,
+					Ok(None) => break,
+					Err(_) => {
+						errors.push(ChainError::Cycle { start: slot_offset });
+						break;
+					}
+				};
+				let Some(object) = Self::check_link(
+					io,
+					header,
+					from,
+					field_offset,
+					ObjectType::Field,
+					&mut errors,
+				)
+				.await?
+				else {
+					break;
+				};
+				let field =
+					FieldObjectHeader::read_at(io, field_offset.get() + OBJECT_HEADER_SIZE).await?;
+				let name_offset =
+					field_offset.get() + OBJECT_HEADER_SIZE + FIELD_OBJECT_HEADER_SIZE;
+				let name = io
+					.read_some_at(
+						name_offset,
+						object
+							.payload_size()
+							.saturating_sub(FIELD_OBJECT_HEADER_SIZE) as _,
+					)
+					.await?;
+
+				let mut data_from = field_offset.get();
+				let mut data_walk =
+					ChainWalk::unordered(NonZeroU64::new(field.next_data_offset), header.n_objects);
+				loop {
+					let data_offset = match data_walk.next() {
+						Ok(Some(offset)) => offset,
+						Ok(None) => break,
+						Err(_) => {
+							errors.push(ChainError::Cycle {
+								start: field_offset.get(),
+							});
+							break;
+						}
+					};
+					if Self::check_link(
+						io,
+						header,
+						data_from,
+						data_offset,
+						ObjectType::Data,
+						&mut errors,
+					)
+					.await?
+					.is_none()
+					{
+						break;
+					}
+					let data = Data::read_at_limited(
+						io,
+						data_offset.get(),
+						header.is_compact(),
+						max_ratio,
+					)
+					.await?;
+					if data.key != name {
+						errors.push(ChainError::WrongField {
+							field: field_offset.get(),
+							data: data_offset.get(),
+						});
+						break;
+					}
+
+					data_from = data_offset.get();
+					data_walk.link(data.header.next_field_offset)?;
+				}
+
+				from = field_offset.get();
+				walk.link(field.next_hash_offset)?;
+			}
+		}
+
+		Ok(errors)
+	}
+
+	/// Pass an object to the handler for its type, if it's a custom type with one.
+	#[tracing::instrument(level = "trace", skip(io, handlers, header))]
+	async fn handle_object(
+		io: &mut T,
+		handlers: &mut HashMap<u8, ObjectHandler>,
+		header: &Header,
+		offset: NonZeroU64,
+		object: &ObjectHeader,
+	) -> std::io::Result<()> {
+		let ObjectType::Unknown(type_id) = object.r#type else {
+			return Ok(());
+		};
+		let Some(handler) = handlers.get_mut(&type_id) else {
+			return Ok(());
+		};
+
+		let payload = Self::read_payload(io, header, offset, object).await?;
+		handler(offset, object, &payload)
+	}
+
+	/// Read the payload of an object (what follows its header), checking that it's in the arena.
+	#[tracing::instrument(level = "trace", skip(io, header))]
+	async fn read_payload(
+		io: &mut T,
+		header: &Header,
+		offset: NonZeroU64,
+		object: &ObjectHeader,
+	) -> std::io::Result<Vec<u8>> {
+		let arena_end = header.header_size.get() + header.arena_size.get();
+		if object.size < OBJECT_HEADER_SIZE || offset.get().saturating_add(object.size) > arena_end
+		{
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"object at {offset} of type {:?} ({} bytes) doesn't fit in the arena, which ends at {arena_end}",
+					object.r#type, object.size
+				),
+			));
+		}
+
+		io.read_some_at(
+			offset.get() + OBJECT_HEADER_SIZE,
+			object.payload_size() as _,
+		)
+		.await
+	}
+
+	/// Check that a link points to an object of the expected type within the arena.
+	///
+	/// Returns the header of the object, or records the problem and returns `None`.
+	#[tracing::instrument(level = "trace", skip(io, header, errors))]
+	async fn check_link(
+		io: &mut T,
+		header: &Header,
+		from: u64,
+		to: NonZeroU64,
+		expected: ObjectType,
+		errors: &mut Vec<ChainError>,
+	) -> std::io::Result<Option<ObjectHeader>> {
+		let arena = header.header_size.get()..=header.tail_object_offset.get();
+		if !to.get().is_multiple_of(8) || !arena.contains(&to.get()) {
+			errors.push(ChainError::Dangling { from, to: to.get() });
+			return Ok(None);
+		}
+
+		let object = ObjectHeader::read_at(io, to.get()).await?;
+		if object.r#type != expected || object.size < expected.min_size() {
+			errors.push(ChainError::WrongType {
+				from,
+				to: to.get(),
+				found: object.r#type,
+			});
+			return Ok(None);
+		}
+
+		Ok(Some(object))
+	}
+
+	/// Get the selected journal and its prefix, failing if no journal is selected.
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn selected_journal(&self) -> std::io::Result<(&JournalSelection, PathBuf)> {
+		self.select
+			.as_ref()
+			.ok_or_else(|| {
+				std::io::Error::new(std::io::ErrorKind::NotConnected, "no journal selected")
+			})
+			.map(|j| (j, T::make_prefix(j)))
+	}
+
+	/// List the files of the selected journal, oldest first.
+	///
+	/// This includes the latest file, if it exists. When reading a single file, this is just that
+	/// file, if its name can be parsed.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn selected_files(&self) -> std::io::Result<Vec<FilenameInfo>> {
+		let (selected, prefix) = self.selected_journal()?;
+
+		if let Some(path) = &self.single_file {
+			return T::parse_filename(path)
+				.map(|file| vec![file])
+				.ok_or_else(|| {
+					std::io::Error::new(
+						std::io::ErrorKind::Unsupported,
+						"the opened file's name doesn't follow the file scheme",
+					)
+				});
+		}
+
+		self.files_of(selected, &prefix).await
+	}
+
+	/// List the files of a journal, oldest first, given its prefix.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn files_of(
+		&self,
+		journal: &JournalSelection,
+		prefix: &Path,
+	) -> std::io::Result<Vec<FilenameInfo>> {
+		// the prefix only matches archived files, so list its directory and filter instead
+		let mut files = Vec::new();
+		let mut listing = self.io.list_files_sorted(prefix.parent());
+		while let Some(file) = listing.next().await {
+			let file = file?;
+			if JournalSelection::from(file.clone()) == *journal {
+				files.push(file);
+			}
+		}
+
+		Ok(files)
+	}
+
+	/// Find (and cache) the monotonic epoch of a boot by looking through the given files.
+	///
+	/// This opens other files without loading them, so the caller must restore the open file.
+	#[tracing::instrument(level = "trace", skip(self, files))]
+	async fn find_epoch(
+		&mut self,
+		files: &[FilenameInfo],
+		boot_id: u128,
+	) -> std::io::Result<Option<Timestamp>> {
+		for file in files {
+			self.io.open(&T::make_filename(file)).await?;
+			let header = Header::read(&mut self.io).await?;
+			header.check_compact_addressable()?;
+
+			// the tail fields are only about the last entry if the flag is set
+			if header
+				.compatible_flags
+				.contains(CompatibleFlag::TailEntryBootId)
+				&& header.tail_entry_boot_id.map(NonZeroU128::get) == Some(boot_id)
+			{
+				if let (Some(realtime), Some(monotonic)) =
+					(header.tail_entry_realtime, header.tail_entry_monotonic)
+				{
+					tracing::trace!(?file, "using tail entry from header");
+					let epoch = monotonic.estimate_epoch(realtime);
+					self.epochs.insert(boot_id, epoch);
+					return Ok(Some(epoch));
+				}
+			}
+
+			let mut walk = ChainWalk::new(Some(header.entry_array_offset), header.n_objects);
+			while let Some(offset) = walk.next()? {
+				let array = EntryArray::read_at(&mut self.io, offset, &header).await?;
+				for index in 0..array.capacity() {
+					let Some(entry_offset) = array.item_at(&mut self.io, index).await? else {
+						break;
+					};
+
+					ObjectHeader::read_at(&mut self.io, entry_offset.get())
+						.await?
+						.check_type(ObjectType::Entry)?;
+					let entry = EntryObjectHeader::read_at(
+						&mut self.io,
+						entry_offset.get() + OBJECT_HEADER_SIZE,
+					)
+					.await?;
+
+					// cache every boot we come across, it's free
+					let epoch = entry.monotonic.estimate_epoch(entry.realtime);
+					self.epochs.entry(entry.boot_id.get()).or_insert(epoch);
+					if entry.boot_id.get() == boot_id {
+						return Ok(Some(epoch));
+					}
+				}
+				walk.link(array.next_offset.map_or(0, NonZeroU64::get))?;
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Make independent handles to process files concurrently.
+	///
+	/// Returns `None` if files should be processed sequentially on the reader's own handle, either
+	/// because concurrency is disabled, because there's no room for more open files, or because
+	/// the handle can't be cloned.
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn clone_handles(&self, files: usize) -> std::io::Result<Option<Vec<T>>> {
+		let open = usize::from(self.io.current().is_some())
+			+ usize::from(
+				self.data_io
+					.as_ref()
+					.is_some_and(|io| io.current().is_some()),
+			);
+		let count = self
+			.options
+			.concurrency
+			.get()
+			.min(files)
+			.min(self.options.max_open_files.get().saturating_sub(open));
+		if count <= 1 {
+			return Ok(None);
+		}
+
+		match (0..count).map(|_| self.io.try_clone()).collect() {
+			Ok(handles) => Ok(Some(handles)),
+			Err(err) if err.kind() == std::io::ErrorKind::Unsupported => Ok(None),
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Check every entry of a file and all of its data, and its field hash chains.
+	///
+	/// Corruption is recorded in the report, and stops the check of the entries (but not of the
+	/// chains). This leaves the file open without loading it, so the caller must restore the open
+	/// file if needed.
+	#[tracing::instrument(level = "trace", skip(io, progress))]
+	async fn verify_file(
+		io: &mut T,
+		file: &FilenameInfo,
+		max_ratio: Option<NonZeroU64>,
+		progress: Option<&ProgressHandler>,
+	) -> std::io::Result<FileReport> {
+		let mut report = FileReport {
+			file: file.clone(),
+			entries: 0,
+			data: 0,
+			errors: Vec::new(),
+			seal: None,
+		};
+
+		io.open(&T::make_filename(file)).await?;
+		let header: std::io::Result<Header> = async {
+			let header = Header::read(io).await?;
+			header.check_compact_addressable()?;
+			Ok(header)
+		}
+		.await;
+		let header = match header {
+			Ok(header) => header,
+			Err(err) => {
+				report.errors.push(VerifyError::structure(err, None)?);
+				tracing::warn!(?file, errors = ?report.errors, "file failed verification");
+				return Ok(report);
+			}
+		};
+		report.seal = Some(SealStatus::of(&header));
+
+		let mut progress = ProgressReporter::start(progress, Some(file.clone()), &header);
+		let mut at = None;
+		if let Err(err) =
+			Self::verify_entries(io, &header, max_ratio, &mut report, &mut at, &mut progress).await
+		{
+			report.errors.push(VerifyError::structure(err, at)?);
+		}
+
+		match Self::field_chain_errors(io, &header, max_ratio).await {
+			Ok(errors) => report
+				.errors
+				.extend(errors.into_iter().map(VerifyError::Chain)),
+			Err(err) => report.errors.push(VerifyError::structure(err, None)?),
+		}
+		progress.finish();
+
+		if !report.is_clean() {
+			tracing::warn!(?file, errors = ?report.errors, "file failed verification");
+		}
+		Ok(report)
+	}
+
+	/// Check every entry of a file, for [`verify_file`](Self::verify_file).
+	///
+	/// Hash mismatches and monotonic regressions are recorded in the report, and checking
+	/// continues. Other corruption is returned as an error, with `at` set to the offset of the
+	/// entry being checked.
+	#[tracing::instrument(level = "trace", skip(io, header, report, at, progress))]
+	async fn verify_entries(
+		io: &mut T,
+		header: &Header,
+		max_ratio: Option<NonZeroU64>,
+		report: &mut FileReport,
+		at: &mut Option<u64>,
+		progress: &mut ProgressReporter<'_>,
+	) -> std::io::Result<()> {
+		let mut checked = HashSet::new();
+		// the latest monotonic time of each boot
+		let mut monotonic = HashMap::new();
+		let mut walk = ChainWalk::new(Some(header.entry_array_offset), header.n_objects);
+		while let Some(offset) = walk.next()? {
+			let array = EntryArray::read_at(io, offset, header).await?;
+			progress.advance(1);
+			for index in 0..array.capacity() {
+				let Some(entry_offset) = array.item_at(io, index).await? else {
+					break;
+				};
+
+				*at = Some(entry_offset.get());
+				let entry = Entry::read_at(io, entry_offset.get(), header).await?;
+				report.entries += 1;
+				let data_before = report.data;
+
+				let boot_id = entry.header.boot_id.get();
+				let time = entry.header.monotonic.0.get();
+				match monotonic.insert(boot_id, time) {
+					Some(previous) if previous > time => {
+						// keep the latest, so a single bad entry is only reported once
+						monotonic.insert(boot_id, previous);
+						report.errors.push(VerifyError::MonotonicRegression {
+							offset: entry_offset.get(),
+							boot_id,
+							previous,
+							monotonic: time,
+						});
+					}
+					_ => {}
+				}
+
+				// the xor hash is always unkeyed, so that it can be compared across files
+				let mut xor_hash = 0;
+				let mut data = entry.data(io, header, max_ratio);
+				while let Some(datum) = data.next().await {
+					let datum = datum?;
+					if let Some(flag) = datum.compression.incompatible_flag() {
+						if !header.incompatible_flags.contains(flag) {
+							return Err(std::io::Error::new(
+								std::io::ErrorKind::InvalidData,
+								format!(
+									"Data:{} is compressed with {:?} but the file header doesn't declare it",
+									datum.offset, datum.compression
+								),
+							));
+						}
+					}
+
+					let payload = [&datum.key[..], b"=", &datum.value[..]].concat();
+					xor_hash ^= jenkins_hash64(&payload);
+					if checked.insert(datum.offset) {
+						report.data += 1;
+						let computed = header.hash(&payload);
+						if computed != datum.header.hash {
+							report.errors.push(VerifyError::DataHash {
+								offset: datum.offset.get(),
+								stored: datum.header.hash,
+								computed,
+							});
+						}
+					}
+				}
+
+				if xor_hash != entry.header.xor_hash {
+					report.errors.push(VerifyError::XorHash {
+						offset: entry_offset.get(),
+						stored: entry.header.xor_hash,
+						computed: xor_hash,
+					});
+				}
+
+				// the entry and the data it's the first to reference
+				progress.advance(1 + report.data - data_before);
+			}
+			walk.link(array.next_offset.map_or(0, NonZeroU64::get))?;
+		}
+
+		Ok(())
+	}
+
+	/// Open a file and load it.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn open_and_load(&mut self, file: &FilenameInfo) -> std::io::Result<()> {
+		self.io.open(&T::make_filename(file)).await?;
+		self.load().await
+	}
+
+	/// Reopen the file that was open before other files were opened to read their headers, or
+	/// close the handle if none was, so that the open file is the one `current` describes.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn restore_open(&mut self, previous: Option<PathBuf>) -> std::io::Result<()> {
+		match previous {
+			Some(path) => self.io.open(&path).await,
+			None => {
+				self.io.close().await;
+				Ok(())
+			}
+		}
+	}
+
+	/// Position on the entry whose key is closest to the target, across the selected journal.
+	///
+	/// When the target is exactly between two entries, the earlier one wins.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_closest(&mut self, target: u64, key: SeekKey) -> std::io::Result<()> {
+		let files = self.selected_files().await?;
+		let Some(newest) = files.last() else {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::NotFound,
+				"no files found",
+			));
+		};
+
+		// the file the target falls in, and the last file with entries before that
+		let open = self.io.current().map(Path::to_path_buf);
+		let scan = async {
+			let mut landing = None;
+			let mut previous: Option<(&FilenameInfo, u64)> = None;
+			for file in &files {
+				self.io.open(&T::make_filename(file)).await?;
+				let header = Header::read(&mut self.io).await?;
+				let Some(tail) = key.tail(&header) else {
+					continue;
+				};
+
+				if tail >= target {
+					landing = Some(file);
+					break;
+				}
+				previous = Some((file, tail));
+			}
+			std::io::Result::Ok((landing, previous))
+		}
+		.await;
+		let (landing, previous) = match scan {
+			Ok(found) => found,
+			Err(err) => {
+				self.restore_open(open).await?;
+				return Err(err);
+			}
+		};
+
+		let Some(file) = landing else {
+			// the target is past the end, so the closest entry is the last one
+			self.open_and_load(previous.map_or(newest, |(file, _)| file))
+				.await?;
+			return self.seek_last_entry().await;
+		};
+
+		self.open_and_load(file).await?;
+		self.seek_closest_in_current(target, key, previous).await
+	}
+
+	/// Position on the entry whose key is closest to the target, in the current file.
+	///
+	/// If there's a previous file, its last entry is closer than the first of this one, and the
+	/// target is before this file, that entry is picked instead.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_closest_in_current(
+		&mut self,
+		target: u64,
+		key: SeekKey,
+		previous: Option<(&FilenameInfo, u64)>,
+	) -> std::io::Result<()> {
+		let chain = self.entry_array_chain().await?;
+		let total = chain.iter().map(|(_, len)| len).sum::<u64>();
+		if total == 0 {
+			return self.skip_to_end().await;
+		}
+
+		// lower bound: first entry with a key >= target
+		let (mut low, mut high) = (0, total);
+		while low < high {
+			let mid = low + (high - low) / 2;
+			if self.entry_key_at(&chain, mid, key).await? < target {
+				low = mid + 1;
+			} else {
+				high = mid;
+			}
+		}
+
+		let found = low.min(total - 1);
+		let found_distance = self
+			.entry_key_at(&chain, found, key)
+			.await?
+			.abs_diff(target);
+		let index = if found > 0 {
+			let before = self.entry_key_at(&chain, found - 1, key).await?;
+			if before.abs_diff(target) <= found_distance {
+				found - 1
+			} else {
+				found
+			}
+		} else {
+			if let Some((previous, tail)) = previous {
+				if tail.abs_diff(target) <= found_distance {
+					self.open_and_load(previous).await?;
+					return self.seek_last_entry().await;
+				}
+			}
+			found
+		};
+
+		self.set_position(&chain, index);
+		Ok(())
+	}
+
+	/// Position on the entry with exactly this seqnum in a seqnum domain.
+	///
+	/// Errors with `NotFound` if the selected journal has no such entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_absolute(
+		&mut self,
+		seqnum_id: NonZeroU128,
+		seqnum: NonZeroU64,
+	) -> std::io::Result<()> {
+		let files = self.selected_files().await?;
+		let open = self.io.current().map(Path::to_path_buf);
+		let scan = async {
+			for file in files {
+				// archived files are named after their domain, so the others needn't be opened
+				if let FilenameInfo::Archived { file_seqnum, .. } = file {
+					if file_seqnum != seqnum_id {
+						continue;
+					}
+				}
+
+				self.io.open(&T::make_filename(&file)).await?;
+				let header = Header::read(&mut self.io).await?;
+				let (Some(head), Some(tail)) = (header.head_entry_seqnum, header.tail_entry_seqnum)
+				else {
+					continue;
+				};
+				if header.seqnum_id == seqnum_id && (head..=tail).contains(&seqnum) {
+					return Ok(file);
+				}
+			}
+
+			Err(absolute_not_found(seqnum_id, seqnum))
+		}
+		.await;
+		let file = match scan {
+			Ok(file) => file,
+			Err(err) => {
+				self.restore_open(open).await?;
+				return Err(err);
+			}
+		};
+
+		self.open_and_load(&file).await?;
+		if self.seek_seqnum_in_current(seqnum).await? {
+			return Ok(());
+		}
+
+		// seqnums don't overlap between files of a domain, so it's not anywhere else either
+		Err(absolute_not_found(seqnum_id, seqnum))
+	}
+
+	/// Position on the first entry of a boot in the selected journal.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_boot(&mut self, boot_id: u128) -> std::io::Result<()> {
+		for file in self.selected_files().await? {
+			self.open_and_load(&file).await?;
+			if self.seek_boot_in_current(boot_id).await? {
+				return Ok(());
+			}
+		}
+
+		Err(boot_not_found(boot_id))
+	}
+
+	/// Position on the first entry of a boot in the current file.
+	///
+	/// Entries aren't indexed by boot, and a file can have several boots in any order (e.g. when
+	/// the clock went back), so this reads the entry headers in order until it finds one.
+	///
+	/// Returns whether there's such an entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_boot_in_current(&mut self, boot_id: u128) -> std::io::Result<bool> {
+		let chain = self.entry_array_chain().await?;
+		let total = chain.iter().map(|(_, len)| len).sum::<u64>();
+		for index in 0..total {
+			if self.entry_header_at(&chain, index).await?.boot_id.get() == boot_id {
+				self.set_position(&chain, index);
+				return Ok(true);
+			}
+		}
+
+		Ok(false)
+	}
+
+	/// Position on the entry nearest to a fraction of the way through the selected journal.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_fraction(&mut self, fraction: f64) -> std::io::Result<()> {
+		self.seek_index(|total| fraction_index(total, fraction))
+			.await
+	}
+
+	/// Position on the entry at an index across the selected journal, computed from the total
+	/// count of entries.
+	///
+	/// The counts come from the file headers, so only the entry arrays of the file the index
+	/// lands in are read. If there are no entries, this goes to the end as for [`Seek::Newest`].
+	#[tracing::instrument(level = "trace", skip(self, index))]
+	async fn seek_index(&mut self, index: impl FnOnce(u64) -> u64) -> std::io::Result<()> {
+		let files = self.selected_files().await?;
+		let open = self.io.current().map(Path::to_path_buf);
+		let scan = async {
+			let mut counts = Vec::with_capacity(files.len());
+			for file in &files {
+				self.io.open(&T::make_filename(file)).await?;
+				counts.push(Header::read(&mut self.io).await?.n_entries);
+			}
+			std::io::Result::Ok(counts)
+		}
+		.await;
+		let counts = match scan {
+			Ok(counts) => counts,
+			Err(err) => {
+				self.restore_open(open).await?;
+				return Err(err);
+			}
+		};
+
+		let mut index = index(counts.iter().sum());
+		for (file, count) in files.iter().zip(counts) {
+			if index < count {
+				self.open_and_load(file).await?;
+				return self.seek_index_in_current(index).await;
+			}
+			index -= count;
+		}
+
+		// there are no entries, so go to the end as for Seek::Newest
+		let Some(newest) = files.last() else {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::NotFound,
+				"no files found",
+			));
+		};
+		self.open_and_load(newest).await?;
+		self.skip_to_end().await
+	}
+
+	/// Position on the entry at an index in the current file, or its last entry if the index is
+	/// past the end (as when the header's count is off).
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_index_in_current(&mut self, index: u64) -> std::io::Result<()> {
+		let chain = self.entry_array_chain().await?;
+		match chain.iter().map(|(_, len)| len).sum::<u64>() {
+			0 => self.skip_to_end().await,
+			total => {
+				self.set_position(&chain, index.min(total - 1));
+				Ok(())
+			}
+		}
+	}
+
+	/// Position on the entry with exactly this seqnum in the current file.
+	///
+	/// Returns whether there's such an entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_seqnum_in_current(&mut self, seqnum: NonZeroU64) -> std::io::Result<bool> {
+		let chain = self.entry_array_chain().await?;
+		let total = chain.iter().map(|(_, len)| len).sum::<u64>();
+
+		// lower bound: first entry with a seqnum >= target
+		let (mut low, mut high) = (0, total);
+		while low < high {
+			let mid = low + (high - low) / 2;
+			if self.entry_key_at(&chain, mid, SeekKey::Seqnum).await? < seqnum.get() {
+				low = mid + 1;
+			} else {
+				high = mid;
+			}
+		}
+
+		if low < total && self.entry_key_at(&chain, low, SeekKey::Seqnum).await? == seqnum.get() {
+			self.set_position(&chain, low);
+			return Ok(true);
+		}
+
+		Ok(false)
+	}
+
+	/// Position on the entry at an offset in the current file.
+	///
+	/// The position is left as it was if there's no such entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_offset_in_current(&mut self, offset: NonZeroU64) -> std::io::Result<()> {
+		let entry = self.read_entry_at(offset.get()).await?;
+		// UNWRAP: read_entry_at() loads the current file
+		let previous = self.current.as_ref().unwrap().position.clone();
+		if self.seek_seqnum_in_current(entry.header.seqnum).await? {
+			let current = self.current.as_ref().unwrap();
+			let array = EntryArray::read_at(
+				&mut self.io,
+				current.position.entry_array_offset,
+				&current.header,
+			)
+			.await?;
+			// UNWRAP: seek_seqnum_in_current() positions on an entry when it finds one
+			let index = current.position.index.unwrap();
+			// another entry with the same seqnum is a corrupt file, but not this entry
+			if array.item_at(&mut self.io, index).await? == Some(offset) {
+				return Ok(());
+			}
+		}
+
+		self.current.as_mut().unwrap().position = previous;
+		Err(std::io::Error::new(
+			std::io::ErrorKind::NotFound,
+			format!("entry at {offset} is not in the entry arrays of the file"),
+		))
+	}
+
+	/// Seek within the file opened with [`open_file`](Self::open_file).
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_single_file(&mut self, path: &Path, seek: Seek) -> std::io::Result<()> {
+		self.io.open(path).await?;
+		self.load().await?;
+
+		match seek {
+			Seek::Oldest => Ok(()),
+			Seek::Newest => self.skip_to_end().await,
+			Seek::Timestamp(realtime) => {
+				self.seek_closest_in_current(realtime, SeekKey::Realtime, None)
+					.await
+			}
+			Seek::At(time) => {
+				self.seek_closest_in_current(at_realtime(time), SeekKey::Realtime, None)
+					.await
+			}
+			Seek::Seqnum(seqnum) => {
+				self.seek_closest_in_current(seqnum, SeekKey::Seqnum, None)
+					.await
+			}
+			Seek::Absolute { seqnum_id, seqnum } => {
+				// UNWRAP: load() sets current
+				if self.current.as_ref().unwrap().header.seqnum_id == seqnum_id
+					&& self.seek_seqnum_in_current(seqnum).await?
+				{
+					Ok(())
+				} else {
+					Err(absolute_not_found(seqnum_id, seqnum))
+				}
+			}
+			Seek::Fraction(fraction) => {
+				// UNWRAP: load() sets current
+				let total = self.current.as_ref().unwrap().header.n_entries;
+				self.seek_index_in_current(fraction_index(total, fraction))
+					.await
+			}
+			Seek::Offset(offset) => self.seek_offset_in_current(offset).await,
+			Seek::BootId(boot_id) => {
+				if self.seek_boot_in_current(boot_id).await? {
+					Ok(())
+				} else {
+					Err(boot_not_found(boot_id))
+				}
+			}
+			Seek::Entries(n) => Err(entries_unsupported(n)),
+		}
+	}
+
+	/// Position on the last entry of the current file, if there's any.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn seek_last_entry(&mut self) -> std::io::Result<()> {
+		self.seek_index_in_current(u64::MAX).await
+	}
+
+	/// Get the offsets and lengths of all entry arrays in the current file.
+	///
+	/// Only the last array in the chain is expected to be partially filled.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn entry_array_chain(&mut self) -> std::io::Result<Vec<(EntryArray, u64)>> {
+		self.load_if_needed().await?;
+		let header = &self.current.as_ref().unwrap().header;
+
+		let mut chain = Vec::new();
+		let mut walk = ChainWalk::new(Some(header.entry_array_offset), header.n_objects);
+		while let Some(offset) = walk.next()? {
+			let array = EntryArray::read_at(&mut self.io, offset, header).await?;
+			chain.push((array, array.len(&mut self.io).await?));
+			walk.link(array.next_offset.map_or(0, NonZeroU64::get))?;
+		}
+
+		tracing::trace!(?chain, "walked entry array chain");
+		Ok(chain)
+	}
+
+	/// Read the key of the entry at an index across the chain of entry arrays.
+	#[tracing::instrument(level = "trace", skip(self, chain))]
+	async fn entry_key_at(
+		&mut self,
+		chain: &[(EntryArray, u64)],
+		index: u64,
+		key: SeekKey,
+	) -> std::io::Result<u64> {
+		Ok(key.of_entry(&self.entry_header_at(chain, index).await?))
+	}
+
+	/// Read the header of the entry at an index across an entry array chain.
+	#[tracing::instrument(level = "trace", skip(self, chain))]
+	async fn entry_header_at(
+		&mut self,
+		chain: &[(EntryArray, u64)],
+		mut index: u64,
+	) -> std::io::Result<EntryObjectHeader> {
+		for (array, len) in chain {
+			if index >= *len {
+				index -= len;
+				continue;
+			}
+
+			let Some(entry_offset) = array.item_at(&mut self.io, index).await? else {
+				break;
+			};
+			ObjectHeader::read_at(&mut self.io, entry_offset.get())
+				.await?
+				.check_type(ObjectType::Entry)?;
+			return EntryObjectHeader::read_at(
+				&mut self.io,
+				entry_offset.get() + OBJECT_HEADER_SIZE,
+			)
+			.await;
+		}
+
+		Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			"entry index out of bounds",
+		))
+	}
+
+	/// Set the position to an index across the chain of entry arrays.
+	#[tracing::instrument(level = "trace", skip(self, chain))]
+	fn set_position(&mut self, chain: &[(EntryArray, u64)], mut index: u64) {
+		let current = self.current.as_mut().unwrap();
+		for (array, len) in chain {
+			if index < *len {
+				current.position = Position {
+					entry_array_offset: array.offset,
+					index: Some(index),
+				};
+				return;
+			}
+			index -= len;
+		}
+	}
+
+	/// Load the header and base structures of the current open file into memory.
+	///
+	/// Also set the position to the first entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn load(&mut self) -> std::io::Result<()> {
+		let header = Header::read(&mut self.io).await?;
+		header.check_compact_layout()?;
+		check_supported(header.incompatible_flags, supported_compression())?;
+		check_header_objects(&mut self.io, &header).await?;
+		self.load_header(header);
+		Ok(())
+	}
+
+	/// Set the current file from its header, positioned on the first entry.
+	fn load_header(&mut self, header: Header) {
+		if header.state == State::Online {
+			tracing::warn!(
+				file = ?self.io.current(),
+				"journal file is online, it may be being written to or its writer may have crashed"
+			);
+		}
+
+		self.io.set_read_ceiling(header.arena_end());
+		let position = Position {
+			entry_array_offset: header.entry_array_offset,
+			index: Some(0),
+		};
+		self.current = Some(CurrentFile {
+			path: self.io.current().map(Path::to_path_buf),
+			header,
+			position,
+			last_seqnum: None,
+		});
+	}
+
+	/// load_if_needed(), but error with `NotConnected` if no file is open.
+	///
+	/// You can unwrap self.current after calling this.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn load_open(&mut self) -> std::io::Result<()> {
+		if self.io.current().is_none() {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::NotConnected,
+				"no journal file open, select a journal first",
+			));
+		}
+
+		self.load_if_needed().await
+	}
+
+	/// load() only if needed.
+	///
+	/// If the open file isn't the loaded one, which happens when switching files is cancelled
+	/// between opening the new file and loading it, the loaded file is reopened so that its
+	/// position stays valid.
+	///
+	/// You can unwrap self.current after calling this.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn load_if_needed(&mut self) -> std::io::Result<()> {
+		match &self.current {
+			None => self.load().await?,
+			Some(CurrentFile {
+				path: Some(path), ..
+			}) if self.io.current() != Some(path.as_path()) => {
+				tracing::debug!(?path, "reopening the loaded file");
+				let path = path.clone();
+				self.io.open(&path).await?;
+			}
+			Some(_) => {}
+		}
+
+		Ok(())
+	}
+
+	/// Record a gap if the newly loaded file doesn't start right after the previous seqnum.
+	#[tracing::instrument(level = "trace", skip(self))]
+	fn check_seqnum_gap(&mut self, seqnum_id: NonZeroU128, previous: NonZeroU64) {
+		// UNWRAP: only called after load()
+		let header = &self.current.as_ref().unwrap().header;
+		let Some(head) = header.head_entry_seqnum else {
+			return;
+		};
+
+		// seqnums are only comparable within a seqnum_id domain
+		if header.seqnum_id != seqnum_id || head.get() <= previous.get().saturating_add(1) {
+			return;
+		}
+
+		tracing::warn!(
+			seqnum_id = format!("{seqnum_id:032x}"),
+			from = previous.get(),
+			to = head.get(),
+			missing = head.get() - previous.get() - 1,
+			file = ?self.io.current(),
+			"gap in sequence numbers between journal files, entries are missing"
+		);
+		self.gaps.push(SeqnumGap {
+			seqnum_id,
+			from: previous,
+			to: head,
+		});
+	}
+
+	/// Jump to the next entry array, at index 0.
+	///
+	/// If we're already at the end, does nothing and returns false. Errors with `InvalidData` if the
+	/// next array isn't further into the file, as the chain would otherwise go around forever.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn next_entry_array(&mut self) -> std::io::Result<bool> {
+		self.load_if_needed().await?;
+		let current = self.current.as_mut().unwrap();
+
+		let mut walk = ChainWalk::new(
+			Some(current.position.entry_array_offset),
+			current.header.n_objects,
+		);
+		// UNWRAP: the walk starts on the current array
+		let offset = walk.next()?.unwrap();
+		let entry_array = EntryArray::read_at(&mut self.io, offset, &current.header).await?;
+		// the link must go further into the file, so that skip_to_end() and entries() can't loop
+		walk.link(entry_array.next_offset.map_or(0, NonZeroU64::get))?;
+		if let Some(next) = walk.next()? {
+			current.position.entry_array_offset = next;
+			current.position.index = Some(0);
+			Ok(true)
+		} else {
+			Ok(false)
+		}
+	}
+
+	/// Find the Data objects of the priorities allowed by `max_priority` in the current file.
+	///
+	/// They're only looked up again if the file changed, or grew since they were.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn load_priority_data(&mut self) -> std::io::Result<()> {
+		let Some(level) = self.max_priority else {
+			return Ok(());
+		};
+
+		self.load_open().await?;
+		// UNWRAP: load_open() loads the current file
+		let header = &self.current.as_ref().unwrap().header;
+		let key = (header.file_id, header.tail_object_offset);
+		if self
+			.priority_data
+			.as_ref()
+			.is_some_and(|(loaded, _)| *loaded == key)
+		{
+			return Ok(());
+		}
+
+		let mut offsets = HashSet::new();
+		for priority in 0..=level {
+			if let Some(data) = self
+				.find_data(format!("PRIORITY={priority}").as_bytes())
+				.await?
+			{
+				offsets.insert(data.offset.get());
+			}
+		}
+		tracing::trace!(?level, ?offsets, "found data of the allowed priorities");
+		self.priority_data = Some((key, offsets));
+		Ok(())
+	}
+
+	/// Follow the chain of primary entry arrays until the last, and set position.
+	///
+	/// The position is on the first empty item of the last array if it isn't full, so that entries
+	/// added later are read from there.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn skip_to_end(&mut self) -> std::io::Result<()> {
+		while self.next_entry_array().await? {}
+
+		// UNWRAP: next_entry_array() depends on current being Some()
+		let current = self.current.as_mut().unwrap();
+		let array = EntryArray::read_at(
+			&mut self.io,
+			current.position.entry_array_offset,
+			&current.header,
+		)
+		.await?;
+		let len = array.len(&mut self.io).await?;
+		current.position.index = Some(len).filter(|len| *len < array.capacity());
+
+		Ok(())
+	}
+
+	/// Re-read the header of the current file, to see entries added since it was loaded.
+	///
+	/// The file is reopened first, so that this works with implementations which read files
+	/// whole. If it was replaced by another file, that file is loaded, positioned on its first
+	/// entry.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn refresh(&mut self) -> std::io::Result<()> {
+		self.load_open().await?;
+		// UNWRAP: load_open() loads the current file
+		if let Some(path) = self.current.as_ref().unwrap().path.clone() {
+			self.io.open(&path).await?;
+		}
+
+		let header = Header::read(&mut self.io).await?;
+		header.check_compact_layout()?;
+		self.io.set_read_ceiling(header.arena_end());
+		let current = self.current.as_mut().unwrap();
+		if header.file_id == current.header.file_id {
+			current.header = header;
+		} else {
+			tracing::debug!(file = ?self.io.current(), "file was replaced, loading the new one");
+			check_header_objects(&mut self.io, &header).await?;
+			self.load_header(header);
+		}
+
+		Ok(())
+	}
+}
+
+/// What to compare entries by when seeking.
+#[derive(Debug, Clone, Copy)]
+enum SeekKey {
+	Realtime,
+	Seqnum,
+}
+
+impl SeekKey {
+	fn of_entry(self, entry: &EntryObjectHeader) -> u64 {
+		match self {
+			Self::Realtime => timestamp_micros(entry.realtime),
+			Self::Seqnum => entry.seqnum.get(),
+		}
+	}
+
+	fn tail(self, header: &Header) -> Option<u64> {
+		match self {
+			Self::Realtime => header.tail_entry_realtime.map(timestamp_micros),
+			Self::Seqnum => header.tail_entry_seqnum.map(NonZeroU64::get),
+		}
+	}
+}
+
+fn boot_not_found(boot_id: u128) -> std::io::Error {
+	std::io::Error::new(
+		std::io::ErrorKind::NotFound,
+		format!("no entries of boot {boot_id:032x}"),
+	)
+}
+
+fn entries_unsupported(n: i64) -> std::io::Error {
+	std::io::Error::new(
+		std::io::ErrorKind::Unsupported,
+		format!("seeking by a number of entries ({n}) is not supported"),
+	)
+}
+
+/// The realtime to seek to for [`Seek::At`].
+fn at_realtime(time: Timestamp) -> u64 {
+	u64::try_from(time.as_microsecond()).unwrap_or(0)
+}
+
+fn absolute_not_found(seqnum_id: NonZeroU128, seqnum: NonZeroU64) -> std::io::Error {
+	std::io::Error::new(
+		std::io::ErrorKind::NotFound,
+		format!("no entry with seqnum {seqnum} in seqnum domain {seqnum_id:032x}"),
+	)
+}
+
+/// The index of the entry nearest to a fraction of the way through a number of entries.
+///
+/// See [`Seek::Fraction`].
+fn fraction_index(total: u64, fraction: f64) -> u64 {
+	let fraction = if fraction.is_nan() {
+		0.0
+	} else {
+		fraction.clamp(0.0, 1.0)
+	};
+	(total.saturating_sub(1) as f64 * fraction).round() as u64
+}
+
+/// Check that the objects a header points to are there and of the right types.
+///
+/// A corrupt header would otherwise only fail once the objects are used, with errors that don't
+/// say where the bad offset came from.
+async fn check_header_objects<T: AsyncFileRead>(
+	io: &mut T,
+	header: &Header,
+) -> std::io::Result<()> {
+	let invalid = |name: &'static str, offset: NonZeroU64| {
+		move |err: std::io::Error| {
+			std::io::Error::new(
+				err.kind(),
+				format!("header's {name} ({offset}) is invalid: {err}"),
+			)
+		}
+	};
+
+	// the root entry array is always there, and its size must fit the item size of the flag
+	EntryArray::read_at(io, header.entry_array_offset, header)
+		.await
+		.map_err(invalid("entry_array_offset", header.entry_array_offset))?;
+
+	// hash table offsets are of their items, after the object header
+	for (name, offset, size, r#type) in [
+		(
+			"data_hash_table_offset",
+			header.data_hash_table_offset,
+			header.data_hash_table_size,
+			ObjectType::DataHashTable,
+		),
+		(
+			"field_hash_table_offset",
+			header.field_hash_table_offset,
+			header.field_hash_table_size,
+			ObjectType::FieldHashTable,
+		),
+	] {
+		let object_offset = offset.get().checked_sub(OBJECT_HEADER_SIZE).ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"offset is before the end of an object header",
+			)
+		});
+		let object = match object_offset {
+			Ok(object_offset) => ObjectHeader::read_at(io, object_offset)
+				.await
+				.and_then(|object| object.check_type(r#type)),
+			Err(err) => Err(err),
+		}
+		.map_err(invalid(name, offset))?;
+		if object.payload_size() < size.get() {
+			return Err(invalid(name, offset)(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"table of {size} bytes doesn't fit in its object of {} bytes",
+					object.size
+				),
+			)));
+		}
+	}
+
+	Ok(())
+}
+
+/// The sequence number domain of the entry a sub-reader of a merge just read.
+fn merge_seqnum_id<T: AsyncFileRead>(reader: &JournalReader<T>) -> std::io::Result<NonZeroU128> {
+	reader
+		.position()
+		.map(|location| location.seqnum_id)
+		.ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"merged journal yielded an entry without a position",
+			)
+		})
+}
+
+fn timestamp_micros(ts: Timestamp) -> u64 {
+	u64::try_from(ts.as_microsecond()).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Seek {
+	/// Seek to just after the newest entry.
+	Newest,
+
+	/// Seek to just before the oldest entry.
+	Oldest,
+
+	/// Seek to the entry closest to the given timestamp (microseconds since the Unix epoch).
+	///
+	/// If the timestamp is exactly between two entries, seeks to the earlier one.
+	Timestamp(u64),
+
+	/// Seek to the entry closest to the given time.
+	///
+	/// This is [`Timestamp`](Self::Timestamp) with a [`jiff::Timestamp`], truncated to the
+	/// microsecond. The journal can't represent times before the Unix epoch, so those are taken as
+	/// the epoch: the closest entry is then the oldest one.
+	At(Timestamp),
+
+	/// Seek to the entry closest to the given sequence number.
+	///
+	/// If the sequence number falls in a gap, exactly between two entries, seeks to the earlier one.
+	Seqnum(u64),
+
+	/// Seek to the first entry of the given boot ID.
+	///
+	/// Entries aren't indexed by boot, so this reads entry headers from the oldest until it finds
+	/// one of the boot. Errors with `NotFound` if there are none in the selected journal.
+	BootId(u128),
+
+	/// Seek to the given number of entries before or after the current position.
+	///
+	/// This isn't supported yet, and errors with `Unsupported`: read and skip entries from the
+	/// current position instead, e.g. with [`JournalReader::entries`].
+	Entries(i64),
+
+	/// Seek to the entry with exactly this sequence number in a sequence number domain.
+	///
+	/// Unlike [`Seqnum`](Self::Seqnum), this doesn't go to the closest entry: it errors with
+	/// `NotFound` if there's no such entry in the selected journal.
+	Absolute {
+		seqnum_id: NonZeroU128,
+		seqnum: NonZeroU64,
+	},
+
+	/// Seek to the entry nearest to this fraction of the way through the selected journal, by
+	/// count of entries across files: `0.0` is the oldest entry and `1.0` the newest.
+	///
+	/// The fraction is clamped to `[0, 1]`, and NaN is taken as `0`. This is an approximate
+	/// position for scrubbing, e.g. with a scrollbar: the counts come from the file headers, so
+	/// only the entry arrays of the file it lands in are read.
+	Fraction(f64),
+
+	/// Seek to the entry at this offset in the current file.
+	///
+	/// This is for resuming from an entry offset that was kept from before, e.g. from
+	/// [`Entry::offset`], which is cheaper to store than a cursor but only makes sense within the
+	/// file it came from. The offset must be 8-byte aligned within the arena (or this errors with
+	/// `InvalidInput`), point at an Entry object (or this errors with `InvalidData`), and that entry
+	/// must be in the file's entry arrays (or this errors with `NotFound`).
+	///
+	/// The reader's position is kept as an entry array and an index in it rather than as the
+	/// offset of the entry, so that reading can go on from there: the entry is looked up in the
+	/// entry arrays by its sequence number, as they're sorted by it.
+	Offset(NonZeroU64),
+}
+
+impl Seek {
+	/// Alias of [`Oldest`](Self::Oldest), as in `sd_journal_seek_head()`.
+	pub const HEAD: Self = Self::Oldest;
+
+	/// Alias of [`Newest`](Self::Newest), as in `sd_journal_seek_tail()`.
+	pub const TAIL: Self = Self::Newest;
+}
+
+impl From<&EntryLocation> for Seek {
+	fn from(location: &EntryLocation) -> Self {
+		Self::Absolute {
+			seqnum_id: location.seqnum_id,
+			seqnum: location.seqnum,
+		}
+	}
+}
+
+/// Summary of a journal, from [`JournalReader::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionSummary {
+	/// The files of the journal, from oldest to newest.
+	pub files: Vec<FileSummary>,
+
+	/// The number of entries across all files.
+	pub n_entries: u64,
+
+	/// The lowest sequence number across all files.
+	///
+	/// Sequence numbers are only comparable within a domain, so this is only meaningful if all
+	/// files have the same `seqnum_id`.
+	pub head_seqnum: Option<NonZeroU64>,
+
+	/// The highest sequence number across all files.
+	///
+	/// Sequence numbers are only comparable within a domain, so this is only meaningful if all
+	/// files have the same `seqnum_id`.
+	pub tail_seqnum: Option<NonZeroU64>,
+
+	/// The earliest realtime timestamp across all files.
+	pub head_realtime: Option<Timestamp>,
+
+	/// The latest realtime timestamp across all files.
+	pub tail_realtime: Option<Timestamp>,
+}
+
+/// Summary of a journal file from its header, in a [`SelectionSummary`].
+///
+/// The fields are `None` if the file has no entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+	pub file: FilenameInfo,
+	pub seqnum_id: NonZeroU128,
+	pub n_entries: u64,
+	pub head_seqnum: Option<NonZeroU64>,
+	pub tail_seqnum: Option<NonZeroU64>,
+	pub head_realtime: Option<Timestamp>,
+	pub tail_realtime: Option<Timestamp>,
+}
+
+/// A problem in a hash chain, from [`JournalReader::verify_field_chains`].
+///
+/// `from` is the offset of the object, or of the hash table item, which holds the bad link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+	/// A link points outside of the arena, or to an offset which isn't 8-byte aligned.
+	Dangling { from: u64, to: u64 },
+
+	/// A link points to an object of the wrong type, or which is too small for its type.
+	WrongType {
+		from: u64,
+		to: u64,
+		found: ObjectType,
+	},
+
+	/// A chain has more links than there are objects in the file, so it loops.
+	///
+	/// `start` is where the chain starts: the hash table item of a chain of Fields, or the Field
+	/// of a chain of Data.
+	Cycle { start: u64 },
+
+	/// A Data object in the chain of a Field is for another field.
+	WrongField { field: u64, data: u64 },
+}
+
+/// The result of [`JournalReader::verify_all_report`], with a report for each file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+	pub files: Vec<FileReport>,
+}
+
+impl VerifyReport {
+	/// Whether no problem was found in any file.
+	pub fn is_clean(&self) -> bool {
+		self.files.iter().all(FileReport::is_clean)
+	}
+}
+
+/// How far a scan of a file has got, see [`JournalReader::set_progress_handler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+	/// The file being scanned, if its name can be parsed.
+	pub file: Option<FilenameInfo>,
+
+	/// How many objects of the file have been scanned.
+	///
+	/// Not every scan reads every object: verification goes through entries, their arrays, and
+	/// the data they reference. So this may jump to `objects_total` when the file is done, which
+	/// it always ends at.
+	pub objects_done: u64,
+
+	/// How many objects the file has, from its header.
+	pub objects_total: u64,
+}
+
+/// Reports the progress of a scan of a file to the progress handler, if there's one.
+struct ProgressReporter<'h> {
+	handler: Option<&'h ProgressHandler>,
+	progress: Progress,
+}
+
+impl<'h> ProgressReporter<'h> {
+	/// Start a scan, reporting that nothing is done yet.
+	fn start(
+		handler: Option<&'h ProgressHandler>,
+		file: Option<FilenameInfo>,
+		header: &Header,
+	) -> Self {
+		let reporter = Self {
+			handler,
+			progress: Progress {
+				file,
+				objects_done: 0,
+				objects_total: header.n_objects.get(),
+			},
+		};
+		reporter.report();
+		reporter
+	}
+
+	/// Count some objects as done, up to the total (which could be wrong in a corrupt file).
+	fn advance(&mut self, objects: u64) {
+		let progress = &mut self.progress;
+		progress.objects_done = progress
+			.objects_done
+			.saturating_add(objects)
+			.min(progress.objects_total);
+		self.report();
+	}
+
+	/// Finish the scan, reporting that everything is done.
+	fn finish(&mut self) {
+		self.progress.objects_done = self.progress.objects_total;
+		self.report();
+	}
+
+	fn report(&self) {
+		if let Some(handler) = self.handler {
+			handler(&self.progress);
+		}
+	}
+}
+
+/// The verification of one file, from [`JournalReader::verify_all_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+	pub file: FilenameInfo,
+
+	/// How many entries were checked.
+	pub entries: u64,
+
+	/// How many distinct Data objects were checked.
+	pub data: u64,
+
+	/// The problems found, in the order they were found.
+	pub errors: Vec<VerifyError>,
+
+	/// Whether the file is sealed, or `None` if its header couldn't be read.
+	pub seal: Option<SealStatus>,
+}
+
+impl FileReport {
+	/// Whether no problem was found in this file.
+	pub fn is_clean(&self) -> bool {
+		self.errors.is_empty()
+	}
+}
+
+/// A problem found in a file, from [`JournalReader::verify_all_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+	/// The hash stored in a Data object doesn't match its payload.
+	DataHash {
+		offset: u64,
+		stored: u64,
+		computed: u64,
+	},
+
+	/// The `xor_hash` stored in an Entry doesn't match the payloads of its Data objects.
+	XorHash {
+		offset: u64,
+		stored: u64,
+		computed: u64,
+	},
+
+	/// An Entry's monotonic time is before the previous entry's of the same boot.
+	///
+	/// Entries are checked in order, so `previous` is the latest monotonic time of the boot
+	/// before this entry.
+	MonotonicRegression {
+		offset: u64,
+		boot_id: u128,
+		previous: u64,
+		monotonic: u64,
+	},
+
+	/// A problem in a field hash chain, as from [`JournalReader::verify_field_chains`].
+	Chain(ChainError),
+
+	/// The file couldn't be read further.
+	///
+	/// `entry` is the offset of the Entry being checked, if any. Entries after it weren't checked.
+	Structure { entry: Option<u64>, message: String },
+}
+
+impl VerifyError {
+	/// Record corruption as a structural error, passing other IO errors through.
+	fn structure(err: std::io::Error, entry: Option<u64>) -> std::io::Result<Self> {
+		match err.kind() {
+			std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => {
+				Ok(Self::Structure {
+					entry,
+					message: err.to_string(),
+				})
+			}
+			_ => Err(err),
+		}
+	}
+}
+
+/// Whether a file is sealed with Forward Secure Sealing, from [`JournalReader::verify_all_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealStatus {
+	/// The file isn't sealed.
+	Unsealed,
+
+	/// The file is sealed, but its tags weren't verified, as this needs the verification key,
+	/// which isn't supported yet.
+	///
+	/// `tags` is the count from the header, which older files don't have.
+	Unverified { tags: Option<u64> },
+}
+
+impl SealStatus {
+	fn of(header: &Header) -> Self {
+		if header.compatible_flags.contains(CompatibleFlag::Sealed) {
+			Self::Unverified {
+				tags: header.n_tags,
+			}
+		} else {
+			Self::Unsealed
+		}
+	}
+}
+
+/// A gap in sequence numbers between files, from [`JournalReader::take_gaps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeqnumGap {
+	/// The sequence number domain of the files.
+	pub seqnum_id: NonZeroU128,
+
+	/// The last sequence number before the gap.
+	pub from: NonZeroU64,
+
+	/// The first sequence number after the gap.
+	pub to: NonZeroU64,
+}
+
+/// Where an entry is in a journal, from [`JournalReader::position`] or
+/// [`JournalReader::location_of`].
+///
+/// This gathers the identity of an entry, which is otherwise spread between the entry itself,
+/// the header of its file, and the name of its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLocation {
+	/// The file the entry is in, if its name can be parsed.
+	pub file: Option<FilenameInfo>,
+
+	/// Offset of the entry object in its file.
+	pub offset: NonZeroU64,
+
+	/// The sequence number domain of the entry's file.
+	pub seqnum_id: NonZeroU128,
+
+	pub seqnum: NonZeroU64,
+	pub realtime: Timestamp,
+	pub monotonic: Monotonic,
+	pub boot_id: NonZeroU128,
+}
+
+impl EntryLocation {
+	/// The location of an entry, given the `seqnum_id` and name of the file it was read from.
+	pub fn new(entry: &Entry, seqnum_id: NonZeroU128, file: Option<FilenameInfo>) -> Self {
+		Self {
+			file,
+			offset: entry.offset,
+			seqnum_id,
+			seqnum: entry.header.seqnum,
+			realtime: entry.header.realtime,
+			monotonic: entry.header.monotonic,
+			boot_id: entry.header.boot_id,
+		}
+	}
+}
+
+#[cfg(test)]
+async fn test_reader(
+	journals: &[crate::testing::JournalBuilder],
+) -> JournalReader<crate::testing::MemoryFiles> {
+	test_reader_with(crate::testing::MemoryFiles::default(), journals).await
+}
+
+#[cfg(test)]
+async fn test_reader_with(
+	mut files: crate::testing::MemoryFiles,
+	journals: &[crate::testing::JournalBuilder],
+) -> JournalReader<crate::testing::MemoryFiles> {
+	use crate::testing::MACHINE_ID;
+
+	let (latest, archived) = journals.split_last().unwrap();
+	files = files.with_file(&latest.latest_name(), latest.build());
+	for journal in archived {
+		files = files.with_file(&journal.archived_name(), journal.build());
+	}
+
+	let mut reader = JournalReader::new(files);
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	reader
+}
+
+#[cfg(test)]
+async fn test_next_seqnum<T: AsyncFileRead>(reader: &mut JournalReader<T>) -> Option<u64> {
+	reader
+		.entries()
+		.next()
+		.await
+		.map(|entry| entry.unwrap().header.seqnum.get())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_timestamp_closest() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.array_capacity(2)
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000));
+	let mut reader = test_reader(&[journal]).await;
+
+	for (target, expected) in [
+		(10, 1),
+		(1000, 1),
+		(2400, 2),
+		(2500, 2), // exactly between: prefer the earlier
+		(2600, 3),
+		(3000, 3),
+		(99999, 4),
+	] {
+		reader.seek(Seek::Timestamp(target)).await.unwrap();
+		assert_eq!(
+			test_next_seqnum(&mut reader).await,
+			Some(expected),
+			"seeking to {target}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_at() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000));
+	let mut reader = test_reader(&[journal]).await;
+
+	for (target, expected) in [
+		(Timestamp::MIN, 1),
+		(Timestamp::from_second(-1).unwrap(), 1),
+		(Timestamp::UNIX_EPOCH, 1),
+		// sub-microsecond precision is dropped
+		(Timestamp::from_nanosecond(2_500_999).unwrap(), 2),
+		(Timestamp::from_microsecond(2600).unwrap(), 3),
+		(Timestamp::MAX, 3),
+	] {
+		reader.seek(Seek::At(target)).await.unwrap();
+		assert_eq!(
+			test_next_seqnum(&mut reader).await,
+			Some(expected),
+			"seeking to {target}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_timestamp_closest_across_files() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let latest = JournalBuilder::new()
+		.entry(TestEntry::new(3, 4000))
+		.entry(TestEntry::new(4, 5000));
+	let mut reader = test_reader(&[archived, latest]).await;
+
+	for (target, expected) in [(2900, 2), (3000, 2), (3100, 3), (4500, 3), (4600, 4)] {
+		reader.seek(Seek::Timestamp(target)).await.unwrap();
+		assert_eq!(
+			test_next_seqnum(&mut reader).await,
+			Some(expected),
+			"seeking to {target}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_seqnum_gap() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(6, 3000))
+		.entry(TestEntry::new(7, 4000));
+	let mut reader = test_reader(&[journal]).await;
+
+	for (target, expected) in [(2, 2), (3, 2), (4, 2), (5, 6), (9, 7)] {
+		reader.seek(Seek::Seqnum(target)).await.unwrap();
+		assert_eq!(
+			test_next_seqnum(&mut reader).await,
+			Some(expected),
+			"seeking to {target}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_verify_all() {
+	use std::num::NonZeroUsize;
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let mut truncated = JournalBuilder::new()
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000))
+		.entry(TestEntry::new(5, 5000))
+		.build();
+	// cut off the last (empty) item of the entry array
+	truncated.truncate(truncated.len() - 4);
+	let latest = JournalBuilder::new().entry(TestEntry::new(6, 6000));
+
+	let files = MemoryFiles::default()
+		.with_file(&archived.archived_name(), archived.build())
+		.with_file(&latest.latest_name(), latest.build());
+	for concurrency in [1, 2] {
+		let options =
+			ReadOptions::default().with_concurrency(NonZeroUsize::new(concurrency).unwrap());
+		let mut reader = JournalReader::with_options(files.try_clone().unwrap(), options.clone());
+		assert!(
+			reader.verify_all().await.unwrap(),
+			"concurrency {concurrency}"
+		);
+
+		let files = files.try_clone().unwrap().with_file(
+			&FilenameInfo::Archived {
+				machine_id: crate::testing::MACHINE_ID,
+				scope: "system".into(),
+				file_seqnum: NonZeroU128::new(crate::testing::SEQNUM_ID).unwrap(),
+				head_seqnum: NonZeroU64::new(3).unwrap(),
+				head_realtime: Timestamp::from_microsecond(3000).unwrap(),
+			},
+			truncated.clone(),
+		);
+		let mut reader = JournalReader::with_options(files, options);
+		assert!(
+			!reader.verify_all().await.unwrap(),
+			"concurrency {concurrency}"
+		);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_verify_all_report() {
+	use std::num::NonZeroUsize;
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let find = |bytes: &[u8], needle: u64| {
+		bytes
+			.windows(8)
+			.position(|window| window == needle.to_le_bytes())
+			.unwrap()
+	};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000).with_field("A=1"));
+	let mut corrupt = archived.build();
+	// the hash is the first field of the Data header, and the xor_hash the last of the Entry's
+	let data_hash = jenkins_hash64(b"MESSAGE=entry 1");
+	let data = find(&corrupt, data_hash);
+	corrupt[data] ^= 1;
+	let xor_hash = jenkins_hash64(b"MESSAGE=entry 2") ^ jenkins_hash64(b"A=1");
+	let entry = find(&corrupt, xor_hash);
+	corrupt[entry] ^= 1;
+	let latest = JournalBuilder::new()
+		.sealed(true)
+		.entry(TestEntry::new(3, 3000));
+
+	let files = MemoryFiles::default()
+		.with_file(&archived.archived_name(), corrupt)
+		.with_file(&latest.latest_name(), latest.build());
+	for concurrency in [1, 2] {
+		let options =
+			ReadOptions::default().with_concurrency(NonZeroUsize::new(concurrency).unwrap());
+		let mut reader = JournalReader::with_options(files.try_clone().unwrap(), options);
+		let report = reader.verify_all_report().await.unwrap();
+		assert!(!report.is_clean(), "concurrency {concurrency}");
+
+		let [archived_report, latest_report] = &report.files[..] else {
+			panic!("expected two files, got {report:?}");
+		};
+		assert_eq!(archived_report.file, archived.archived_name());
+		assert_eq!((archived_report.entries, archived_report.data), (2, 3));
+		assert_eq!(archived_report.seal, Some(SealStatus::Unsealed));
+		assert_eq!(
+			archived_report.errors,
+			vec![
+				VerifyError::DataHash {
+					offset: data as u64 - OBJECT_HEADER_SIZE,
+					stored: data_hash ^ 1,
+					computed: data_hash,
+				},
+				VerifyError::XorHash {
+					offset: entry as u64 - OBJECT_HEADER_SIZE - 40,
+					stored: xor_hash ^ 1,
+					computed: xor_hash,
+				},
+			],
+			"concurrency {concurrency}"
+		);
+
+		assert!(latest_report.is_clean(), "concurrency {concurrency}");
+		assert_eq!(latest_report.file, latest.latest_name());
+		assert_eq!((latest_report.entries, latest_report.data), (1, 1));
+		assert!(matches!(
+			latest_report.seal,
+			Some(SealStatus::Unverified { .. })
+		));
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_data_separate_handle() {
+	use futures_util::AsyncSeekExt as _;
+
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_field("PRIORITY=6"))
+		.entry(TestEntry::new(2, 2000));
+	let mut reader = test_reader(&[journal]).await;
+
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	let cursor = reader.io.seek(std::io::SeekFrom::Current(0)).await.unwrap();
+
+	let data = reader
+		.entry_data(&entry)
+		.map(|datum| datum.map(|datum| (datum.key, datum.value)))
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<std::io::Result<Vec<_>>>()
+		.unwrap();
+	assert_eq!(
+		data,
+		vec![
+			("MESSAGE".into(), "entry 1".into()),
+			("PRIORITY".into(), "6".into())
+		]
+	);
+	assert_eq!(
+		reader.io.seek(std::io::SeekFrom::Current(0)).await.unwrap(),
+		cursor
+	);
+	assert_eq!(
+		reader.data_io.as_ref().unwrap().current(),
+		reader.io.current()
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_data_between_entries() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journals = [
+		JournalBuilder::new()
+			.array_capacity(2)
+			.entry(TestEntry::new(1, 1000))
+			.entry(TestEntry::new(2, 2000))
+			.entry(TestEntry::new(3, 3000)),
+		JournalBuilder::new()
+			.entry(TestEntry::new(4, 4000))
+			.entry(TestEntry::new(5, 5000)),
+	];
+
+	for files in [
+		MemoryFiles::default(),
+		MemoryFiles::default().without_clone(),
+	] {
+		let mut reader = test_reader_with(files, &journals).await;
+		reader.seek(Seek::Oldest).await.unwrap();
+
+		let mut seqnums = Vec::new();
+		// each entries() stream is dropped after one entry, as if paused to read its data
+		loop {
+			let Some(entry) = reader.entries().next().await else {
+				break;
+			};
+			let entry = entry.unwrap();
+			let message = reader.entry_data(&entry).next().await.unwrap().unwrap();
+			assert_eq!(
+				message.value,
+				format!("entry {}", entry.header.seqnum),
+				"data of entry {}",
+				entry.header.seqnum
+			);
+			seqnums.push(entry.header.seqnum.get());
+		}
+
+		assert_eq!(seqnums, vec![1, 2, 3, 4, 5]);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_is_dirty() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	for online in [false, true] {
+		let mut reader = test_reader(&[JournalBuilder::new()
+			.online(online)
+			.entry(TestEntry::new(1, 1000))])
+		.await;
+		assert_eq!(reader.is_dirty(), None);
+
+		assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+		assert_eq!(reader.is_dirty(), Some(online));
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_merged_entries() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let system = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 3000))
+		.entry(TestEntry::new(3, 4000));
+	let user = JournalBuilder::new()
+		.seqnum_id(0x5eb6a35d3a5d4c5f8fb7e0c7a4d6b8e1)
+		.entry(TestEntry::new(1, 2000))
+		.entry(TestEntry::new(2, 4000))
+		.entry(TestEntry::new(3, 5000));
+	let user_selection = JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: "user-1000".into(),
+	};
+
+	let files = MemoryFiles::default()
+		.with_file(&system.latest_name(), system.build())
+		.with_file(
+			&FilenameInfo::Latest {
+				machine_id: MACHINE_ID,
+				scope: user_selection.scope.clone(),
+			},
+			user.build(),
+		);
+	let mut reader = JournalReader::new(files);
+	let system_selection = JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+
+	let merged = reader
+		.merged_entries(&[system_selection.clone(), user_selection.clone()])
+		.map(|entry| {
+			let (selection, entry) = entry.unwrap();
+			(
+				selection.scope,
+				entry.header.seqnum.get(),
+				entry.header.realtime.as_microsecond(),
+			)
+		})
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(
+		merged,
+		vec![
+			("system".into(), 1, 1000),
+			("user-1000".into(), 1, 2000),
+			("system".into(), 2, 3000),
+			// same realtime, so ordered by seqnum even across domains
+			("user-1000".into(), 2, 4000),
+			("system".into(), 3, 4000),
+			("user-1000".into(), 3, 5000),
+		]
+	);
+	assert_eq!(reader.selection(), None);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_merged_entries_max_open_files() {
+	use std::num::NonZeroUsize;
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// four journals with interleaved entries, so the merge goes back and forth between them
+	let mut journals = Vec::new();
+	let mut selections = Vec::new();
+	for journal in 0..4 {
+		let scope = format!("user-{journal}");
+		let builder = (0..3).fold(
+			JournalBuilder::new().seqnum_id(0x1000 + journal as u128),
+			|builder, n| builder.entry(TestEntry::new(n + 1, 1000 * n + 100 * journal + 100)),
+		);
+		journals.push((
+			FilenameInfo::Latest {
+				machine_id: MACHINE_ID,
+				scope: scope.clone(),
+			},
+			builder.build(),
+		));
+		selections.push(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope,
+		});
+	}
+	let files = || {
+		journals
+			.iter()
+			.fold(MemoryFiles::default(), |files, (info, bytes)| {
+				files.with_file(info, bytes.clone())
+			})
+	};
+
+	for (max_open_files, peak) in [(8, 4), (2, 2), (1, 1)] {
+		// clones share the count of open handles
+		let files = files();
+		let handles = files.try_clone().unwrap();
+		let mut reader = JournalReader::with_options(
+			files,
+			ReadOptions::default().with_max_open_files(NonZeroUsize::new(max_open_files).unwrap()),
+		);
+		let merged = reader
+			.merged_entries(&selections)
+			.map(|entry| entry.unwrap().1.header.realtime.as_microsecond())
+			.collect::<Vec<_>>()
+			.await;
+		assert_eq!(
+			merged,
+			(0..3)
+				.flat_map(|n| (0..4).map(move |journal| 1000 * n + 100 * journal + 100))
+				.collect::<Vec<_>>(),
+			"max {max_open_files}"
+		);
+		assert_eq!(handles.peak_open(), peak, "max {max_open_files}");
+	}
+
+	// the reader's own open file takes the only one allowed
+	let mut reader = JournalReader::with_options(
+		files(),
+		ReadOptions::default().with_max_open_files(NonZeroUsize::new(1).unwrap()),
+	);
+	reader.select(selections[0].clone()).await.unwrap();
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(
+		reader
+			.merged_entries(&selections)
+			.next()
+			.await
+			.unwrap()
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::InvalidInput
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_follow() {
+	use std::time::Duration;
+
+	use crate::{
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+		writer::{CreateOptions, JournalWriter},
+	};
+
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+
+	// the file as it's written: 2 entries, then 3, then 6 (past the first entry array)
+	let mut writer = JournalWriter::with_options(
+		MemoryFiles::default(),
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system"),
+	);
+	let mut snapshots = Vec::new();
+	for n in 1..=6 {
+		let fields = [("MESSAGE".to_string(), format!("entry {n}").into())];
+		writer.write_entry(fields.into_iter()).await.unwrap();
+		if [2, 3, 6].contains(&n) {
+			writer.sync().await.unwrap();
+			snapshots.push(writer.io().bytes(&latest).unwrap());
+		}
+	}
+	writer.close().await.unwrap();
+
+	let mut snapshots = snapshots.into_iter();
+	let files = MemoryFiles::default()
+		.with_file(&latest, snapshots.next().unwrap())
+		.with_change(None)
+		.with_change(None)
+		.with_change(Some((&latest, snapshots.next().unwrap())))
+		.with_change(None)
+		.with_change(Some((&latest, snapshots.next().unwrap())));
+	let ms = Duration::from_millis;
+	let mut reader = JournalReader::with_options(
+		files,
+		ReadOptions::default().with_follow_interval(ms(1), ms(3)),
+	);
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+
+	let seqnums = reader
+		.follow()
+		.take(6)
+		.map(|entry| entry.unwrap().seqnum().get())
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(seqnums, (1..=6).collect::<Vec<_>>());
+	// backing off while there's nothing new, up to the maximum, and back down on new entries
+	assert_eq!(reader.io.waits, vec![ms(1), ms(2), ms(3), ms(1), ms(2)]);
+
+	// from the end, only new entries are followed
+	let files = MemoryFiles::default()
+		.with_file(&latest, writer.io().bytes(&latest).unwrap())
+		.with_change(None);
+	let mut reader = JournalReader::with_options(
+		files,
+		ReadOptions::default().with_follow_interval(ms(1), ms(1)),
+	);
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	reader.seek(Seek::Newest).await.unwrap();
+	let follow = reader.follow();
+	let timeout = crate::reader::sleep(ms(50));
+	futures_util::pin_mut!(timeout);
+	let first = futures_util::future::select(follow.into_future(), timeout).await;
+	assert!(matches!(first, futures_util::future::Either::Right(_)));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_merged_entries_dedup() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// the "copy" journal shares the system journal's seqnum domain and overlaps it by 3 entries
+	let system = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000))
+		.entry(TestEntry::new(5, 5000));
+	let copy = JournalBuilder::new()
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000))
+		.entry(TestEntry::new(5, 5000))
+		.entry(TestEntry::new(6, 6000));
+	// a different domain, with a copy of an entry under another seqnum (only its identity gives it
+	// away), and an entry at the same realtime and seqnum as an overlapping one
+	let user = JournalBuilder::new()
+		.seqnum_id(0x5eb6a35d3a5d4c5f8fb7e0c7a4d6b8e1)
+		.entry(TestEntry {
+			seqnum: 1,
+			..TestEntry::new(2, 2000)
+		})
+		.entry(TestEntry::new(4, 4000).with_field("_UID=1000"));
+
+	let selection = |scope: &str| JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: scope.into(),
+	};
+	let latest = |scope: &str| FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: scope.into(),
+	};
+	let files = MemoryFiles::default()
+		.with_file(&latest("system"), system.build())
+		.with_file(&latest("copy"), copy.build())
+		.with_file(&latest("user-1000"), user.build());
+	let selections = [
+		selection("system"),
+		selection("user-1000"),
+		selection("copy"),
+	];
+
+	let merge = |dedup| {
+		let files = files.try_clone().unwrap();
+		let selections = selections.clone();
+		async move {
+			let mut reader =
+				JournalReader::with_options(files, ReadOptions::default().with_dedup_merged(dedup));
+			reader
+				.merged_entries(&selections)
+				.map(|entry| {
+					let (selection, entry) = entry.unwrap();
+					(selection.scope, entry.header.seqnum.get())
+				})
+				.collect::<Vec<_>>()
+				.await
+		}
+	};
+
+	assert_eq!(merge(false).await.len(), 11);
+	assert_eq!(
+		merge(true).await,
+		vec![
+			("system".into(), 1),
+			// the copy sorts first for its lower seqnum, and is the one yielded
+			("user-1000".into(), 1),
+			("system".into(), 3),
+			("system".into(), 4),
+			("user-1000".into(), 4),
+			("system".into(), 5),
+			("copy".into(), 6),
+		]
+	);
+
+	// copies with the same seqnum come from the earliest selection
+	let selections = [
+		selection("copy"),
+		selection("user-1000"),
+		selection("system"),
+	];
+	let mut reader = JournalReader::with_options(
+		files.try_clone().unwrap(),
+		ReadOptions::default().with_dedup_merged(true),
+	);
+	let merged = reader
+		.merged_entries(&selections)
+		.map(|entry| {
+			let (selection, entry) = entry.unwrap();
+			(selection.scope, entry.header.seqnum.get())
+		})
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(
+		merged,
+		vec![
+			("system".into(), 1),
+			("user-1000".into(), 1),
+			("copy".into(), 3),
+			("copy".into(), 4),
+			("user-1000".into(), 4),
+			("copy".into(), 5),
+			("copy".into(), 6),
+		]
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_seqnum_domains() {
+	use crate::testing::{JournalBuilder, TestEntry, SEQNUM_ID};
+
+	let other_domain = 0xf1e2d3c4b5a6978877665544332211ff;
+	let mut reader = test_reader(&[
+		JournalBuilder::new()
+			.entry(TestEntry::new(1, 1000))
+			.entry(TestEntry::new(2, 2000)),
+		// higher seqnums, but not comparable to the others
+		JournalBuilder::new()
+			.seqnum_id(other_domain)
+			.entry(TestEntry::new(5, 5000)),
+		JournalBuilder::new().entry(TestEntry::new(3, 3000)),
+	])
+	.await;
+	reader.seek(Seek::Oldest).await.unwrap();
+
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+	assert_eq!(reader.current_seqnum_id(), NonZeroU128::new(SEQNUM_ID));
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(3));
+	assert_eq!(test_next_seqnum(&mut reader).await, None);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_data_mistyped() {
+	use crate::{
+		objects::ENTRY_OBJECT_HEADER_SIZE,
+		testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID},
+	};
+
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000).with_field("FOO=bar"));
+	let entry = {
+		let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+		let entry = reader.entries().next().await.unwrap().unwrap();
+		entry
+	};
+
+	// point the first data reference of the entry at the entry itself
+	let mut bytes = journal.build();
+	let item = (entry.offset.get() + OBJECT_HEADER_SIZE + ENTRY_OBJECT_HEADER_SIZE) as usize;
+	bytes[item..item + 4].copy_from_slice(&(entry.offset.get() as u32).to_le_bytes());
+
+	for tolerant in [false, true] {
+		let files = MemoryFiles::default().with_file(&journal.latest_name(), bytes.clone());
+		let mut reader =
+			JournalReader::with_options(files, ReadOptions::default().with_tolerant(tolerant));
+		reader
+			.select(JournalSelection {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		let entry = reader.entries().next().await.unwrap().unwrap();
+		let data = reader.entry_data(&entry).collect::<Vec<_>>().await;
+
+		if tolerant {
+			let data = data
+				.into_iter()
+				.map(|datum| {
+					let datum = datum.unwrap();
+					format!("{}={}", datum.key, datum.value)
+				})
+				.collect::<Vec<_>>();
+			assert_eq!(data, vec!["FOO=bar"]);
+		} else {
+			assert_eq!(data.len(), 1);
+			assert_eq!(
+				data[0].as_ref().unwrap_err().kind(),
+				std::io::ErrorKind::InvalidData
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_between() {
+	use std::ops::Bound;
+
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let latest = JournalBuilder::new()
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000))
+		.entry(TestEntry::new(5, 5000));
+	let mut reader = test_reader(&[archived, latest]).await;
+
+	for (range, expected) in [
+		((Bound::Included(1500), Bound::Excluded(4000)), vec![2, 3]),
+		(
+			(Bound::Included(1500), Bound::Included(4000)),
+			vec![2, 3, 4],
+		),
+		((Bound::Excluded(2000), Bound::Unbounded), vec![3, 4, 5]),
+		((Bound::Unbounded, Bound::Included(1000)), vec![1]),
+		((Bound::Included(5500), Bound::Unbounded), vec![]),
+	] {
+		let seqnums = reader
+			.entries_between(range)
+			.map(|entry| entry.unwrap().header.seqnum.get())
+			.collect::<Vec<_>>()
+			.await;
+		assert_eq!(seqnums, expected, "{range:?}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_read_entry_at() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let mut reader = test_reader(&[JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000))])
+	.await;
+
+	let second = {
+		let mut entries = reader.entries();
+		entries.next().await.unwrap().unwrap();
+		entries.next().await.unwrap().unwrap()
+	};
+
+	let entry = reader.read_entry_at(second.offset.get()).await.unwrap();
+	assert_eq!(entry, second);
+	// the position is unchanged
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(3));
+
+	for offset in [second.offset.get() + 4, 8, u64::MAX - 7] {
+		assert_eq!(
+			reader.read_entry_at(offset).await.unwrap_err().kind(),
+			std::io::ErrorKind::InvalidInput,
+			"{offset}"
+		);
+	}
+
+	let mut reader = JournalReader::new(crate::testing::MemoryFiles::default());
+	assert_eq!(
+		reader.read_entry_at(8).await.unwrap_err().kind(),
+		std::io::ErrorKind::NotConnected
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_current_file() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let latest = JournalBuilder::new().entry(TestEntry::new(3, 3000));
+	let mut reader = test_reader(&[archived.clone(), latest.clone()]).await;
+	assert_eq!(reader.current_header(), None);
+	reader.seek(Seek::Oldest).await.unwrap();
+
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+	assert_eq!(reader.current_file(), Some(archived.archived_name()));
+	assert_eq!(reader.current_header().unwrap().n_entries, 2);
+
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(3));
+	assert_eq!(reader.current_file(), Some(latest.latest_name()));
+	assert_eq!(reader.current_header().unwrap().n_entries, 1);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_sealed_without_key() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	// tags are interleaved with entries, and are never referenced by entry arrays
+	let archived = JournalBuilder::new()
+		.sealed(true)
+		.array_capacity(2)
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000));
+	let latest = JournalBuilder::new()
+		.sealed(true)
+		.entry(TestEntry::new(4, 4000).with_field("FOO=bar"));
+	let mut reader = test_reader(&[archived, latest]).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+
+	let mut seqnums = Vec::new();
+	loop {
+		let Some(entry) = reader.entries().next().await else {
+			break;
+		};
+		let entry = entry.unwrap();
+		seqnums.push(entry.header.seqnum.get());
+		let data = reader.entry_data(&entry).collect::<Vec<_>>().await;
+		assert_eq!(data.len(), entry.objects.len());
+		assert!(data.iter().all(Result::is_ok));
+	}
+	assert_eq!(seqnums, vec![1, 2, 3, 4]);
+	assert!(reader
+		.current_header()
+		.unwrap()
+		.compatible_flags
+		.contains(CompatibleFlag::Sealed));
+
+	assert!(reader.verify_all().await.unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_most_common_data() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let mut reader = test_reader(&[JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_field("FOO=bar"))
+		.entry(TestEntry::new(2, 2000).with_field("FOO=bar"))
+		.entry(
+			TestEntry::new(3, 3000)
+				.with_field("FOO=bar")
+				.with_field("BAZ=qux"),
+		)
+		.entry(TestEntry::new(4, 4000).with_field("BAZ=qux"))])
+	.await;
+
+	assert_eq!(
+		reader.most_common_data(2).await.unwrap(),
+		vec![("FOO=bar".into(), 3), ("BAZ=qux".into(), 2)]
+	);
+	assert_eq!(reader.most_common_data(10).await.unwrap().len(), 6);
+	assert_eq!(reader.most_common_data(0).await.unwrap(), vec![]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_resume() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let archived = (1..=5).fold(JournalBuilder::new().array_capacity(2), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = (6..=8).fold(JournalBuilder::new().array_capacity(2), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+
+	for n in 1..=8 {
+		let mut reader = test_reader(&[archived.clone(), latest.clone()]).await;
+		reader.seek(Seek::Oldest).await.unwrap();
+
+		let mut seqnums = reader
+			.entries()
+			.take(n)
+			.map(|entry| entry.unwrap().header.seqnum.get())
+			.collect::<Vec<_>>()
+			.await;
+		seqnums.extend(
+			reader
+				.entries()
+				.map(|entry| entry.unwrap().header.seqnum.get())
+				.collect::<Vec<_>>()
+				.await,
+		);
+		assert_eq!(seqnums, (1..=8).collect::<Vec<_>>(), "dropped after {n}");
+	}
+
+	// as if switching to the next file was cancelled after opening it, before loading it
+	let mut reader = test_reader(&[archived.clone(), latest.clone()]).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(reader.entries().take(5).count().await, 5);
+	reader
+		.io
+		.open(&MemoryFiles::make_filename(&latest.latest_name()))
+		.await
+		.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(6));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_find_data() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let journal = (1..=3).fold(
+		JournalBuilder::new().data_hash_table_capacity(1),
+		|journal, n| journal.entry(TestEntry::new(n, n * 1000)),
+	);
+
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	let data = reader.find_data(b"MESSAGE=entry 2").await.unwrap().unwrap();
+	assert_eq!(data.value, "entry 2");
+	assert!(reader
+		.find_data(b"MESSAGE=entry 4")
+		.await
+		.unwrap()
+		.is_none());
+	assert_eq!(reader.observed_max_chain_depth(), None);
+
+	let files = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+	let mut reader =
+		JournalReader::with_options(files, ReadOptions::default().with_diagnostics(true));
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(reader.observed_max_chain_depth(), Some(0));
+
+	// a miss walks the whole chain, which is as deep as the builder recorded
+	assert!(reader
+		.find_data(b"MESSAGE=entry 4")
+		.await
+		.unwrap()
+		.is_none());
+	assert_eq!(
+		reader.observed_max_chain_depth(),
+		reader.current_header().unwrap().data_hash_chain_depth
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_position_absolute() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = (1..=3).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = (4..=6).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let mut reader = test_reader(&[archived, latest]).await;
+
+	reader.seek(Seek::HEAD).await.unwrap();
+	assert_eq!(reader.position(), None);
+	let entries = reader.entries().take(4).collect::<Vec<_>>().await;
+	let location = reader.position().unwrap().clone();
+	assert_eq!(location.seqnum.get(), 4);
+	assert_eq!(location.offset, entries[3].as_ref().unwrap().offset);
+	assert!(location.file.as_ref().unwrap().is_latest());
+
+	reader
+		.seek(Seek::Absolute {
+			seqnum_id: location.seqnum_id,
+			seqnum: NonZeroU64::new(2).unwrap(),
+		})
+		.await
+		.unwrap();
+	assert_eq!(reader.position(), None);
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	assert!(reader
+		.position()
+		.unwrap()
+		.file
+		.as_ref()
+		.unwrap()
+		.is_archived());
+
+	reader.seek((&location).into()).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(4));
+	assert_eq!(reader.position(), Some(&location));
+
+	for (seqnum_id, seqnum) in [
+		(location.seqnum_id.get(), 7),
+		(location.seqnum_id.get() + 1, 2),
+	] {
+		let err = reader
+			.seek(Seek::Absolute {
+				seqnum_id: NonZeroU128::new(seqnum_id).unwrap(),
+				seqnum: NonZeroU64::new(seqnum).unwrap(),
+			})
+			.await
+			.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_object_too_small() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000));
+	let (entry, array) = {
+		let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+		let entry = reader.entries().next().await.unwrap().unwrap();
+		(
+			entry.offset.get(),
+			reader.current_header().unwrap().entry_array_offset.get(),
+		)
+	};
+
+	// sizes that are too small for the fixed part of an entry and an entry array
+	for (offset, size) in [(entry, 20_u64), (array, 17)] {
+		let mut bytes = journal.build();
+		let at = offset as usize + 8;
+		bytes[at..at + 8].copy_from_slice(&size.to_le_bytes());
+
+		let mut reader =
+			JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+		reader
+			.select(JournalSelection {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		let err = reader.entries().next().await.unwrap().unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{err}");
+		assert!(err.to_string().contains("too small"), "{err}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_summary() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = (1..=3).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = (4..=6).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let mut reader = test_reader(&[archived, latest]).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+
+	let summary = reader.summary().await.unwrap();
+	assert_eq!(summary.n_entries, 6);
+	assert_eq!(summary.head_seqnum.map(NonZeroU64::get), Some(1));
+	assert_eq!(summary.tail_seqnum.map(NonZeroU64::get), Some(6));
+	assert_eq!(
+		summary.head_realtime,
+		Some(Timestamp::from_microsecond(1000).unwrap())
+	);
+	assert_eq!(
+		summary.tail_realtime,
+		Some(Timestamp::from_microsecond(6000).unwrap())
+	);
+	assert_eq!(
+		summary
+			.files
+			.iter()
+			.map(|file| (file.file.is_latest(), file.n_entries))
+			.collect::<Vec<_>>(),
+		vec![(false, 3), (true, 3)]
+	);
+
+	// the position is untouched
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+}
+
+#[test]
+fn test_scope() {
+	for (scope, parsed) in [
+		("system", Scope::System),
+		("user-0", Scope::User(0)),
+		("user-1000", Scope::User(1000)),
+		("system.foo", Scope::Namespace("foo".into())),
+		("user-nobody", Scope::Other("user-nobody".into())),
+		("user-1000.foo", Scope::Other("user-1000.foo".into())),
+		("custom", Scope::Other("custom".into())),
+	] {
+		let selection = JournalSelection {
+			machine_id: 1,
+			scope: scope.into(),
+		};
+		assert_eq!(selection.as_scope(), parsed, "{scope}");
+		assert_eq!(parsed.to_string(), scope);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_data_with_offsets() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let mut reader = test_reader(&[JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_field("FOO=bar"))
+		.entry(TestEntry::new(2, 2000).with_field("FOO=bar"))])
+	.await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	let first = reader.entries().next().await.unwrap().unwrap();
+	let second = reader.entries().next().await.unwrap().unwrap();
+
+	let mut shared = None;
+	for entry in [first, second] {
+		let data = reader
+			.entry_data_with_offsets(&entry)
+			.try_collect::<Vec<_>>()
+			.await
+			.unwrap();
+		assert_eq!(
+			data.iter().map(|(offset, _)| *offset).collect::<Vec<_>>(),
+			entry.objects
+		);
+		for (offset, data) in &data {
+			assert_eq!(data.offset.get(), u64::from(offset.get()));
+		}
+
+		// the data object for the shared field is deduplicated between entries
+		let (offset, _) = data.iter().find(|(_, data)| data.key == "FOO").unwrap();
+		assert_eq!(*shared.get_or_insert(*offset), *offset);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_open_file() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let archived = (1..=3).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = (4..=6).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let files = MemoryFiles::default()
+		.with_file(&latest.latest_name(), latest.build())
+		.with_file(&archived.archived_name(), archived.build());
+	let path = MemoryFiles::make_filename(&archived.archived_name());
+
+	let mut reader = JournalReader::new(files);
+	reader.open_file(&path).await.unwrap();
+	assert_eq!(
+		reader.selection(),
+		Some(&JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+	);
+
+	// only the opened file is read, even though the journal continues in the latest file
+	let seqnums = reader
+		.entries()
+		.map(|entry| entry.unwrap().header.seqnum.get())
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(seqnums, vec![1, 2, 3]);
+
+	reader.seek(Seek::Seqnum(5)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(3));
+	reader.seek(Seek::Timestamp(2000)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+	reader.seek(Seek::Newest).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, None);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_gaps() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = |seqnums: std::ops::RangeInclusive<u64>| {
+		seqnums.fold(JournalBuilder::new(), |journal, n| {
+			journal.entry(TestEntry::new(n, n * 1000))
+		})
+	};
+	let mut reader = test_reader(&[journal(1..=2), journal(4..=5), journal(7..=8)]).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	let entries = reader.entries().collect::<Vec<_>>().await;
+	assert_eq!(entries.len(), 6);
+
+	// between archived files, and between the last archived file and the latest one
+	let gaps = reader.take_gaps();
+	assert_eq!(
+		gaps.iter()
+			.map(|gap| (gap.from.get(), gap.to.get()))
+			.collect::<Vec<_>>(),
+		vec![(2, 4), (5, 7)]
+	);
+	assert_eq!(Some(gaps[0].seqnum_id), reader.current_seqnum_id());
+	assert!(reader.take_gaps().is_empty());
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Seek {
-	/// Seek to just after the newest entry.
-	Newest,
+#[cfg(all(test, feature = "digest"))]
+#[tokio::test]
+async fn test_file_digest() {
+	use sha2::{Digest as _, Sha256};
 
-	/// Seek to just before the oldest entry.
-	Oldest,
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
 
-	/// Seek to the entry closest to the given timestamp.
-	Timestamp(u64),
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_field("A=1"))
+		.entry(TestEntry::new(2, 2000).with_field("A=2"));
+	let bytes = journal.build();
+	let expected: [u8; 32] = Sha256::digest(&bytes).into();
 
-	/// Seek to the entry closest to the given sequence number.
-	Seqnum(u64),
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	assert_eq!(reader.file_digest().await.unwrap(), expected);
 
-	/// Seek to the start of the given boot ID.
-	BootId(u128),
+	// preallocated space past the tail object doesn't count
+	let mut padded = bytes.clone();
+	padded.resize(bytes.len() + 4096, 0);
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), padded));
+	reader
+		.select(JournalSelection {
+			machine_id: crate::testing::MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	assert_eq!(reader.file_digest().await.unwrap(), expected);
 
-	/// Seek to the given number of entries before or after the current position.
-	Entries(i64),
+	let other = JournalBuilder::new().entry(TestEntry::new(1, 1000).with_field("A=3"));
+	let mut reader = test_reader(&[other]).await;
+	assert_ne!(reader.file_digest().await.unwrap(), expected);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_tags() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	for compact in [true, false] {
+		let journal = (1..=3).fold(
+			JournalBuilder::new().compact(compact).sealed(true),
+			|journal, n| journal.entry(TestEntry::new(n, n * 1000).with_field("A=1")),
+		);
+		let mut reader = test_reader(&[journal]).await;
+		let tags = reader.tags().try_collect::<Vec<_>>().await.unwrap();
+		assert_eq!(
+			tags.iter()
+				.map(|tag| tag.header.seqnum.get())
+				.collect::<Vec<_>>(),
+			vec![1, 2, 3],
+			"compact: {compact}"
+		);
+		assert!(tags.windows(2).all(|pair| pair[0].offset < pair[1].offset));
+
+		// entries are still readable after walking
+		reader.seek(Seek::Oldest).await.unwrap();
+		assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+	}
+
+	let mut reader = test_reader(&[JournalBuilder::new().entry(TestEntry::new(1, 1000))]).await;
+	assert_eq!(reader.tags().try_collect::<Vec<_>>().await.unwrap(), vec![]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_compact_chain() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	// 4 arrays: only the items are 32-bit in compact files, not the next array offset
+	let journal = (1..=7).fold(
+		JournalBuilder::new().compact(true).array_capacity(2),
+		|journal, n| journal.entry(TestEntry::new(n, n * 1000).with_field("A=1")),
+	);
+	let mut reader = test_reader(&[journal]).await;
+	let seqnums = reader
+		.entries()
+		.map_ok(|entry| entry.header.seqnum.get())
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+	assert_eq!(seqnums, (1..=7).collect::<Vec<_>>());
+
+	for target in [1, 3, 6, 7] {
+		reader.seek(Seek::Seqnum(target)).await.unwrap();
+		assert_eq!(test_next_seqnum(&mut reader).await, Some(target));
+	}
+
+	let chain = reader.entry_array_chain().await.unwrap();
+	assert_eq!(
+		chain
+			.iter()
+			.map(|(array, len)| (array.capacity(), *len))
+			.collect::<Vec<_>>(),
+		vec![(2, 2), (2, 2), (2, 2), (2, 1)]
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_location_of() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_boot(0xb007, 500))
+		.entry(TestEntry::new(2, 2000).with_boot(0xb007, 1500));
+	let reader = JournalReader::new(crate::testing::MemoryFiles::default());
+	assert_eq!(
+		reader
+			.location_of(&Entry {
+				offset: NonZeroU64::MIN,
+				header: EntryObjectHeader {
+					seqnum: NonZeroU64::MIN,
+					realtime: Timestamp::UNIX_EPOCH,
+					monotonic: Monotonic::new(1).unwrap(),
+					boot_id: NonZeroU128::MIN,
+					xor_hash: 0,
+				},
+				objects: Vec::new(),
+			})
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::NotConnected
+	);
+
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	let location = reader.location_of(&entry).unwrap();
+	assert_eq!(reader.position(), Some(&location));
+	assert_eq!(location.file, Some(journal.latest_name()));
+	assert_eq!(location.offset, entry.offset);
+	assert_eq!(location.seqnum.get(), 1);
+	assert_eq!(location.monotonic, Monotonic::new(500).unwrap());
+	assert_eq!(location.boot_id.get(), 0xb007);
+
+	// any entry of the current file, not just the last one read
+	let second = reader.entries().next().await.unwrap().unwrap();
+	let reread = reader.read_entry_at(entry.offset.get()).await.unwrap();
+	assert_eq!(reader.location_of(&reread).unwrap(), location);
+	assert_ne!(reader.position().unwrap().offset, entry.offset);
+	assert_eq!(reader.position().unwrap().offset, second.offset);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_empty_journal() {
+	use crate::testing::JournalBuilder;
+
+	for compact in [true, false] {
+		let journal = JournalBuilder::new().compact(compact);
+		let mut reader = test_reader(&[journal]).await;
+
+		assert_eq!(reader.entries().next().await.transpose().unwrap(), None);
+		for seek in [
+			Seek::Oldest,
+			Seek::Newest,
+			Seek::Timestamp(1000),
+			Seek::Seqnum(1),
+		] {
+			reader.seek(seek).await.unwrap();
+			assert_eq!(
+				reader.entries().next().await.transpose().unwrap(),
+				None,
+				"{seek:?}, compact: {compact}"
+			);
+			assert_eq!(reader.position(), None);
+		}
+
+		let summary = reader.summary().await.unwrap();
+		assert_eq!(summary.n_entries, 0);
+		assert_eq!(summary.head_seqnum, None);
+		assert_eq!(summary.tail_realtime, None);
+
+		assert_eq!(reader.find_data(b"MESSAGE=hi").await.unwrap(), None);
+		assert_eq!(reader.most_common_data(10).await.unwrap(), vec![]);
+		assert_eq!(reader.tags().try_collect::<Vec<_>>().await.unwrap(), vec![]);
+		#[cfg(feature = "digest")]
+		reader.file_digest().await.unwrap();
+		assert!(reader.verify_all().await.unwrap());
+		assert!(reader.take_gaps().is_empty());
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_load_with_header() {
+	use deku::DekuContainerRead as _;
+
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = (1..=3).fold(JournalBuilder::new(), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let (_, mut header) = Header::from_bytes((&journal.build(), 0)).unwrap();
+
+	let mut reader = JournalReader::new(crate::testing::MemoryFiles::default());
+	assert_eq!(
+		reader
+			.load_with_header(Header::from_bytes((&journal.build(), 0)).unwrap().1)
+			.await
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::NotConnected
+	);
+
+	let mut reader = test_reader(&[journal]).await;
+	test_next_seqnum(&mut reader).await;
+
+	// the supplied header is used as-is, not re-read
+	header.n_entries = 42;
+	reader.load_with_header(header).await.unwrap();
+	assert_eq!(reader.current_header().unwrap().n_entries, 42);
+	assert_eq!(reader.position(), None);
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+
+	let (_, mut header) = Header::from_bytes((&JournalBuilder::new().build(), 0)).unwrap();
+	header.header_size = NonZeroU64::new(212).unwrap();
+	assert_eq!(
+		reader.load_with_header(header).await.unwrap_err().kind(),
+		std::io::ErrorKind::InvalidData
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_max_open_files() {
+	use std::num::NonZeroUsize;
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journals = [
+		JournalBuilder::new().entry(TestEntry::new(1, 1000)),
+		JournalBuilder::new().entry(TestEntry::new(2, 2000)),
+		JournalBuilder::new().entry(TestEntry::new(3, 3000)),
+		JournalBuilder::new().entry(TestEntry::new(4, 4000)),
+	];
+	for (max_open_files, handles, data_io) in [
+		(8, Some(4), true),
+		(4, Some(2), true),
+		(2, None, true),
+		(1, None, false),
+	] {
+		let mut reader = test_reader_with(MemoryFiles::default(), &journals).await;
+		reader.options = ReadOptions::default()
+			.with_concurrency(NonZeroUsize::new(4).unwrap())
+			.with_max_open_files(NonZeroUsize::new(max_open_files).unwrap());
+
+		let entry = reader.entries().next().await.unwrap().unwrap();
+		reader
+			.entry_data(&entry)
+			.try_collect::<Vec<_>>()
+			.await
+			.unwrap();
+		assert_eq!(reader.data_io.is_some(), data_io, "max {max_open_files}");
+
+		// the main handle and the data handle (if any) count against the limit
+		assert_eq!(
+			reader
+				.clone_handles(journals.len())
+				.unwrap()
+				.map(|handles| handles.len()),
+			handles,
+			"max {max_open_files}"
+		);
+		assert!(reader.verify_all().await.unwrap(), "max {max_open_files}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_with_fields() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(
+			TestEntry::new(1, 1000)
+				.with_field("PRIORITY=6")
+				.with_field("_PID=42"),
+		)
+		.entry(TestEntry::new(2, 2000).with_field("PRIORITY=3"));
+	let mut reader = test_reader(&[journal]).await;
+
+	let entries = reader
+		.entries_with_fields(&[b"PRIORITY", b"_PID", b"PRIORITY"])
+		.map_ok(|(entry, fields)| (entry.header.seqnum.get(), fields))
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+	assert_eq!(
+		entries,
+		vec![
+			(
+				1,
+				BTreeMap::from([
+					("PRIORITY".into(), "6".into()),
+					("_PID".into(), "42".into())
+				])
+			),
+			(2, BTreeMap::from([("PRIORITY".into(), "3".into())])),
+		]
+	);
+
+	// data objects past the last requested field aren't read
+	let reads = |reader: &JournalReader<crate::testing::MemoryFiles>| {
+		reader.data_io.as_ref().unwrap().reads
+	};
+	reader.seek(Seek::Oldest).await.unwrap();
+	let before = reads(&reader);
+	let (_, fields) = reader
+		.entries_with_fields(&[b"MESSAGE"])
+		.next()
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(
+		fields,
+		BTreeMap::from([("MESSAGE".into(), "entry 1".into())])
+	);
+	let one = reads(&reader) - before;
+
+	reader.seek(Seek::Oldest).await.unwrap();
+	let before = reads(&reader);
+	reader
+		.entries_with_fields(&[b"_PID"])
+		.next()
+		.await
+		.unwrap()
+		.unwrap();
+	assert!(reads(&reader) - before > one);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_multimap() {
+	use crate::{
+		export::{write_export_field, write_json_fields},
+		testing::{JournalBuilder, TestEntry},
+	};
+
+	let journal = JournalBuilder::new().entry(
+		TestEntry::new(1, 1000)
+			.with_field("GROUP=wheel")
+			.with_field("_PID=42")
+			.with_field("GROUP=adm")
+			.with_field("GROUP=users"),
+	);
+	let mut reader = test_reader(&[journal]).await;
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	let fields = reader.entry_multimap(&entry).await.unwrap();
+
+	let groups = fields.get(&BString::from("GROUP")).unwrap();
+	assert_eq!(groups.len(), 3);
+	// in the order the entry references them, which is by offset
+	let mut in_order = Vec::new();
+	let mut data = reader.entry_data(&entry);
+	while let Some(datum) = data.next().await {
+		let datum = datum.unwrap();
+		if datum.key == "GROUP" {
+			in_order.push(datum.value);
+		}
+	}
+	assert_eq!(*groups, in_order);
+	let mut sorted = groups.clone();
+	sorted.sort();
+	assert_eq!(sorted, ["adm", "users", "wheel"]);
+	assert_eq!(fields[&BString::from("_PID")], ["42"]);
+	assert_eq!(fields[&BString::from("MESSAGE")], ["entry 1"]);
+
+	// every value survives serialisation
+	let mut export = Vec::new();
+	let mut json = Vec::new();
+	for (key, values) in &fields {
+		for value in values {
+			write_export_field(&mut export, key, value).unwrap();
+		}
+		write_json_fields(&mut json, key, values).unwrap();
+	}
+	let export = String::from_utf8(export).unwrap();
+	assert_eq!(
+		export
+			.lines()
+			.filter(|line| line.starts_with("GROUP="))
+			.count(),
+		3
+	);
+	let json = String::from_utf8(json).unwrap();
+	let expected = format!(
+		r#""GROUP":["{}","{}","{}"]"#,
+		groups[0], groups[1], groups[2]
+	);
+	assert!(json.contains(&expected), "{json}");
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_header_objects() {
+	use deku::DekuContainerRead as _;
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000));
+	let bytes = journal.build();
+	let (_, header) = Header::from_bytes((&bytes, 0)).unwrap();
+	let load = |at: usize, value: u64| {
+		let mut bytes = bytes.clone();
+		bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+		let files = MemoryFiles::default().with_file(&journal.latest_name(), bytes);
+		async move {
+			let mut reader = JournalReader::new(files);
+			reader
+				.select(JournalSelection {
+					machine_id: MACHINE_ID,
+					scope: "system".into(),
+				})
+				.await?;
+			let entry = reader.entries().next().await.transpose();
+			entry
+		}
+	};
+	assert!(load(0, u64::from_le_bytes(*b"LPKSHHRH")).await.is_ok());
+
+	let data_table = header.data_hash_table_offset.get();
+	let field_table = header.field_hash_table_offset.get();
+	for (at, value, error) in [
+		(
+			176,
+			data_table - OBJECT_HEADER_SIZE,
+			format!(
+				"header's entry_array_offset ({}) is invalid: expected object of type EntryArray, found DataHashTable",
+				data_table - OBJECT_HEADER_SIZE
+			),
+		),
+		(
+			104,
+			field_table,
+			format!(
+				"header's data_hash_table_offset ({field_table}) is invalid: expected object of type DataHashTable, found FieldHashTable"
+			),
+		),
+		(
+			128,
+			header.field_hash_table_size.get() * 2,
+			format!(
+				"header's field_hash_table_offset ({field_table}) is invalid: table of {} bytes doesn't fit in its object of {} bytes",
+				header.field_hash_table_size.get() * 2,
+				header.field_hash_table_size.get() + OBJECT_HEADER_SIZE
+			),
+		),
+	] {
+		let err = load(at, value).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), error);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_compact_layout() {
+	use deku::DekuContainerRead as _;
+
+	use crate::{
+		header::IncompatibleFlag,
+		testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID},
+	};
+
+	// an odd capacity, so that compact items don't fill a whole number of regular items
+	let journal = |compact| {
+		JournalBuilder::new()
+			.compact(compact)
+			.array_capacity(3)
+			.entry(TestEntry::new(1, 1000))
+	};
+	let load = |journal: &JournalBuilder, bytes: Vec<u8>| {
+		let files = MemoryFiles::default().with_file(&journal.latest_name(), bytes);
+		async move {
+			let mut reader = JournalReader::new(files);
+			reader
+				.select(JournalSelection {
+					machine_id: MACHINE_ID,
+					scope: "system".into(),
+				})
+				.await?;
+			reader.entries().next().await.transpose()?;
+			Ok::<_, std::io::Error>(reader)
+		}
+	};
+	for compact in [true, false] {
+		let journal = journal(compact);
+		let mut reader = load(&journal, journal.build()).await.unwrap();
+		assert_eq!(reader.current_header().unwrap().is_compact(), compact);
+
+		// a compact header too small for the compact fields
+		let (_, mut header) = Header::from_bytes((&journal.build(), 0)).unwrap();
+		header.incompatible_flags |= IncompatibleFlag::Compact;
+		header.header_size = NonZeroU64::new(256).unwrap();
+		assert_eq!(
+			reader.load_with_header(header).await.unwrap_err().kind(),
+			std::io::ErrorKind::InvalidData,
+			"compact {compact}"
+		);
+	}
+
+	// a compact file flagged as regular: its entry array doesn't fit 8-byte items
+	let journal = journal(true);
+	let mut bytes = journal.build();
+	let (_, mut header) = Header::from_bytes((&bytes, 0)).unwrap();
+	header.incompatible_flags -= IncompatibleFlag::Compact;
+	let serialized = header.serialize().unwrap();
+	bytes[..serialized.len()].copy_from_slice(&serialized);
+	assert_eq!(
+		load(&journal, bytes).await.unwrap_err().kind(),
+		std::io::ErrorKind::InvalidData
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_object_handler() {
+	use std::sync::{Arc, Mutex};
+
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.sealed(true)
+		.custom_object(42, b"answer")
+		.custom_object(43, b"ignored")
+		.entry(TestEntry::new(1, 1000));
+	let mut reader = test_reader(&[journal]).await;
+
+	// without handlers, custom objects are skipped
+	let objects = reader.walk_objects().await.unwrap();
+	assert!(objects > 2);
+
+	let seen = Arc::new(Mutex::new(Vec::new()));
+	let handler = {
+		let seen = seen.clone();
+		move |offset: NonZeroU64, object: &ObjectHeader, payload: &[u8]| {
+			assert_eq!(object.r#type, ObjectType::Unknown(42));
+			seen.lock().unwrap().push((offset, payload.to_vec()));
+			Ok(())
+		}
+	};
+	assert!(reader.set_object_handler(42, handler).is_none());
+	assert_eq!(reader.walk_objects().await.unwrap(), objects);
+	assert_eq!(
+		reader.tags().try_collect::<Vec<_>>().await.unwrap().len(),
+		1
+	);
+	let seen = seen.lock().unwrap().clone();
+	assert_eq!(seen.len(), 2);
+	assert_eq!(seen[0], seen[1]);
+	assert_eq!(seen[0].1, b"answer");
+
+	reader.set_object_handler(43, |_, _, _| {
+		Err(std::io::Error::other("custom object rejected"))
+	});
+	assert_eq!(
+		reader.walk_objects().await.unwrap_err().to_string(),
+		"custom object rejected"
+	);
+
+	assert!(reader.remove_object_handler(43).is_some());
+	assert_eq!(reader.walk_objects().await.unwrap(), objects);
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_fraction() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journal = |seqnums: std::ops::RangeInclusive<u64>| {
+		seqnums.fold(JournalBuilder::new(), |journal, n| {
+			journal.entry(TestEntry::new(n, n * 1000))
+		})
+	};
+	let journals = [journal(1..=3), journal(4..=6), journal(7..=10)];
+	let mut reader = test_reader(&journals).await;
+	for (fraction, seqnum) in [
+		(0.0, 1),
+		(1.0, 10),
+		// 4.5 rounds up
+		(0.5, 6),
+		(0.7, 7),
+		// boundaries of files
+		(2.0 / 9.0, 3),
+		(3.0 / 9.0, 4),
+		(-1.0, 1),
+		(2.0, 10),
+		(f64::NAN, 1),
+	] {
+		reader.seek(Seek::Fraction(fraction)).await.unwrap();
+		assert_eq!(
+			test_next_seqnum(&mut reader).await,
+			Some(seqnum),
+			"{fraction}"
+		);
+	}
+
+	let mut reader = test_reader(&[JournalBuilder::new()]).await;
+	reader.seek(Seek::Fraction(0.5)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, None);
+
+	// in a single file, it's a fraction of that file
+	let files = MemoryFiles::default().with_file(&journals[2].latest_name(), journals[2].build());
+	let mut reader = JournalReader::new(files);
+	reader
+		.open_file(&MemoryFiles::make_filename(&journals[2].latest_name()))
+		.await
+		.unwrap();
+	reader.seek(Seek::Fraction(0.5)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(9));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_data_ref() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000).with_field("A=1"));
+	for contiguous in [true, false] {
+		let files = if contiguous {
+			MemoryFiles::default().with_contiguous()
+		} else {
+			MemoryFiles::default()
+		};
+		let mut reader = test_reader_with(files, std::slice::from_ref(&journal)).await;
+		let entry = reader.entries().next().await.unwrap().unwrap();
+		let expected = reader
+			.entry_data(&entry)
+			.try_collect::<Vec<_>>()
+			.await
+			.unwrap();
+
+		let reads = reader.io.reads;
+		for (offset, expected) in entry.objects.iter().zip(expected) {
+			let data = reader.data_ref(NonZeroU64::from(*offset)).await.unwrap();
+			assert_eq!(data.is_borrowed(), contiguous);
+			assert_eq!(data.into_owned(), expected);
+		}
+		// nothing is read through the handle when borrowing from the file
+		assert_eq!(reader.io.reads == reads, contiguous);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_read_object_raw() {
+	use crate::{
+		objects::DataCompression,
+		testing::{JournalBuilder, MemoryFiles, TestEntry},
+	};
+
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000));
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let entry = reader.entries().next().await.unwrap().unwrap();
+
+	let data = u64::from(entry.objects[0].get());
+	let (object, payload) = reader.read_object_raw(data).await.unwrap();
+	assert_eq!(object.r#type, ObjectType::Data);
+	assert_eq!(object.compression, DataCompression::None);
+	assert_eq!(payload.len() as u64, object.size - OBJECT_HEADER_SIZE);
+	assert!(payload.ends_with(b"MESSAGE=entry 1"));
+
+	let (object, _) = reader.read_object_raw(entry.offset.get()).await.unwrap();
+	assert_eq!(object.r#type, ObjectType::Entry);
+
+	let arena_end = reader.current_header().unwrap().arena_end();
+	for offset in [0, 8, data + 1, arena_end, arena_end + 8] {
+		assert_eq!(
+			reader.read_object_raw(offset).await.unwrap_err().kind(),
+			std::io::ErrorKind::InvalidInput,
+			"{offset}"
+		);
+	}
+
+	// an object claiming to extend past the arena isn't read
+	let mut bytes = journal.build();
+	let size_at = data as usize + 8;
+	bytes[size_at..size_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+	reader
+		.open_file(&MemoryFiles::make_filename(&journal.latest_name()))
+		.await
+		.unwrap();
+	let err = reader.read_object_raw(data).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert!(
+		err.to_string().contains("doesn't fit in the arena"),
+		"{err}"
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_set_max_priority() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// entry n has PRIORITY=n-1, and entry 9 has no priority at all
+	let mut journal = JournalBuilder::new();
+	for priority in 0..=7 {
+		journal = journal.entry(
+			TestEntry::new(priority + 1, (priority + 1) * 1000)
+				.with_field(format!("PRIORITY={priority}")),
+		);
+	}
+	let journal = journal.entry(TestEntry::new(9, 9000));
+
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, journal.build()));
+	let cases: [(Option<u8>, &[u64]); 6] = [
+		(Some(0), &[1]),
+		(Some(3), &[1, 2, 3, 4]),
+		(Some(6), &[1, 2, 3, 4, 5, 6, 7]),
+		(Some(7), &[1, 2, 3, 4, 5, 6, 7, 8]),
+		// levels past debug are the same as debug
+		(Some(200), &[1, 2, 3, 4, 5, 6, 7, 8]),
+		(None, &[1, 2, 3, 4, 5, 6, 7, 8, 9]),
+	];
+	for (level, expected) in cases {
+		match level {
+			Some(level) => reader.set_max_priority(level),
+			None => reader.clear_max_priority(),
+		}
+		reader
+			.select(JournalSelection {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		let seqnums = reader
+			.entries()
+			.map(|entry| entry.unwrap().header.seqnum.get())
+			.collect::<Vec<_>>()
+			.await;
+		assert_eq!(seqnums, expected, "level {level:?}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_object_past_arena_end() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// the tail object is the only entry array, which is full
+	let bytes = JournalBuilder::new()
+		.array_capacity(2)
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.build();
+	let tail = u64::from_le_bytes(bytes[136..144].try_into().unwrap()) as usize;
+	let size = u64::from_le_bytes(bytes[tail + 8..tail + 16].try_into().unwrap());
+	assert_eq!(tail + size as usize, bytes.len());
+
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let read = |bytes: Vec<u8>| {
+		let files = MemoryFiles::default().with_file(&latest, bytes);
+		async move {
+			let mut reader = JournalReader::new(files);
+			reader
+				.select(JournalSelection {
+					machine_id: MACHINE_ID,
+					scope: "system".into(),
+				})
+				.await
+				.unwrap();
+			let mut entries = reader.entries();
+			let mut seqnums = Vec::new();
+			while let Some(entry) = entries.next().await {
+				match entry {
+					Ok(entry) => seqnums.push(entry.header.seqnum.get()),
+					Err(err) => return (seqnums, Some(err)),
+				}
+			}
+			(seqnums, None)
+		}
+	};
+
+	let (seqnums, err) = read(bytes.clone()).await;
+	assert_eq!(seqnums, vec![1, 2]);
+	assert!(err.is_none());
+
+	// the array claims room for a third item, which would be past the end of the arena
+	let mut overrun = bytes.clone();
+	overrun[tail + 8..tail + 16].copy_from_slice(&(size + 8).to_le_bytes());
+	let (seqnums, err) = read(overrun).await;
+	assert_eq!(seqnums, vec![1, 2]);
+	let err = err.unwrap();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{err}");
+	assert!(
+		err.to_string().contains("past the end of the arena"),
+		"{err}"
+	);
+
+	// the file ends before the arena does, in the middle of the second item
+	let mut truncated = bytes;
+	truncated.truncate(truncated.len() - 4);
+	let (seqnums, err) = read(truncated).await;
+	assert_eq!(seqnums, vec![1]);
+	let err = err.unwrap();
+	assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof, "{err}");
+	assert!(err.to_string().contains("within the arena"), "{err}");
+}
+
+#[test]
+fn test_selection_from_machine_id_str() {
+	let id = 0xc444c71c038d45b0af201444a83b91c9;
+	for text in [
+		"c444c71c038d45b0af201444a83b91c9",
+		"c444c71c038d45b0af201444a83b91c9\n",
+		"C444C71C038D45B0AF201444A83B91C9",
+		"c444c71c-038d-45b0-af20-1444a83b91c9",
+	] {
+		assert_eq!(
+			JournalSelection::from_machine_id_str(text, "system").unwrap(),
+			JournalSelection {
+				machine_id: id,
+				scope: "system".into(),
+			},
+			"{text:?}"
+		);
+	}
+
+	for text in [
+		"",
+		"c444c71c038d45b0af201444a83b91c",
+		"c444c71c038d45b0af201444a83b91c9a",
+		"g444c71c038d45b0af201444a83b91c9",
+		"+444c71c038d45b0af201444a83b91c9",
+		"c444c71c-038d45b0-af20-1444a83b91c9",
+		"c444c71c038d45b0af201444a83b91c9----",
+		"00000000000000000000000000000000",
+	] {
+		let err = JournalSelection::from_machine_id_str(text, "system").unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{text:?}");
+	}
+
+	#[cfg(target_os = "linux")]
+	{
+		let path = std::env::temp_dir().join(format!("machine-id-{}", std::process::id()));
+		std::fs::write(&path, "c444c71c038d45b0af201444a83b91c9\n").unwrap();
+		let selection = JournalSelection::from_machine_id_file(&path, "user-1000").unwrap();
+		assert_eq!(selection.machine_id, id);
+		assert_eq!(selection.as_scope(), Scope::User(1000));
+
+		std::fs::write(&path, "uninitialized\n").unwrap();
+		let err = JournalSelection::from_machine_id_file(&path, "system").unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+		std::fs::remove_file(&path).unwrap();
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_offset() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// several entry arrays, to position in the middle of the chain
+	let journal = JournalBuilder::new().array_capacity(2);
+	let journal = (1..=5).fold(journal, |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, journal.build()));
+
+	let err = reader
+		.seek(Seek::Offset(NonZeroU64::new(4096).unwrap()))
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let offsets = reader
+		.entries()
+		.map(|entry| entry.unwrap().offset)
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(offsets.len(), 5);
+
+	for (n, offset) in offsets.iter().enumerate().rev() {
+		reader.seek(Seek::Offset(*offset)).await.unwrap();
+		let seqnums = reader
+			.entries()
+			.map(|entry| entry.unwrap().header.seqnum.get())
+			.collect::<Vec<_>>()
+			.await;
+		assert_eq!(seqnums, (n as u64 + 1..=5).collect::<Vec<_>>());
+	}
+
+	// errors leave the position where it was
+	reader.seek(Seek::Offset(offsets[3])).await.unwrap();
+	let data = reader.find_data(b"MESSAGE=entry 1").await.unwrap().unwrap();
+	for (offset, kind) in [
+		(offsets[0].get() + 4, std::io::ErrorKind::InvalidInput),
+		(1 << 40, std::io::ErrorKind::InvalidInput),
+		(data.offset.get(), std::io::ErrorKind::InvalidData),
+	] {
+		let err = reader
+			.seek(Seek::Offset(NonZeroU64::new(offset).unwrap()))
+			.await
+			.unwrap_err();
+		assert_eq!(err.kind(), kind, "{offset}: {err}");
+	}
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	assert_eq!(entry.offset, offsets[3]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_tail() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// an archived file with 1..=5, and the latest with 6..=7, in arrays of 2
+	let archived = (1..=5).fold(JournalBuilder::new().array_capacity(2), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let latest = (6..=7).fold(JournalBuilder::new().array_capacity(2), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let files = MemoryFiles::default()
+		.with_file(&archived.archived_name(), archived.build())
+		.with_file(&latest.latest_name(), latest.build());
+	let mut reader = JournalReader::new(files);
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+
+	for (n, expected) in [
+		(0, vec![]),
+		(1, vec![7]),
+		(2, vec![6, 7]),
+		(4, vec![4, 5, 6, 7]),
+		(7, (1..=7).collect()),
+		(50, (1..=7).collect()),
+	] {
+		let seqnums = reader
+			.tail(n)
+			.await
+			.unwrap()
+			.iter()
+			.map(|entry| entry.header.seqnum.get())
+			.collect::<Vec<_>>();
+		assert_eq!(seqnums, expected, "{n}");
+		// the position is after the newest entry
+		assert!(reader.entries().next().await.is_none(), "{n}");
+	}
+
+	// an empty journal
+	let empty = JournalBuilder::new();
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&empty.latest_name(), empty.build()));
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	assert!(reader.tail(10).await.unwrap().is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_verify_monotonic_regression() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, BOOT_ID};
+
+	let other_boot = 0x0b0075;
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_boot(BOOT_ID, 500))
+		// another boot can start from anywhere
+		.entry(TestEntry::new(2, 2000).with_boot(other_boot, 10))
+		.entry(TestEntry::new(3, 3000).with_boot(BOOT_ID, 400))
+		.entry(TestEntry::new(4, 4000).with_boot(BOOT_ID, 600))
+		.entry(TestEntry::new(5, 5000).with_boot(other_boot, 20));
+	let files = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+	let mut reader = JournalReader::new(files);
+	let report = reader.verify_all_report().await.unwrap();
+	let [file] = &report.files[..] else {
+		panic!("expected one file, got {report:?}");
+	};
+	assert_eq!(file.entries, 5);
+	let [VerifyError::MonotonicRegression {
+		boot_id,
+		previous,
+		monotonic,
+		..
+	}] = file.errors[..]
+	else {
+		panic!("expected one regression, got {:?}", file.errors);
+	};
+	assert_eq!((boot_id, previous, monotonic), (BOOT_ID, 500, 400));
+	assert!(!reader.verify_all().await.unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_progress_handler() {
+	use std::{
+		num::NonZeroUsize,
+		sync::{Arc, Mutex},
+	};
+
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000).with_field("A=1"));
+	let latest = JournalBuilder::new()
+		.array_capacity(1)
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000))
+		.entry(TestEntry::new(5, 5000).with_field("A=1"));
+	let files = MemoryFiles::default()
+		.with_file(&archived.archived_name(), archived.build())
+		.with_file(&latest.latest_name(), latest.build());
+
+	for concurrency in [1, 2] {
+		let options =
+			ReadOptions::default().with_concurrency(NonZeroUsize::new(concurrency).unwrap());
+		let mut reader = JournalReader::with_options(files.try_clone().unwrap(), options);
+		let reported = Arc::new(Mutex::new(Vec::new()));
+		let sink = reported.clone();
+		assert!(reader
+			.set_progress_handler(move |progress| sink.lock().unwrap().push(progress.clone()))
+			.is_none());
+		assert!(reader.verify_all().await.unwrap());
+
+		let verified = std::mem::take(&mut *reported.lock().unwrap());
+		for name in [archived.archived_name(), latest.latest_name()] {
+			let progress = verified
+				.iter()
+				.filter(|progress| progress.file.as_ref() == Some(&name))
+				.collect::<Vec<_>>();
+			let first = progress.first().unwrap();
+			let last = progress.last().unwrap();
+			assert_eq!(first.objects_done, 0, "concurrency {concurrency}");
+			assert!(last.objects_total > 0);
+			assert_eq!(last.objects_done, last.objects_total);
+			assert!(progress
+				.windows(2)
+				.all(|pair| pair[0].objects_done <= pair[1].objects_done
+					&& pair[1].objects_total == last.objects_total));
+			// entries are reported as they're checked, not only at the start and end
+			assert!(progress.len() > 3, "{progress:?}");
+		}
+
+		reader
+			.select(JournalSelection {
+				machine_id: crate::testing::MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		let walked = reader.walk_objects().await.unwrap();
+		let walk = std::mem::take(&mut *reported.lock().unwrap());
+		assert_eq!(walk.len() as u64, 1 + walked + 1);
+		assert_eq!(walk[0].objects_done, 0);
+		assert_eq!(walk[walked as usize].objects_done, walked);
+		assert_eq!(walk.last().unwrap().file, reader.current_file());
+		assert!(reader.current_file().is_some());
+
+		assert!(reader.remove_progress_handler().is_some());
+		reader.walk_objects().await.unwrap();
+		assert!(reported.lock().unwrap().is_empty());
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_disk_usage() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let latest = JournalBuilder::new()
+		.seqnum_id(2)
+		.entry(TestEntry::new(3, 3000).with_field("A=1"));
+	let user = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "user-1000".into(),
+	};
+	let files = MemoryFiles::default()
+		.with_file(&archived.archived_name(), archived.build())
+		.with_file(&latest.latest_name(), latest.build())
+		.with_file(&user, vec![0; 12345]);
+	let mut reader = JournalReader::new(files);
+	let system = JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	reader.select(system.clone()).await.unwrap();
+	let current = reader.current_file();
+
+	assert_eq!(
+		reader.disk_usage(&system).await.unwrap(),
+		(archived.build().len() + latest.build().len()) as u64
+	);
+	assert_eq!(
+		reader
+			.disk_usage(&JournalSelection::from(user))
+			.await
+			.unwrap(),
+		12345
+	);
+	let other = JournalSelection {
+		machine_id: MACHINE_ID,
+		scope: "system.other".into(),
+	};
+	assert_eq!(reader.disk_usage(&other).await.unwrap(), 0);
+	assert_eq!(reader.current_file(), current);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_hash_chain_cycles() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	// all data in one slot, so they make one long chain
+	let journal = JournalBuilder::new()
+		.data_hash_table_capacity(1)
+		.entry(TestEntry::new(1, 1000).with_field("A=1"))
+		.entry(TestEntry::new(2, 2000).with_field("A=2"));
+	let healthy = journal.build();
+	let reader_of = |bytes: Vec<u8>| async move {
+		let latest = FilenameInfo::Latest {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		};
+		let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, bytes));
+		reader
+			.select(JournalSelection {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		reader
+	};
+	let mut reader = reader_of(healthy.clone()).await;
+	let first = reader
+		.find_data(b"MESSAGE=entry 1")
+		.await
+		.unwrap()
+		.unwrap()
+		.offset
+		.get();
+	assert!(reader.find_data(b"A=3").await.unwrap().is_none());
+
+	// a Data object whose next_hash_offset (the second field of its header) is itself
+	let mut looped = healthy.clone();
+	let link = (first + OBJECT_HEADER_SIZE + 8) as usize;
+	looped[link..link + 8].copy_from_slice(&first.to_le_bytes());
+	let mut reader = reader_of(looped).await;
+	let err = reader.find_data(b"A=3").await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	let err = reader.most_common_data(1).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	// a chain longer than the file says it has objects can only be going in circles
+	let mut understated = healthy;
+	understated[144..152].copy_from_slice(&2_u64.to_le_bytes());
+	let mut reader = reader_of(understated).await;
+	let err = reader.find_data(b"A=3").await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert!(
+		err.to_string().contains("cycle detected in object chain"),
+		"{err}"
+	);
+	let err = reader.most_common_data(1).await.unwrap_err();
+	assert!(err.to_string().contains("cycle detected in object chain"));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entry_array_chain_cycles() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID};
+
+	let journal = (1..=5).fold(JournalBuilder::new().array_capacity(2), |journal, n| {
+		journal.entry(TestEntry::new(n, n * 1000))
+	});
+	let healthy = journal.build();
+	let latest = journal.latest_name();
+	let reader_of = |bytes: Vec<u8>| {
+		let latest = latest.clone();
+		async move {
+			let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, bytes));
+			reader
+				.select(JournalSelection {
+					machine_id: MACHINE_ID,
+					scope: "system".into(),
+				})
+				.await
+				.unwrap();
+			reader
+		}
+	};
+	let link_of = |bytes: &[u8], array: usize| {
+		let link = array + OBJECT_HEADER_SIZE as usize;
+		u64::from_le_bytes(bytes[link..link + 8].try_into().unwrap()) as usize
+	};
+	// the header's entry_array_offset, and the next_entry_array_offset of that first array
+	let first = u64::from_le_bytes(healthy[176..184].try_into().unwrap()) as usize;
+	let second = link_of(&healthy, first);
+	assert_ne!(second, 0);
+
+	// the first array linking to itself, and the second linking back to the first
+	for (array, expected) in [(first, vec![1, 2]), (second, vec![1, 2, 3, 4])] {
+		let mut looped = healthy.clone();
+		let link = array + OBJECT_HEADER_SIZE as usize;
+		looped[link..link + 8].copy_from_slice(&(first as u64).to_le_bytes());
+
+		let mut reader = reader_of(looped).await;
+		assert!(!reader.verify_all().await.unwrap());
+
+		reader.seek(Seek::Oldest).await.unwrap();
+		let mut seqnums = Vec::new();
+		let mut entries = reader.entries();
+		let err = loop {
+			match entries.next().await.unwrap() {
+				Ok(entry) => seqnums.push(entry.header.seqnum.get()),
+				Err(err) => break err,
+			}
+		};
+		drop(entries);
+		assert_eq!(seqnums, expected);
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{err}");
+
+		let err = reader.seek(Seek::Newest).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{err}");
+		let err = reader.seek(Seek::Seqnum(4)).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{err}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_reading_live() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let archived = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let latest = JournalBuilder::new()
+		.entry(TestEntry::new(3, 3000))
+		.entry(TestEntry::new(4, 4000));
+	let mut reader = test_reader(&[archived, latest]).await;
+	assert!(!reader.reading_live());
+
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert!(!reader.reading_live());
+	let mut live = Vec::new();
+	let mut entries = reader.entries();
+	while let Some(entry) = entries.next().await {
+		let seqnum = entry.unwrap().seqnum().get();
+		drop(entries);
+		live.push((seqnum, reader.reading_live()));
+		entries = reader.entries();
+	}
+	drop(entries);
+	assert_eq!(live, [(1, false), (2, false), (3, true), (4, true)]);
+	assert!(reader.reading_live());
+
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert!(!reader.reading_live());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_unknown_header_fields() {
+	use crate::{
+		header::{Header, MAX_HEADER_SIZE},
+		objects::OBJECT_HEADER_SIZE,
+		testing::{JournalBuilder, TestEntry},
+	};
+
+	// a header from a newer systemd, with two more fields than we know of
+	let header_size = MAX_HEADER_SIZE as u64 + 16;
+	let journal = JournalBuilder::new()
+		.header_size(header_size)
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let header = Header::parse(&journal.build()).unwrap();
+	assert!(header.has_unknown_fields());
+	assert_eq!(header.header_size.get(), header_size);
+
+	let mut reader = test_reader(&[journal]).await;
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	assert_eq!(reader.io.read_floor(), header_size);
+
+	// objects start after the whole header
+	let header = reader.current_header().unwrap();
+	assert!(header.has_unknown_fields());
+	assert_eq!(
+		header.field_hash_table_offset.get(),
+		header_size + OBJECT_HEADER_SIZE
+	);
+	assert!(reader.walk_objects().await.unwrap() > 0);
+	assert!(reader.verify_all().await.unwrap());
+
+	// but they must still be aligned
+	let mut bytes = JournalBuilder::new().header_size(header_size).build();
+	bytes[88..96].copy_from_slice(&(header_size - 4).to_le_bytes());
+	let err = Header::parse(&bytes).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert_eq!(
+		err.to_string(),
+		format!("header_size {} is not 8-byte aligned", header_size - 4)
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_for_boot() {
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let (first, second, third) = (0x1111, 0x2222, 0x3333);
+	// the second boot spans both files
+	let journals = [
+		JournalBuilder::new()
+			.entry(TestEntry::new(1, 1000).with_boot(first, 100))
+			.entry(TestEntry::new(2, 2000).with_boot(second, 100))
+			.entry(TestEntry::new(3, 3000).with_boot(second, 200)),
+		JournalBuilder::new()
+			.entry(TestEntry::new(4, 4000).with_boot(second, 300))
+			.entry(TestEntry::new(5, 5000).with_boot(third, 100)),
+	];
+	let mut reader = test_reader(&journals).await;
+
+	for (boot_id, seqnums) in [
+		(first, &[1][..]),
+		(second, &[2, 3, 4]),
+		(third, &[5]),
+		(0x4444, &[]),
+	] {
+		let boot = reader
+			.entries_for_boot(boot_id)
+			.map_ok(|entry| entry.header.seqnum.get())
+			.try_collect::<Vec<_>>()
+			.await
+			.unwrap();
+		assert_eq!(boot, seqnums, "{boot_id:x}");
+	}
+
+	let err = reader.seek(Seek::BootId(0x4444)).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+	reader.seek(Seek::BootId(third)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(5));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_resume_after_error() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000));
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let entries = reader.entries().try_collect::<Vec<_>>().await.unwrap();
+
+	// make the middle entry's object header say it's a Data object
+	let mut bytes = journal.build();
+	bytes[entries[1].offset.get() as usize] = 1;
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+	reader
+		.select(JournalSelection::from(journal.latest_name()))
+		.await
+		.unwrap();
+
+	let mut stream = reader.entries();
+	assert_eq!(stream.next().await.unwrap().unwrap().seqnum().get(), 1);
+	let err = stream.next().await.unwrap().unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert!(stream.next().await.is_none());
+	drop(stream);
+	assert_eq!(reader.position().unwrap().seqnum.get(), 1);
+
+	// a new stream goes on past the bad entry
+	let rest = reader.entries().try_collect::<Vec<_>>().await.unwrap();
+	assert_eq!(rest.len(), 1);
+	assert_eq!(rest[0].seqnum().get(), 3);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_entries_dropped_mid_read() {
+	use futures_util::FutureExt as _;
+
+	use crate::testing::{JournalBuilder, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000));
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let entries = reader.entries().try_collect::<Vec<_>>().await.unwrap();
+
+	// the read of the middle entry never completes, and the stream is dropped while waiting on it
+	reader.seek(Seek::Oldest).await.unwrap();
+	reader.io.stall_at = Some(entries[1].offset.get());
+	let mut stream = reader.entries();
+	assert_eq!(stream.next().await.unwrap().unwrap().seqnum().get(), 1);
+	assert!(stream.next().now_or_never().is_none());
+	drop(stream);
+	assert_eq!(reader.position().unwrap().seqnum.get(), 1);
+
+	// a new stream reads that entry again
+	reader.io.stall_at = None;
+	let rest = reader.entries().try_collect::<Vec<_>>().await.unwrap();
+	let seqnums = rest
+		.iter()
+		.map(|entry| entry.seqnum().get())
+		.collect::<Vec<_>>();
+	assert_eq!(seqnums, [2, 3]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_read_data_header_at() {
+	use crate::{
+		objects::DataCompression,
+		testing::{JournalBuilder, MemoryFiles, TestEntry},
+	};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000).with_field("A=1"))
+		.entry(TestEntry::new(2, 2000).with_field("A=1"));
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let entry = reader.entries().next().await.unwrap().unwrap();
+	let data = reader.find_data(b"A=1").await.unwrap().unwrap();
+	let (offset, expected) = (data.offset.get(), data.header);
+	assert_eq!(reader.read_data_header_at(offset).await.unwrap(), expected);
+
+	// the payload isn't read, so it isn't decompressed either
+	let mut bytes = journal.build();
+	bytes[offset as usize + 1] = DataCompression::Zstd as u8;
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+	reader
+		.select(JournalSelection::from(journal.latest_name()))
+		.await
+		.unwrap();
+	assert!(reader.data_ref(offset.try_into().unwrap()).await.is_err());
+	let header = reader.read_data_header_at(offset).await.unwrap();
+	assert_eq!(header, expected);
+	assert_eq!(header.n_entries, 2);
+
+	let err = reader
+		.read_data_header_at(entry.offset.get())
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	let err = reader.read_data_header_at(offset + 4).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_monotonic_epoch() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 10_000_000).with_boot(0xaaaa, 4_000_000))
+		.entry(TestEntry::new(2, 11_000_000).with_boot(0xaaaa, 5_000_000))
+		.entry(TestEntry::new(3, 20_000_000).with_boot(0xbbbb, 1_000_000));
+	let epoch = |micros: i64| Timestamp::from_microsecond(micros).unwrap();
+
+	for trusted in [true, false] {
+		let mut bytes = journal.build();
+		if !trusted {
+			// without TailEntryBootId, the tail entry fields can't be trusted: make them wrong
+			bytes[8] &= !0b10;
+			bytes[200..208].copy_from_slice(&123_u64.to_le_bytes());
+		}
+		let mut reader =
+			JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+		reader
+			.select(JournalSelection::from(journal.latest_name()))
+			.await
+			.unwrap();
+		reader.seek(Seek::Oldest).await.unwrap();
+		let first = reader.entries().next().await.unwrap().unwrap();
+
+		assert_eq!(
+			reader.monotonic_epoch(0xbbbb).await.unwrap(),
+			Some(epoch(19_000_000)),
+			"trusted: {trusted}"
+		);
+		let boot_epoch = reader.monotonic_epoch(0xaaaa).await.unwrap().unwrap();
+		assert_eq!(boot_epoch, epoch(6_000_000));
+		assert_eq!(
+			first.header.monotonic.to_timestamp(boot_epoch),
+			first.realtime()
+		);
+		assert_eq!(reader.monotonic_epoch(0xcccc).await.unwrap(), None);
+
+		// the position isn't disturbed
+		let next = reader.entries().next().await.unwrap().unwrap();
+		assert_eq!(next.seqnum().get(), 2);
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_failed_seek_keeps_open_file() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry, SEQNUM_ID};
+
+	let first = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000));
+	let corrupt = JournalBuilder::new().entry(TestEntry::new(3, 3000));
+	let latest = JournalBuilder::new().entry(TestEntry::new(4, 4000));
+	let mut bytes = corrupt.build();
+	bytes[0] = b'X';
+	let files = MemoryFiles::default().with_file(&corrupt.archived_name(), bytes);
+	let mut reader = test_reader_with(files, &[first.clone(), latest]).await;
+	reader.seek(Seek::Oldest).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(1));
+
+	// these all read the header of the corrupt file before they'd open the one they land in
+	let path = MemoryFiles::make_filename(&first.archived_name());
+	for seek in [
+		Seek::Timestamp(4000),
+		Seek::Seqnum(4),
+		Seek::Absolute {
+			seqnum_id: NonZeroU128::new(SEQNUM_ID).unwrap(),
+			seqnum: NonZeroU64::new(3).unwrap(),
+		},
+		Seek::Fraction(0.9),
+	] {
+		let err = reader.seek(seek).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{seek:?}");
+		assert_eq!(reader.io.current(), Some(path.as_path()), "{seek:?}");
+	}
+
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_seek_entries_unsupported() {
+	use crate::testing::{JournalBuilder, MemoryFiles, TestEntry};
+
+	let journal = JournalBuilder::new()
+		.entry(TestEntry::new(1, 1000))
+		.entry(TestEntry::new(2, 2000))
+		.entry(TestEntry::new(3, 3000));
+
+	let mut reader = test_reader(std::slice::from_ref(&journal)).await;
+	let err = reader.seek(Seek::Entries(1)).await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+
+	let files = MemoryFiles::default().with_file(&journal.latest_name(), journal.build());
+	let mut reader = JournalReader::new(files);
+	reader
+		.open_file(&MemoryFiles::make_filename(&journal.latest_name()))
+		.await
+		.unwrap();
+	for n in [-1, 0, 2] {
+		let err = reader.seek(Seek::Entries(n)).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::Unsupported, "{n}");
+	}
+
+	// the reader is still usable
+	reader.seek(Seek::Seqnum(2)).await.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(2));
+	reader
+		.seek(Seek::At(Timestamp::from_microsecond(3000).unwrap()))
+		.await
+		.unwrap();
+	assert_eq!(test_next_seqnum(&mut reader).await, Some(3));
 }
@@ -1,7 +1,12 @@
 use std::{
+	borrow::Cow,
 	collections::BTreeSet,
+	future::Future,
 	num::{NonZeroU128, NonZeroU64},
 	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	task::{Poll, Waker},
+	time::Duration,
 };
 
 use futures_util::{
@@ -17,7 +22,8 @@ use super::JournalSelection;
 pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	/// Open a file for reading.
 	///
-	/// This may close the current file (if any).
+	/// This closes the current file (if any) first, so that it's closed even if the new file can't
+	/// be opened: afterwards, [`current`](Self::current) is the new file or nothing.
 	fn open(
 		&mut self,
 		filename: &Path,
@@ -29,13 +35,127 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	/// The path to the current file, if one is open.
 	fn current(&self) -> Option<&Path>;
 
+	/// Make an independent handle to the same set of files.
+	///
+	/// The new handle starts with no file open, and opening, seeking, or reading through it must
+	/// not affect this handle (and vice versa). This is used to process several files
+	/// concurrently, for example in [`JournalReader::verify_all`](super::JournalReader::verify_all).
+	///
+	/// The default returns an [`Unsupported`](std::io::ErrorKind::Unsupported) error, in which
+	/// case the reader falls back to doing everything through this handle, one file at a time.
+	fn try_clone(&self) -> std::io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Err(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			"cloning this handle is not supported",
+		))
+	}
+
+	/// The lowest offset at which objects may be read in the current file.
+	///
+	/// Object reads at offsets below this error with `InvalidData`, as they would be reading into
+	/// the file header, which means the offset is corrupt. This should be the `header_size` of the
+	/// current file once its header has been read (see [`set_read_floor`](Self::set_read_floor)),
+	/// and [`MIN_HEADER_SIZE`] otherwise, which is what the default returns.
+	///
+	/// Implementations for storage schemes where this doesn't make sense can return 0 to disable
+	/// the check.
+	fn read_floor(&self) -> u64 {
+		MIN_HEADER_SIZE as u64
+	}
+
+	/// Set the read floor for the current file.
+	///
+	/// This is called with the `header_size` when the header of the current file is read, and
+	/// should be reset when another file is opened. The default does nothing, so the floor stays at
+	/// [`MIN_HEADER_SIZE`].
+	fn set_read_floor(&mut self, floor: u64) {
+		let _ = floor;
+	}
+
+	/// The offset past which objects may not be read in the current file.
+	///
+	/// Object reads which would go past this error with `InvalidData`, as the object claims to be
+	/// larger than the file says it is, while reads which go past the end of a file that's shorter
+	/// than that error with `UnexpectedEof`. This should be the
+	/// [`arena_end`](crate::header::Header::arena_end) of the current file once its header has been
+	/// read by a reader (see [`set_read_ceiling`](Self::set_read_ceiling)), and `u64::MAX`
+	/// otherwise, which is what the default returns.
+	fn read_ceiling(&self) -> u64 {
+		u64::MAX
+	}
+
+	/// Set the read ceiling for the current file.
+	///
+	/// This is called with the end of the arena when the header of the current file is loaded by a
+	/// [`JournalReader`](super::JournalReader), and should be reset when another file is opened.
+	/// The default does nothing, so there's no ceiling.
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		let _ = ceiling;
+	}
+
+	/// The contents of the current file as one slice, if the implementation holds it in memory.
+	///
+	/// This lets uncompressed Data be read without copying, see
+	/// [`JournalReader::data_ref`](super::JournalReader::data_ref). The default returns `None`,
+	/// and everything is read through [`AsyncRead`](futures_util::AsyncRead).
+	fn contiguous(&self) -> Option<&[u8]> {
+		None
+	}
+
+	/// Wait until the current file may have changed, for at most `timeout`.
+	///
+	/// This is used to wait for new entries when following a journal, see
+	/// [`JournalReader::follow`](super::JournalReader::follow), which re-reads the header of the
+	/// file after each wait. The default sleeps for the whole timeout, so the file is polled;
+	/// implementations which can be notified of changes (e.g. with inotify) should return as soon
+	/// as the file changes instead.
+	///
+	/// The default doesn't depend on an async runtime, so it sleeps on a thread of its own for
+	/// each wait. Implementations which use a runtime should sleep on its timer instead, as the
+	/// on-disk implementations do with tokio.
+	fn wait_for_change(
+		&mut self,
+		timeout: Duration,
+	) -> impl std::future::Future<Output = std::io::Result<()>> + Send {
+		async move {
+			sleep(timeout).await;
+			Ok(())
+		}
+	}
+
+	/// The size of a journal file in bytes, without opening it.
+	///
+	/// This is used to measure how much space journals take, see
+	/// [`JournalReader::disk_usage`](super::JournalReader::disk_usage). The default errors with
+	/// `Unsupported`, and files are then opened and measured by seeking to their end instead.
+	fn file_size(
+		&self,
+		filename: &Path,
+	) -> impl std::future::Future<Output = std::io::Result<u64>> + Send {
+		let _ = filename;
+		async {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"file sizes are not supported",
+			))
+		}
+	}
+
 	/// Recursively list all journal files available.
 	///
 	/// The optional prefix filters the results. If `None`, all files are listed.
 	/// The prefix may have a partial filename as the last component.
 	///
+	/// The on-disk implementations resolve the prefix against their canonical root, and compare
+	/// canonical paths component by component: only the last component is matched as a partial
+	/// name, so `journal` matches `journal2/` but `journal/` doesn't.
+	///
 	/// The library will interpret every file returned as a journal, so you may want to filter by
-	/// the `.journal` extension for the systemd on-disk file scheme. However, [`JournalReader`]
+	/// the `.journal` extension for the systemd on-disk file scheme (the on-disk implementations in
+	/// this library do so by default). However, [`JournalReader`](super::JournalReader)
 	/// does not itself check the extension, so you can implement custom storage schemes; possibly
 	/// overwriting the default [`make_filename`](AsyncFileRead::make_filename) and
 	/// [`parse_filename`](AsyncFileRead::parse_filename) associated functions.
@@ -102,32 +222,14 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	/// where `(machine_id)`, `(file_seqnum)`, `(head_seqnum)`, and `(head_realtime)` are lowercase hex-encoded in
 	/// little-endian.
 	///
+	/// Journals in a namespace can be laid out as systemd does with
+	/// [`namespaced_filename`] instead.
+	///
 	/// This MUST be the inverse of [`parse_filename`](AsyncFileRead::parse_filename), and you should ensure that
 	/// [`make_prefix`](AsyncFileRead::make_prefix) remains compatible.
 	#[tracing::instrument(level = "trace")]
 	fn make_filename(info: &FilenameInfo) -> PathBuf {
-		match info {
-			FilenameInfo::Latest { machine_id, scope } => {
-				PathBuf::from(hex::encode(machine_id.to_be_bytes()))
-					.join(format!("{scope}.journal"))
-			}
-			FilenameInfo::Archived {
-				machine_id,
-				scope,
-				file_seqnum,
-				head_seqnum,
-				head_realtime,
-			} => PathBuf::from(hex::encode(machine_id.to_be_bytes())).join(format!(
-				"{scope}@{file_seqnum}-{head_seqnum}-{head_realtime}.journal",
-				file_seqnum = hex::encode(file_seqnum.get().to_be_bytes()),
-				head_seqnum = hex::encode(head_seqnum.get().to_be_bytes()),
-				head_realtime = hex::encode(
-					u64::try_from(head_realtime.as_microsecond())
-						.unwrap_or_default()
-						.to_be_bytes()
-				),
-			)),
-		}
+		make_filename(info, false)
 	}
 
 	/// Make a journal filename prefix from a machine ID and scope.
@@ -142,8 +244,8 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	///
 	/// This MUST be compatible with [`make_filename`](AsyncFileRead::parse_filename).
 	#[tracing::instrument(level = "trace")]
-	fn make_prefix(JournalSelection { machine_id, scope }: &JournalSelection) -> PathBuf {
-		PathBuf::from(hex::encode(machine_id.to_be_bytes())).join(format!("{scope}@"))
+	fn make_prefix(selection: &JournalSelection) -> PathBuf {
+		make_prefix(selection, false)
 	}
 
 	/// Parse a journal filename.
@@ -160,46 +262,20 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	/// where `(machine_id)`, `(file_seqnum)`, `(head_seqnum)`, and `(head_realtime)` are lowercase hex-encoded in
 	/// little-endian.
 	///
+	/// Journals in a namespace can be parsed as systemd lays them out with
+	/// [`parse_namespaced_filename`] instead.
+	///
 	/// This MUST be the inverse of [`make_filename`](AsyncFileRead::make_filename), though it may be more lenient.
 	/// The default implementation ignores the extension (or even the presence of a file extension), and is
-	/// case-insensitive on the hex fields.
+	/// case-insensitive on the hex fields. It skips files ending with `~`, which systemd leaves behind when it
+	/// couldn't finish writing them.
+	///
+	/// The machine ID directory doesn't have to be the immediate parent of the file: the closest ancestor
+	/// directory which is named like one is used, so files can be nested further within it. Backslashes are
+	/// treated as path separators too, so Windows-style paths are parsed the same on all platforms.
 	#[tracing::instrument(level = "trace")]
 	fn parse_filename(path: &Path) -> Option<FilenameInfo> {
-		let mut components = path.components().rev();
-		let filename = components.next()?.as_os_str().to_str()?;
-		let machine_id = u128::from_be_bytes(
-			hex::decode(components.next()?.as_os_str().to_str()?)
-				.ok()?
-				.try_into()
-				.ok()?,
-		);
-
-		let Some((scope, rest)) = filename.split_once('@') else {
-			let (scope, _) = filename.split_once('.').unwrap_or((filename, ""));
-			if scope == "fss" {
-				return None;
-			}
-			return Some(FilenameInfo::Latest {
-				machine_id,
-				scope: scope.to_string(),
-			});
-		};
-
-		let (file_seqnum, rest) = rest.split_once('-')?;
-		let (head_seqnum, rest) = rest.split_once('-')?;
-		let (head_realtime, _) = rest.split_once('.').unwrap_or((rest, ""));
-
-		let file_seqnum = u128::from_be_bytes(hex::decode(file_seqnum).ok()?.try_into().ok()?);
-		let head_seqnum = u64::from_be_bytes(hex::decode(head_seqnum).ok()?.try_into().ok()?);
-		let head_realtime = u64::from_be_bytes(hex::decode(head_realtime).ok()?.try_into().ok()?);
-
-		Some(FilenameInfo::Archived {
-			machine_id,
-			scope: scope.to_string(),
-			file_seqnum: NonZeroU128::new(file_seqnum)?,
-			head_seqnum: NonZeroU64::new(head_seqnum)?,
-			head_realtime: Timestamp::from_microsecond(head_realtime.try_into().ok()?).ok()?,
-		})
+		parse_filename(path, false)
 	}
 
 	/// For internal use only.
@@ -225,14 +301,35 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	where
 		Self: Unpin,
 	{
-		debug_assert!(
-			offset >= MIN_HEADER_SIZE as u64,
-			"small seek protection! [{offset}]"
-		);
+		let floor = self.read_floor();
+		if offset < floor {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("attempted to read into header region ({offset} < {floor})"),
+			));
+		}
+
+		let end = offset.saturating_add(size as u64);
+		let ceiling = self.read_ceiling();
+		if end > ceiling {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("attempted to read past the end of the arena ({end} > {ceiling})"),
+			));
+		}
 
 		let mut buf = vec![0; size];
 		self.seek(std::io::SeekFrom::Start(offset)).await?;
-		self.read_exact(&mut buf).await?;
+		self.read_exact(&mut buf).await.map_err(|err| {
+			if err.kind() == std::io::ErrorKind::UnexpectedEof {
+				std::io::Error::new(
+					std::io::ErrorKind::UnexpectedEof,
+					format!("file ends before {end}, which is within the arena"),
+				)
+			} else {
+				err
+			}
+		})?;
 		Ok(buf)
 	}
 
@@ -285,6 +382,125 @@ pub trait AsyncFileRead: AsyncReadExt + AsyncSeekExt + Unpin {
 	}
 }
 
+/// [`AsyncFileRead::make_filename`] for journals in namespaces, as systemd lays them out.
+///
+/// systemd keeps the journals of a namespace (`journalctl --namespace`) in a
+/// `(machine_id).(namespace)` directory rather than the machine's. This puts journals with a
+/// `(scope).(namespace)` scope (see [`Scope`](super::Scope)) there, and others where the default
+/// does. The default filename functions don't know about namespaces, so implementations opt in by
+/// overriding them with this, [`namespaced_prefix`], and [`parse_namespaced_filename`].
+pub fn namespaced_filename(info: &FilenameInfo) -> PathBuf {
+	make_filename(info, true)
+}
+
+/// [`AsyncFileRead::make_prefix`] for journals in namespaces, see [`namespaced_filename`].
+pub fn namespaced_prefix(selection: &JournalSelection) -> PathBuf {
+	make_prefix(selection, true)
+}
+
+/// [`AsyncFileRead::parse_filename`] for journals in namespaces, see [`namespaced_filename`].
+///
+/// Files in a `(machine_id).(namespace)` directory get a `(scope).(namespace)` scope.
+pub fn parse_namespaced_filename(path: &Path) -> Option<FilenameInfo> {
+	parse_filename(path, true)
+}
+
+fn make_filename(info: &FilenameInfo, namespaced: bool) -> PathBuf {
+	match info {
+		FilenameInfo::Latest { machine_id, scope } => {
+			let (dir, scope) = journal_dir(*machine_id, scope, namespaced);
+			dir.join(format!("{scope}.journal"))
+		}
+		FilenameInfo::Archived {
+			machine_id,
+			scope,
+			file_seqnum,
+			head_seqnum,
+			head_realtime,
+		} => {
+			let (dir, scope) = journal_dir(*machine_id, scope, namespaced);
+			dir.join(format!(
+				"{scope}@{file_seqnum}-{head_seqnum}-{head_realtime}.journal",
+				file_seqnum = hex::encode(file_seqnum.get().to_be_bytes()),
+				head_seqnum = hex::encode(head_seqnum.get().to_be_bytes()),
+				head_realtime = hex::encode(
+					u64::try_from(head_realtime.as_microsecond())
+						.unwrap_or_default()
+						.to_be_bytes()
+				),
+			))
+		}
+	}
+}
+
+fn make_prefix(
+	JournalSelection { machine_id, scope }: &JournalSelection,
+	namespaced: bool,
+) -> PathBuf {
+	let (dir, scope) = journal_dir(*machine_id, scope, namespaced);
+	dir.join(format!("{scope}@"))
+}
+
+fn parse_filename(path: &Path, namespaced: bool) -> Option<FilenameInfo> {
+	let mut components = path
+		.components()
+		.rev()
+		.map(|component| component.as_os_str());
+	let mut last = components.next()?.to_str()?.rsplit('\\');
+	let filename = last.next()?;
+	if filename.ends_with('~') {
+		// systemd renames files it couldn't finish writing or rotating to end with a tilde
+		return None;
+	}
+
+	let (machine_id, namespace) = last
+		.map(Cow::Borrowed)
+		.chain(components.map(|dir| dir.to_string_lossy()))
+		.find_map(|dir| parse_machine_dir(&dir, namespaced))?;
+	let with_namespace = |scope: &str| match &namespace {
+		Some(namespace) => format!("{scope}.{namespace}"),
+		None => scope.to_string(),
+	};
+
+	let Some((scope, rest)) = filename.split_once('@') else {
+		let (scope, _) = filename.split_once('.').unwrap_or((filename, ""));
+		if scope == "fss" {
+			return None;
+		}
+		return Some(FilenameInfo::Latest {
+			machine_id,
+			scope: with_namespace(scope),
+		});
+	};
+
+	let (file_seqnum, rest) = rest.split_once('-')?;
+	let (head_seqnum, rest) = rest.split_once('-')?;
+	let (head_realtime, _) = rest.split_once('.').unwrap_or((rest, ""));
+
+	let file_seqnum = u128::from_be_bytes(hex::decode(file_seqnum).ok()?.try_into().ok()?);
+	let head_seqnum = u64::from_be_bytes(hex::decode(head_seqnum).ok()?.try_into().ok()?);
+	let head_realtime = u64::from_be_bytes(hex::decode(head_realtime).ok()?.try_into().ok()?);
+
+	Some(FilenameInfo::Archived {
+		machine_id,
+		scope: with_namespace(scope),
+		file_seqnum: NonZeroU128::new(file_seqnum)?,
+		head_seqnum: NonZeroU64::new(head_seqnum)?,
+		head_realtime: Timestamp::from_microsecond(head_realtime.try_into().ok()?).ok()?,
+	})
+}
+
+/// The directory of a journal, and the scope without its namespace.
+///
+/// Without `namespaced`, this is always the machine's directory and the whole scope.
+fn journal_dir(machine_id: u128, scope: &str, namespaced: bool) -> (PathBuf, &str) {
+	let machine_id = hex::encode(machine_id.to_be_bytes());
+	match scope.split_once('.').filter(|_| namespaced) {
+		Some((scope, namespace)) => (PathBuf::from(format!("{machine_id}.{namespace}")), scope),
+		None => (PathBuf::from(machine_id), scope),
+	}
+}
+
 /// Information contained in a journal filename.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FilenameInfo {
@@ -303,20 +519,15 @@ pub enum FilenameInfo {
 
 impl FilenameInfo {
 	pub fn is_archived(&self) -> bool {
-		match self {
-			Self::Archived { .. } => true,
-			_ => false,
-		}
+		matches!(self, Self::Archived { .. })
 	}
 
 	pub fn is_latest(&self) -> bool {
-		match self {
-			Self::Latest { .. } => true,
-			_ => false,
-		}
+		matches!(self, Self::Latest { .. })
 	}
 }
 
+#[allow(clippy::non_canonical_partial_ord_impl)] // Ord defers to this
 impl PartialOrd for FilenameInfo {
 	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
 		match (self, other) {
@@ -365,3 +576,46 @@ impl Ord for FilenameInfo {
 		self.partial_cmp(other).unwrap()
 	}
 }
+
+/// Parse a `(machine_id)` directory name, or `(machine_id).(namespace)` if `namespaced`.
+fn parse_machine_dir(dir: &str, namespaced: bool) -> Option<(u128, Option<String>)> {
+	let (machine_id, namespace) = dir
+		.split_once('.')
+		.filter(|_| namespaced)
+		.map_or((dir, None), |(id, namespace)| (id, Some(namespace)));
+	if machine_id.len() != 32 || namespace.is_some_and(str::is_empty) {
+		return None;
+	}
+
+	let machine_id = u128::from_be_bytes(hex::decode(machine_id).ok()?.try_into().ok()?);
+	Some((machine_id, namespace.map(String::from)))
+}
+
+/// Sleep without depending on an async runtime, by waking the task from a thread.
+pub(crate) fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+	// (done, waker of the task to wake when done)
+	let shared = Arc::new(Mutex::new((duration.is_zero(), None::<Waker>)));
+	let mut started = false;
+	futures_util::future::poll_fn(move |cx| {
+		// UNWRAP: the lock is never held across a panic
+		let mut state = shared.lock().unwrap();
+		if state.0 {
+			return Poll::Ready(());
+		}
+
+		state.1 = Some(cx.waker().clone());
+		if !started {
+			started = true;
+			let shared = shared.clone();
+			std::thread::spawn(move || {
+				std::thread::sleep(duration);
+				let mut state = shared.lock().unwrap();
+				state.0 = true;
+				if let Some(waker) = state.1.take() {
+					waker.wake();
+				}
+			});
+		}
+		Poll::Pending
+	})
+}
@@ -0,0 +1,167 @@
+use std::{
+	num::{NonZeroU64, NonZeroUsize},
+	time::Duration,
+};
+
+/// Options used when reading journals.
+///
+/// All options have defaults, so start from [`ReadOptions::default()`] and change what you need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOptions {
+	/// How many files to process at once when scanning whole journals.
+	///
+	/// This is used by [`JournalReader::verify_all`](super::JournalReader::verify_all). Each file
+	/// being processed gets its own handle from
+	/// [`AsyncFileRead::try_clone`](super::AsyncFileRead::try_clone); if the implementation does
+	/// not support that, files are processed one at a time regardless of this option.
+	///
+	/// Defaults to 4.
+	pub concurrency: NonZeroUsize,
+
+	/// How many files the reader can have open at once, across all of its handles.
+	///
+	/// Besides its main handle, the reader opens a second handle to read entry data with
+	/// [`JournalReader::entry_data`](super::JournalReader::entry_data), and one per file being
+	/// processed concurrently (see [`concurrency`](Self::concurrency)). Those are only made while
+	/// they fit within this limit: data is read through the main handle instead, and files are
+	/// processed with fewer handles or one at a time on the main handle. Journals read by
+	/// [`JournalReader::merged_entries`](super::JournalReader::merged_entries) have a handle each,
+	/// but only this many keep their file open between reads. Lower this where file descriptors
+	/// are scarce.
+	///
+	/// Defaults to 8.
+	pub max_open_files: NonZeroUsize,
+
+	/// Skip over objects which can't be read instead of erroring.
+	///
+	/// When this is enabled, a data object referenced by an entry which is of the wrong type or
+	/// fails to read is logged and skipped by
+	/// [`JournalReader::entry_data`](super::JournalReader::entry_data), so the rest of the entry's
+	/// data can still be read.
+	///
+	/// Defaults to false.
+	pub tolerant: bool,
+
+	/// Record diagnostics about the journal files while reading.
+	///
+	/// Currently this is the depth of the hash chains walked by
+	/// [`JournalReader::find_data`](super::JournalReader::find_data), available from
+	/// [`JournalReader::observed_max_chain_depth`](super::JournalReader::observed_max_chain_depth).
+	///
+	/// Defaults to false.
+	pub diagnostics: bool,
+
+	/// How many times larger than its compressed size a Data payload can get when decompressed.
+	///
+	/// Payloads are always limited to
+	/// [`MAX_DECOMPRESSED_SIZE`](crate::compression::MAX_DECOMPRESSED_SIZE), but that's a lot of
+	/// memory for a single field: with this set, a small compressed payload which expands past the
+	/// ratio (a "compression bomb") is an `InvalidData` error instead, which is caught during
+	/// decompression. Set this when reading untrusted journals; 100 is plenty for log messages.
+	///
+	/// Defaults to `None`, as highly repetitive fields can legitimately compress very well.
+	pub max_expansion_ratio: Option<NonZeroU64>,
+
+	/// Yield each entry only once from merged streams.
+	///
+	/// Journals read together by [`JournalReader::merged_entries`](super::JournalReader::merged_entries)
+	/// can have entries in common, for example when one holds a copy of another's files. With this
+	/// enabled, entries are identified by their sequence number domain and sequence number, or
+	/// failing that by their [`identity`](crate::objects::Entry::identity) (for copies which were
+	/// written into another domain), and only the first of several identical entries is yielded.
+	///
+	/// Defaults to false.
+	pub dedup_merged: bool,
+
+	/// The shortest time to wait for new entries when following a journal.
+	///
+	/// [`JournalReader::follow`](super::JournalReader::follow) waits this long when it reaches the
+	/// end of the journal, and then twice as long each time it finds nothing new, up to
+	/// [`follow_max_interval`](Self::follow_max_interval). Finding new entries resets the wait to
+	/// this. Implementations which are notified of changes (see
+	/// [`AsyncFileRead::wait_for_change`](super::AsyncFileRead::wait_for_change)) can end waits
+	/// early.
+	///
+	/// Defaults to 100ms.
+	pub follow_min_interval: Duration,
+
+	/// The longest time to wait for new entries when following a journal.
+	///
+	/// See [`follow_min_interval`](Self::follow_min_interval). This is raised to the minimum if
+	/// it's lower.
+	///
+	/// Defaults to 2s.
+	pub follow_max_interval: Duration,
+
+	/// How many entries to read ahead of the consumer of a prefetching stream.
+	///
+	/// This is used by [`JournalReader::prefetched_entries`](super::JournalReader::prefetched_entries):
+	/// each time the stream is polled, up to this many entries after the one it yields are read.
+	/// Higher values use more memory, and only help if reads take a while to complete (e.g. on
+	/// network storage) and run in the background while the consumer works. With 0, no entries
+	/// are read ahead.
+	///
+	/// Defaults to 16.
+	pub prefetch: usize,
+}
+
+impl Default for ReadOptions {
+	fn default() -> Self {
+		Self {
+			// UNWRAP: 4 is not zero
+			concurrency: NonZeroUsize::new(4).unwrap(),
+			// UNWRAP: 8 is not zero
+			max_open_files: NonZeroUsize::new(8).unwrap(),
+			tolerant: false,
+			diagnostics: false,
+			max_expansion_ratio: None,
+			dedup_merged: false,
+			follow_min_interval: Duration::from_millis(100),
+			follow_max_interval: Duration::from_secs(2),
+			prefetch: 16,
+		}
+	}
+}
+
+impl ReadOptions {
+	pub fn with_concurrency(mut self, concurrency: NonZeroUsize) -> Self {
+		self.concurrency = concurrency;
+		self
+	}
+
+	pub fn with_max_open_files(mut self, max_open_files: NonZeroUsize) -> Self {
+		self.max_open_files = max_open_files;
+		self
+	}
+
+	pub fn with_tolerant(mut self, tolerant: bool) -> Self {
+		self.tolerant = tolerant;
+		self
+	}
+
+	pub fn with_diagnostics(mut self, diagnostics: bool) -> Self {
+		self.diagnostics = diagnostics;
+		self
+	}
+
+	pub fn with_max_expansion_ratio(mut self, ratio: Option<NonZeroU64>) -> Self {
+		self.max_expansion_ratio = ratio;
+		self
+	}
+
+	pub fn with_dedup_merged(mut self, dedup: bool) -> Self {
+		self.dedup_merged = dedup;
+		self
+	}
+
+	pub fn with_follow_interval(mut self, min: Duration, max: Duration) -> Self {
+		self.follow_min_interval = min;
+		self.follow_max_interval = max;
+		self
+	}
+
+	pub fn with_prefetch(mut self, prefetch: usize) -> Self {
+		self.prefetch = prefetch;
+		self
+	}
+}
@@ -0,0 +1,124 @@
+use std::{
+	collections::VecDeque,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures_util::{Stream, StreamExt as _};
+
+/// A stream which reads ahead of its consumer.
+///
+/// Each time it's polled, this polls the inner stream for as long as it has items ready, until
+/// `ahead` items are buffered after the one it returns. When the inner stream is pending (i.e. a
+/// read is in flight), the buffered item is returned anyway.
+///
+/// Nothing is spawned: the inner stream only makes progress while this is polled. A pending read
+/// only carries on while the consumer processes the item if the IO runs elsewhere, as with tokio's
+/// file IO which runs on a thread pool; otherwise this just batches reads.
+///
+/// Items are returned in order. Once the inner stream yields an error, it's not polled again:
+/// the error is returned after the items before it, and then the stream ends.
+pub(crate) struct Prefetch<S: Stream> {
+	inner: Option<S>,
+	buffer: VecDeque<S::Item>,
+	ahead: usize,
+}
+
+impl<S: Stream> Prefetch<S> {
+	pub(crate) fn new(inner: S, ahead: usize) -> Self {
+		Self {
+			inner: Some(inner),
+			buffer: VecDeque::with_capacity(ahead + 1),
+			ahead,
+		}
+	}
+}
+
+// buffered items are never pinned, so only the inner stream needs to be Unpin
+impl<S: Stream + Unpin> Unpin for Prefetch<S> {}
+
+impl<T, S> Stream for Prefetch<S>
+where
+	S: Stream<Item = std::io::Result<T>> + Unpin,
+{
+	type Item = std::io::Result<T>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		while this.buffer.len() <= this.ahead {
+			let Some(inner) = this.inner.as_mut() else {
+				break;
+			};
+
+			match inner.poll_next_unpin(cx) {
+				Poll::Ready(Some(item)) => {
+					if item.is_err() {
+						this.inner = None;
+					}
+					this.buffer.push_back(item);
+				}
+				Poll::Ready(None) => {
+					this.inner = None;
+				}
+				Poll::Pending => break,
+			}
+		}
+
+		match this.buffer.pop_front() {
+			Some(item) => Poll::Ready(Some(item)),
+			None if this.inner.is_none() => Poll::Ready(None),
+			None => Poll::Pending,
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let (lower, upper) = self
+			.inner
+			.as_ref()
+			.map_or((0, Some(0)), |inner| inner.size_hint());
+		let buffered = self.buffer.len();
+		(
+			lower.saturating_add(buffered),
+			upper.and_then(|upper| upper.checked_add(buffered)),
+		)
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_prefetch() {
+	use std::{
+		io::{Error, ErrorKind},
+		sync::atomic::{AtomicUsize, Ordering},
+	};
+
+	let pulled = AtomicUsize::new(0);
+	let items = [
+		Ok(1),
+		Ok(2),
+		Ok(3),
+		Ok(4),
+		Err(ErrorKind::InvalidData),
+		Ok(5),
+	];
+	let inner = futures_util::stream::iter(items).map(|item| {
+		pulled.fetch_add(1, Ordering::SeqCst);
+		item.map_err(Error::from)
+	});
+
+	let mut stream = Prefetch::new(inner, 2);
+	assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+	// the first item and the two after it
+	assert_eq!(pulled.load(Ordering::SeqCst), 3);
+	assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+	assert_eq!(pulled.load(Ordering::SeqCst), 4);
+	assert_eq!(stream.next().await.unwrap().unwrap(), 3);
+	assert_eq!(stream.next().await.unwrap().unwrap(), 4);
+	assert_eq!(
+		stream.next().await.unwrap().unwrap_err().kind(),
+		ErrorKind::InvalidData
+	);
+	// nothing is read past an error
+	assert!(stream.next().await.is_none());
+	assert_eq!(pulled.load(Ordering::SeqCst), 5);
+}
@@ -0,0 +1,206 @@
+//! Typed views of entries, for consumers which deal in log records rather than fields.
+//!
+//! The well-known fields are described in
+//! [systemd.journal-fields(7)](https://www.freedesktop.org/software/systemd/man/latest/systemd.journal-fields.html).
+
+use std::collections::BTreeMap;
+
+use bstr::BString;
+use jiff::Timestamp;
+
+use crate::objects::Entry;
+
+/// The syslog priority of an entry, from its `PRIORITY` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Priority {
+	Emergency = 0,
+	Alert = 1,
+	Critical = 2,
+	Error = 3,
+	Warning = 4,
+	Notice = 5,
+	Info = 6,
+	Debug = 7,
+}
+
+impl Priority {
+	/// The priority of a syslog level, from 0 (`emerg`) to 7 (`debug`).
+	pub fn from_level(level: u8) -> Option<Self> {
+		Some(match level {
+			0 => Self::Emergency,
+			1 => Self::Alert,
+			2 => Self::Critical,
+			3 => Self::Error,
+			4 => Self::Warning,
+			5 => Self::Notice,
+			6 => Self::Info,
+			7 => Self::Debug,
+			_ => return None,
+		})
+	}
+
+	/// The syslog level of this priority.
+	pub fn level(self) -> u8 {
+		self as u8
+	}
+
+	/// Parse the value of a `PRIORITY` field, which is the level as a single decimal digit.
+	pub fn parse(value: &[u8]) -> Option<Self> {
+		match value {
+			[digit @ b'0'..=b'7'] => Self::from_level(digit - b'0'),
+			_ => None,
+		}
+	}
+}
+
+/// An entry with its well-known fields parsed, from
+/// [`JournalReader::read_record`](crate::reader::JournalReader::read_record).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+	/// From the `PRIORITY` field.
+	pub priority: Option<Priority>,
+
+	/// From the `MESSAGE` field, empty if the entry doesn't have one.
+	pub message: BString,
+
+	/// When the entry was written to the journal.
+	pub timestamp: Timestamp,
+
+	/// From the `_SYSTEMD_UNIT` field.
+	pub unit: Option<String>,
+
+	/// From the `_PID` field.
+	pub pid: Option<u32>,
+
+	/// The other fields of the entry, by name.
+	///
+	/// This also has the well-known fields whose values couldn't be parsed, so they're not lost.
+	pub fields: BTreeMap<BString, BString>,
+}
+
+impl LogRecord {
+	/// Make a record from an entry and its fields, e.g. from
+	/// [`JournalReader::entries_with_fields`](crate::reader::JournalReader::entries_with_fields).
+	///
+	/// The well-known fields are taken out of the map when they parse, and left in it otherwise.
+	pub fn from_fields(entry: &Entry, mut fields: BTreeMap<BString, BString>) -> Self {
+		Self {
+			priority: take(&mut fields, "PRIORITY", Priority::parse),
+			message: take(&mut fields, "MESSAGE", |value| Some(value.into())).unwrap_or_default(),
+			timestamp: entry.realtime(),
+			unit: take(&mut fields, "_SYSTEMD_UNIT", |value| {
+				String::from_utf8(value.to_vec()).ok()
+			}),
+			pid: take(&mut fields, "_PID", parse_pid),
+			fields,
+		}
+	}
+}
+
+/// Remove a field from the map if its value parses.
+fn take<T>(
+	fields: &mut BTreeMap<BString, BString>,
+	key: &str,
+	parse: impl Fn(&[u8]) -> Option<T>,
+) -> Option<T> {
+	let parsed = parse(fields.get(key.as_bytes())?)?;
+	fields.remove(key.as_bytes());
+	Some(parsed)
+}
+
+fn parse_pid(value: &[u8]) -> Option<u32> {
+	std::str::from_utf8(value)
+		.ok()
+		.filter(|value| value.bytes().all(|byte| byte.is_ascii_digit()))
+		.and_then(|value| value.parse().ok())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_read_record() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{FilenameInfo, JournalReader, JournalSelection},
+		testing::{JournalBuilder, MemoryFiles, TestEntry, MACHINE_ID},
+	};
+
+	let journal = JournalBuilder::new()
+		.entry(
+			TestEntry::new(1, 1000)
+				.with_field("PRIORITY=3")
+				.with_field("_SYSTEMD_UNIT=sshd.service")
+				.with_field("_PID=1234")
+				.with_field("_HOSTNAME=box"),
+		)
+		// malformed well-known fields, which stay in the map
+		.entry(
+			TestEntry::new(2, 2000)
+				.with_field("PRIORITY=warning")
+				.with_field(&b"_SYSTEMD_UNIT=\xff.service"[..])
+				.with_field("_PID=-1"),
+		)
+		.entry(TestEntry::new(3, 3000).with_field("PRIORITY=8"));
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, journal.build()));
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let entries = reader
+		.entries()
+		.map(Result::unwrap)
+		.collect::<Vec<_>>()
+		.await;
+
+	let mut records = Vec::new();
+	for entry in &entries {
+		records.push(reader.read_record(entry).await.unwrap());
+	}
+	let fields = |fields: &[(&str, &[u8])]| {
+		fields
+			.iter()
+			.map(|(key, value)| (BString::from(*key), BString::from(*value)))
+			.collect::<BTreeMap<_, _>>()
+	};
+
+	assert_eq!(
+		records[0],
+		LogRecord {
+			priority: Some(Priority::Error),
+			message: "entry 1".into(),
+			timestamp: Timestamp::from_microsecond(1000).unwrap(),
+			unit: Some("sshd.service".into()),
+			pid: Some(1234),
+			fields: fields(&[("_HOSTNAME", b"box")]),
+		}
+	);
+	assert_eq!(
+		records[1],
+		LogRecord {
+			priority: None,
+			message: "entry 2".into(),
+			timestamp: Timestamp::from_microsecond(2000).unwrap(),
+			unit: None,
+			pid: None,
+			fields: fields(&[
+				("PRIORITY", b"warning"),
+				("_PID", b"-1"),
+				("_SYSTEMD_UNIT", b"\xff.service"),
+			]),
+		}
+	);
+	assert_eq!(records[2].priority, None);
+	assert_eq!(records[2].fields, fields(&[("PRIORITY", b"8")]));
+
+	assert_eq!(Priority::from_level(7), Some(Priority::Debug));
+	assert_eq!(Priority::Debug.level(), 7);
+	assert!(Priority::Emergency < Priority::Debug);
+}
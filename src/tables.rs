@@ -13,26 +13,190 @@ use crate::reader::AsyncFileRead;
 #[derive(Debug, PartialEq, DekuRead, DekuWrite)]
 #[deku(endian = "little")]
 pub struct HashItem {
+	#[deku(
+		map = "|field: u64| -> Result<_, DekuError> { Ok(NonZeroU64::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.head_hash_offset.map(NonZeroU64::get))"
+	)]
 	pub head_hash_offset: Option<NonZeroU64>,
+
+	#[deku(
+		map = "|field: u64| -> Result<_, DekuError> { Ok(NonZeroU64::new(field)) }",
+		writer = "crate::deku_helpers::writer_or_zero(deku::writer, self.tail_hash_offset.map(NonZeroU64::get))"
+	)]
 	pub tail_hash_offset: Option<NonZeroU64>,
 }
 
 pub const HASH_ITEM_SIZE: usize = 16;
 
+/// A walk along a chain of objects, which checks their links so that corrupt ones can't make it
+/// loop forever.
+///
+/// No chain can have more objects than the file, so a walk which goes on for longer than the
+/// header's `n_objects` must be going around a cycle, and errors with `InvalidData`. Hash chains
+/// and entry array chains are also linked in the order objects were appended, so in those a link
+/// that doesn't point further into the file is corrupt, and errors with `InvalidData` too. Other chains, like the
+/// list of Data objects of a Field, aren't in order, and are walked with
+/// [`unordered`](Self::unordered).
+///
+/// The walk doesn't read objects itself: each offset from [`next`](Self::next) is read by the
+/// caller, which passes the object's link to the next one to [`link`](Self::link).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChainWalk {
+	next: Option<NonZeroU64>,
+	current: Option<NonZeroU64>,
+	ordered: bool,
+	n_objects: u64,
+	walked: u64,
+}
+
+impl ChainWalk {
+	/// Walk a hash chain or an entry array chain from its head.
+	pub(crate) fn new(head: Option<NonZeroU64>, n_objects: NonZeroU64) -> Self {
+		Self {
+			next: head,
+			current: None,
+			ordered: true,
+			n_objects: n_objects.get(),
+			walked: 0,
+		}
+	}
+
+	/// Walk a chain whose objects aren't linked in the order they were appended.
+	pub(crate) fn unordered(head: Option<NonZeroU64>, n_objects: NonZeroU64) -> Self {
+		Self {
+			ordered: false,
+			..Self::new(head, n_objects)
+		}
+	}
+
+	/// Get the offset of the next object of the chain, or `None` at its end.
+	///
+	/// Errors with `InvalidData` once more objects have been walked than there are in the file.
+	pub(crate) fn next(&mut self) -> std::io::Result<Option<NonZeroU64>> {
+		self.current = self.next.take();
+		if self.current.is_some() {
+			self.walked += 1;
+			if self.walked > self.n_objects {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!(
+						"cycle detected in object chain: walked more objects than the {} in the file",
+						self.n_objects
+					),
+				));
+			}
+		}
+
+		Ok(self.current)
+	}
+
+	/// Follow the link from the object last returned by [`next`](Self::next), or 0 if it's the last.
+	///
+	/// In an ordered chain, errors with `InvalidData` if the link doesn't point further into the file.
+	pub(crate) fn link(&mut self, next: u64) -> std::io::Result<()> {
+		let next = NonZeroU64::new(next);
+		if let (true, Some(current), Some(next)) = (self.ordered, self.current, next) {
+			if next <= current {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!("object chain goes backwards from {current} to {next}"),
+				));
+			}
+		}
+
+		self.next = next;
+		Ok(())
+	}
+}
+
 /// Hash table abstraction.
 pub struct HashTable<'h> {
 	pub(crate) offset: NonZeroU64,
 	pub(crate) size: NonZeroU64,
+
+	/// Number of objects in the file, which bounds the length of chains.
+	pub(crate) n_objects: NonZeroU64,
 	pub(crate) _phantom: std::marker::PhantomData<&'h ()>,
 }
 
 impl<'h> HashTable<'h> {
 	/// Number of item slots in the hash table.
+	///
+	/// This is zero if the table is smaller than one item, which a real journal never has, but a
+	/// corrupt one can.
 	#[tracing::instrument(level = "trace", skip(self))]
 	pub fn capacity(&self) -> u64 {
 		self.size.get() / HASH_ITEM_SIZE as u64
 	}
 
+	/// Number of item slots in the hash table, which errors with `InvalidData` if there are none.
+	fn nonzero_capacity(&self) -> std::io::Result<NonZeroU64> {
+		NonZeroU64::new(self.capacity()).ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"hash table at {} is {} bytes, too small for a single item",
+					self.offset, self.size
+				),
+			)
+		})
+	}
+
+	/// Offset of the item slot for a hash.
+	///
+	/// Errors with `InvalidData` if the table has no slots.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub fn item_offset(&self, hash: u64) -> std::io::Result<u64> {
+		let capacity = self.nonzero_capacity()?;
+		Ok(self.offset.get() + (hash % capacity) * HASH_ITEM_SIZE as u64)
+	}
+
+	/// Read the item slot for a hash.
+	#[tracing::instrument(level = "trace", skip(self, io))]
+	pub async fn slot<R: AsyncFileRead + Unpin>(
+		&self,
+		io: &mut R,
+		hash: u64,
+	) -> std::io::Result<HashItem> {
+		let item = io
+			.read_some_at(self.item_offset(hash)?, HASH_ITEM_SIZE)
+			.await?;
+		HashItem::from_bytes((&item, 0))
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+			.map(|(_, item)| item)
+	}
+
+	/// Walk the chain of objects in the item slot for a hash, yielding their offsets.
+	///
+	/// The chain is linked through a `next_hash_offset` field in each object, whose position
+	/// depends on the object type: `next_hash_offset_at` is given the offset of an object and
+	/// returns the offset of that field. For both Data and Field objects, this is 8 bytes into the
+	/// object's payload, after the [`ObjectHeader`](crate::objects::ObjectHeader).
+	///
+	/// Objects in a chain are linked in the order they were appended, so a link that doesn't point
+	/// further into the file is an error, as is a chain longer than the file has objects. This
+	/// protects against walking loops forever.
+	///
+	/// Note that all objects of the slot are yielded, not only those with the given hash.
+	#[tracing::instrument(level = "trace", skip(self, io, next_hash_offset_at))]
+	pub fn chain<'io: 'h, R: AsyncFileRead + Unpin>(
+		&'h self,
+		io: &'io mut R,
+		hash: u64,
+		next_hash_offset_at: impl Fn(NonZeroU64) -> u64 + 'h,
+	) -> impl Stream<Item = std::io::Result<NonZeroU64>> + Unpin + 'h {
+		Box::pin(async_stream::try_stream! {
+			let mut walk = ChainWalk::new(self.slot(io, hash).await?.head_hash_offset, self.n_objects);
+			while let Some(offset) = walk.next()? {
+				yield offset;
+
+				let link = io.read_some_at(next_hash_offset_at(offset), 8).await?;
+				// UNWRAP: read_some_at returns exactly the requested size
+				walk.link(u64::from_le_bytes(link.try_into().unwrap()))?;
+			}
+		})
+	}
+
 	/// Iterate over all items in the hash table.
 	#[tracing::instrument(level = "trace", skip(self, io))]
 	pub fn items<'io: 'h, R: AsyncFileRead + Unpin>(
@@ -51,13 +215,24 @@ impl<'h> HashTable<'h> {
 		})
 	}
 
-	/// Count the number of items in the hash table.
+	/// Count the number of occupied item slots in the hash table.
 	///
 	/// This is computed by reading the entire hash table, and ignores errors.
 	#[tracing::instrument(level = "trace", skip(self, io))]
 	pub async fn count<R: AsyncFileRead + Unpin>(&self, io: &mut R) -> u64 {
 		let stream = self.items(io);
-		stream.count().await as _
+		stream
+			.filter(|item| {
+				std::future::ready(matches!(
+					item,
+					Ok(HashItem {
+						head_hash_offset: Some(_),
+						..
+					})
+				))
+			})
+			.count()
+			.await as _
 	}
 
 	/// How full the hash table is.
@@ -65,8 +240,140 @@ impl<'h> HashTable<'h> {
 	/// This is computed by reading the entire hash table, for performance prefer to use
 	/// [`Header::data_fill_level`](crate::header::Header::data_fill_level) or
 	/// [`Header::field_fill_level`](crate::header::Header::field_fill_level) instead.
+	///
+	/// Errors with `InvalidData` if the table has no slots.
 	#[tracing::instrument(level = "trace", skip(self, io))]
-	pub async fn fill_level<R: AsyncFileRead + Unpin>(&self, io: &mut R) -> f64 {
-		self.count(io).await as f64 / self.capacity() as f64
+	pub async fn fill_level<R: AsyncFileRead + Unpin>(&self, io: &mut R) -> std::io::Result<f64> {
+		let capacity = self.nonzero_capacity()?;
+		Ok(self.count(io).await as f64 / capacity.get() as f64)
 	}
+
+	/// Whether the hash table is at least this full (as a fraction of its capacity).
+	///
+	/// This reads the hash table until the answer is known: it stops as soon as enough occupied
+	/// slots have been seen, or there aren't enough slots left for the threshold to be reached. Use
+	/// [`fill_level`](Self::fill_level) when the exact level is needed.
+	///
+	/// Errors with `InvalidData` if the table has no slots.
+	#[tracing::instrument(level = "trace", skip(self, io))]
+	pub async fn is_at_least_full<R: AsyncFileRead + Unpin>(
+		&self,
+		io: &mut R,
+		fraction: f64,
+	) -> std::io::Result<bool> {
+		let capacity = self.nonzero_capacity()?.get();
+		let needed = (fraction * capacity as f64).ceil().max(0.0) as u64;
+		let mut occupied = 0;
+		let mut remaining = capacity;
+
+		let mut items = self.items(io);
+		loop {
+			if occupied >= needed {
+				return Ok(true);
+			}
+			if occupied + remaining < needed {
+				return Ok(false);
+			}
+
+			// UNWRAP: there are remaining slots, or one of the above would have returned
+			let item = items.next().await.unwrap()?;
+			remaining -= 1;
+			if item.head_hash_offset.is_some() {
+				occupied += 1;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_is_at_least_full() {
+	use futures_util::io::Cursor;
+
+	use crate::header::MAX_HEADER_SIZE;
+
+	// a table of 4 slots, of which only the first 2 exist in the file: reading further errors
+	let table = HashTable {
+		offset: NonZeroU64::new(MAX_HEADER_SIZE as _).unwrap(),
+		size: NonZeroU64::new(4 * HASH_ITEM_SIZE as u64).unwrap(),
+		n_objects: NonZeroU64::MAX,
+		_phantom: std::marker::PhantomData,
+	};
+	let file = |slots: [bool; 2]| {
+		let mut file = vec![0; MAX_HEADER_SIZE];
+		for occupied in slots {
+			let offset = if occupied { 1024_u64 } else { 0 };
+			file.extend(offset.to_le_bytes());
+			file.extend(offset.to_le_bytes());
+		}
+		file
+	};
+
+	let occupied = file([true, true]);
+	let mut io = Cursor::new(&occupied[..]);
+	assert!(table.is_at_least_full(&mut io, 0.0).await.unwrap());
+	assert!(table.is_at_least_full(&mut io, 0.5).await.unwrap());
+	assert!(table.is_at_least_full(&mut io, 0.75).await.is_err());
+
+	let empty = file([false, false]);
+	let mut io = Cursor::new(&empty[..]);
+	assert!(!table.is_at_least_full(&mut io, 0.75).await.unwrap());
+	assert!(!table.is_at_least_full(&mut io, 1.5).await.unwrap());
+	assert!(table.is_at_least_full(&mut io, 0.5).await.is_err());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_zero_capacity() {
+	use futures_util::io::Cursor;
+
+	use crate::{
+		header::MAX_HEADER_SIZE,
+		reader::{JournalReader, JournalSelection},
+		testing::{JournalBuilder, MemoryFiles, TestEntry},
+	};
+
+	let table = HashTable {
+		offset: NonZeroU64::new(MAX_HEADER_SIZE as _).unwrap(),
+		size: NonZeroU64::new(HASH_ITEM_SIZE as u64 - 1).unwrap(),
+		n_objects: NonZeroU64::MAX,
+		_phantom: std::marker::PhantomData,
+	};
+	let file = vec![0; MAX_HEADER_SIZE + HASH_ITEM_SIZE];
+	let mut io = Cursor::new(&file[..]);
+	assert_eq!(table.capacity(), 0);
+	assert_eq!(
+		table.item_offset(0x1234).unwrap_err().kind(),
+		std::io::ErrorKind::InvalidData
+	);
+	assert_eq!(
+		table.slot(&mut io, 0x1234).await.unwrap_err().kind(),
+		std::io::ErrorKind::InvalidData
+	);
+	assert_eq!(
+		table.fill_level(&mut io).await.unwrap_err().kind(),
+		std::io::ErrorKind::InvalidData
+	);
+	assert_eq!(
+		table
+			.is_at_least_full(&mut io, 0.5)
+			.await
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::InvalidData
+	);
+
+	// a journal whose header says its data hash table is smaller than one item
+	let journal = JournalBuilder::new().entry(TestEntry::new(1, 1000));
+	let mut bytes = journal.build();
+	// data_hash_table_size, see Header
+	bytes[112..120].copy_from_slice(&8_u64.to_le_bytes());
+	let mut reader =
+		JournalReader::new(MemoryFiles::default().with_file(&journal.latest_name(), bytes));
+	reader
+		.select(JournalSelection::from(journal.latest_name()))
+		.await
+		.unwrap();
+	let err = reader.find_data(b"MESSAGE=entry 1").await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
 }
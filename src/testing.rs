@@ -0,0 +1,733 @@
+//! Builders for synthetic journal files, used in tests.
+
+// builder knobs are used by tests in different modules, not all of them everywhere
+#![allow(dead_code)]
+
+use std::{
+	collections::{BTreeMap, HashMap, VecDeque},
+	io,
+	num::{NonZeroU128, NonZeroU32, NonZeroU64},
+	path::{Path, PathBuf},
+	pin::Pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	task::Poll,
+	time::Duration,
+};
+
+use deku::DekuContainerWrite as _;
+use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
+use futures_util::{io::Cursor, Stream};
+use jiff::Timestamp;
+
+use crate::{
+	hash::jenkins_hash64,
+	header::{CompatibleFlag, Header, IncompatibleFlag, State, MAX_HEADER_SIZE, MIN_HEADER_SIZE},
+	monotonic::Monotonic,
+	objects::{
+		DataCompression, DataObjectCompactPayloadHeader, DataObjectHeader, EntryArrayObjectHeader,
+		EntryObjectHeader, ObjectHeader, ObjectType, TagObjectHeader, OBJECT_HEADER_SIZE,
+		TAG_LENGTH,
+	},
+	reader::{AsyncFileRead, FilenameInfo},
+	tables::{HashItem, HASH_ITEM_SIZE},
+	writer::AsyncFileWrite,
+};
+
+pub(crate) const MACHINE_ID: u128 = 0xc444c71c038d45b0af201444a83b91c9;
+pub(crate) const SEQNUM_ID: u128 = 0xae257a224b70405a9042a99aef057ce0;
+pub(crate) const BOOT_ID: u128 = 0x82eda8af55804abe8eca8efb4072c698;
+
+/// An entry to write into a test journal.
+#[derive(Debug, Clone)]
+pub(crate) struct TestEntry {
+	pub seqnum: u64,
+	pub realtime: u64,
+	pub monotonic: u64,
+	pub boot_id: u128,
+	pub fields: Vec<Vec<u8>>,
+}
+
+impl TestEntry {
+	/// An entry with a MESSAGE field, and a monotonic timestamp equal to its realtime.
+	pub fn new(seqnum: u64, realtime: u64) -> Self {
+		Self {
+			seqnum,
+			realtime,
+			monotonic: realtime,
+			boot_id: BOOT_ID,
+			fields: vec![format!("MESSAGE=entry {seqnum}").into_bytes()],
+		}
+	}
+
+	pub fn with_boot(mut self, boot_id: u128, monotonic: u64) -> Self {
+		self.boot_id = boot_id;
+		self.monotonic = monotonic;
+		self
+	}
+
+	pub fn with_field(mut self, field: impl Into<Vec<u8>>) -> Self {
+		self.fields.push(field.into());
+		self
+	}
+}
+
+/// Lays out a complete journal file in memory.
+#[derive(Debug, Clone)]
+pub(crate) struct JournalBuilder {
+	pub machine_id: u128,
+	pub seqnum_id: u128,
+	pub file_id: u128,
+	pub compact: bool,
+	pub header_size: u64,
+	pub online: bool,
+	pub sealed: bool,
+	pub data_hash_table_capacity: u64,
+	pub array_capacity: u64,
+	pub entries: Vec<TestEntry>,
+	pub custom_objects: Vec<(u8, Vec<u8>)>,
+}
+
+impl JournalBuilder {
+	pub fn new() -> Self {
+		Self {
+			machine_id: MACHINE_ID,
+			seqnum_id: SEQNUM_ID,
+			file_id: 0xa0713ac194e540cca662d1988b5dd924,
+			compact: true,
+			header_size: MAX_HEADER_SIZE as _,
+			online: false,
+			sealed: false,
+			data_hash_table_capacity: 64,
+			array_capacity: 4,
+			entries: Vec::new(),
+			custom_objects: Vec::new(),
+		}
+	}
+
+	pub fn compact(mut self, compact: bool) -> Self {
+		self.compact = compact;
+		self
+	}
+
+	/// Use a header of this size, which may be larger than the library knows about.
+	///
+	/// The bytes past the known fields are left zero.
+	pub fn header_size(mut self, header_size: u64) -> Self {
+		self.header_size = header_size;
+		self
+	}
+
+	/// Leave the file marked online, as if its writer crashed.
+	pub fn online(mut self, online: bool) -> Self {
+		self.online = online;
+		self
+	}
+
+	/// Mark the file as sealed, and add (blank) Tag objects between entries.
+	pub fn sealed(mut self, sealed: bool) -> Self {
+		self.sealed = sealed;
+		self
+	}
+
+	pub fn seqnum_id(mut self, seqnum_id: u128) -> Self {
+		self.seqnum_id = seqnum_id;
+		self
+	}
+
+	pub fn data_hash_table_capacity(mut self, capacity: u64) -> Self {
+		self.data_hash_table_capacity = capacity;
+		self
+	}
+
+	pub fn array_capacity(mut self, array_capacity: u64) -> Self {
+		self.array_capacity = array_capacity;
+		self
+	}
+
+	/// Add an object of a type unknown to the library, after the hash tables.
+	pub fn custom_object(mut self, type_id: u8, payload: &[u8]) -> Self {
+		self.custom_objects.push((type_id, payload.into()));
+		self
+	}
+
+	pub fn entry(mut self, entry: TestEntry) -> Self {
+		self.entries.push(entry);
+		self
+	}
+
+	/// Archived filename for this journal, derived from its first entry.
+	pub fn archived_name(&self) -> FilenameInfo {
+		let head = self
+			.entries
+			.first()
+			.expect("archived journals have entries");
+		FilenameInfo::Archived {
+			machine_id: self.machine_id,
+			scope: "system".into(),
+			file_seqnum: NonZeroU128::new(self.seqnum_id).unwrap(),
+			head_seqnum: NonZeroU64::new(head.seqnum).unwrap(),
+			head_realtime: Timestamp::from_microsecond(head.realtime as _).unwrap(),
+		}
+	}
+
+	/// Latest filename for this journal.
+	pub fn latest_name(&self) -> FilenameInfo {
+		FilenameInfo::Latest {
+			machine_id: self.machine_id,
+			scope: "system".into(),
+		}
+	}
+
+	pub fn build(&self) -> Vec<u8> {
+		let mut file = FileLayout::new(self.header_size);
+		let field_table = file.hash_table(ObjectType::FieldHashTable, 16);
+		let data_table = file.hash_table(ObjectType::DataHashTable, self.data_hash_table_capacity);
+		for (type_id, payload) in &self.custom_objects {
+			file.object(ObjectType::Unknown(*type_id), payload);
+		}
+
+		let mut data_offsets = HashMap::new();
+		// (head, tail, depth) of each used data hash table slot
+		let mut data_slots = HashMap::<u64, (u64, u64, u64)>::new();
+		for payload in self.entries.iter().flat_map(|entry| &entry.fields) {
+			if data_offsets.contains_key(payload) {
+				continue;
+			}
+
+			// the header isn't keyed
+			let hash = jenkins_hash64(payload);
+			let mut object = DataObjectHeader {
+				hash,
+				next_hash_offset: 0,
+				next_field_offset: 0,
+				entry_offset: 0,
+				entry_array_offset: 0,
+				n_entries: self
+					.entries
+					.iter()
+					.flat_map(|entry| &entry.fields)
+					.filter(|field| *field == payload)
+					.count() as _,
+			}
+			.to_bytes()
+			.unwrap();
+			if self.compact {
+				object.extend(
+					DataObjectCompactPayloadHeader {
+						tail_entry_array_offset: 0,
+						tail_entry_array_n_entries: 0,
+					}
+					.to_bytes()
+					.unwrap(),
+				);
+			}
+			object.extend(payload);
+			let offset = file.object(ObjectType::Data, &object);
+			data_offsets.insert(payload.clone(), offset);
+
+			let slot = hash % (data_table.1 / HASH_ITEM_SIZE as u64);
+			if let Some((_, tail, depth)) = data_slots.get_mut(&slot) {
+				// next_hash_offset is the second field of the data header
+				file.patch(*tail + OBJECT_HEADER_SIZE + 8, &offset.to_le_bytes());
+				*tail = offset;
+				*depth += 1;
+			} else {
+				data_slots.insert(slot, (offset, offset, 1));
+			}
+		}
+		let data_hash_chain_depth = data_slots
+			.values()
+			.map(|(_, _, depth)| *depth)
+			.max()
+			.unwrap_or(0);
+		for (slot, (head, tail, _)) in data_slots {
+			let item = HashItem {
+				head_hash_offset: NonZeroU64::new(head),
+				tail_hash_offset: NonZeroU64::new(tail),
+			};
+			file.patch(
+				data_table.0 + slot * HASH_ITEM_SIZE as u64,
+				&item.to_bytes().unwrap(),
+			);
+		}
+
+		let mut n_tags = 0;
+		let mut entry_offsets = Vec::with_capacity(self.entries.len());
+		for entry in &self.entries {
+			let mut object = EntryObjectHeader {
+				seqnum: NonZeroU64::new(entry.seqnum).unwrap(),
+				realtime: Timestamp::from_microsecond(entry.realtime as _).unwrap(),
+				monotonic: Monotonic::new(entry.monotonic).unwrap(),
+				boot_id: NonZeroU128::new(entry.boot_id).unwrap(),
+				xor_hash: entry
+					.fields
+					.iter()
+					.fold(0, |xor, payload| xor ^ jenkins_hash64(payload)),
+			}
+			.to_bytes()
+			.unwrap();
+			for payload in &entry.fields {
+				let offset = data_offsets[payload];
+				if self.compact {
+					object.extend((offset as u32).to_le_bytes());
+				} else {
+					object.extend(offset.to_le_bytes());
+					object.extend(0_u64.to_le_bytes());
+				}
+			}
+			entry_offsets.push(file.object(ObjectType::Entry, &object));
+
+			if self.sealed {
+				let tag = TagObjectHeader {
+					seqnum: NonZeroU64::new(n_tags + 1).unwrap(),
+					epoch: 0,
+					tag: [0; TAG_LENGTH as _],
+				};
+				file.object(ObjectType::Tag, &tag.to_bytes().unwrap());
+				n_tags += 1;
+			}
+		}
+
+		let item_size = if self.compact { 4 } else { 8 };
+		let mut arrays = Vec::new();
+		let mut chunks = entry_offsets
+			.chunks(self.array_capacity as _)
+			.collect::<Vec<_>>();
+		if chunks.is_empty() {
+			chunks.push(&[]);
+		}
+		for chunk in &chunks {
+			let mut object = vec![0; 8 + (self.array_capacity * item_size) as usize];
+			for (n, offset) in chunk.iter().enumerate() {
+				let at = 8 + n * item_size as usize;
+				if self.compact {
+					object[at..at + 4].copy_from_slice(&(*offset as u32).to_le_bytes());
+				} else {
+					object[at..at + 8].copy_from_slice(&offset.to_le_bytes());
+				}
+			}
+			arrays.push(file.object(ObjectType::EntryArray, &object));
+		}
+		for pair in arrays.windows(2) {
+			let next = EntryArrayObjectHeader {
+				next_entry_array_offset: NonZeroU64::new(pair[1]),
+			};
+			file.patch(pair[0] + OBJECT_HEADER_SIZE, &next.to_bytes().unwrap());
+		}
+
+		let head = self.entries.first();
+		let tail = self.entries.last();
+		let header = Header {
+			compatible_flags: if self.sealed {
+				CompatibleFlag::TailEntryBootId | CompatibleFlag::Sealed
+			} else {
+				CompatibleFlag::TailEntryBootId.into()
+			},
+			incompatible_flags: if self.compact {
+				IncompatibleFlag::Compact.into()
+			} else {
+				Default::default()
+			},
+			state: if self.online {
+				State::Online
+			} else {
+				State::Offline
+			},
+			file_id: self.file_id,
+			machine_id: self.machine_id,
+			tail_entry_boot_id: tail.and_then(|e| NonZeroU128::new(e.boot_id)),
+			seqnum_id: NonZeroU128::new(self.seqnum_id).unwrap(),
+			header_size: NonZeroU64::new(self.header_size).unwrap(),
+			arena_size: NonZeroU64::new(file.bytes.len() as u64 - self.header_size).unwrap(),
+			data_hash_table_offset: NonZeroU64::new(data_table.0).unwrap(),
+			data_hash_table_size: NonZeroU64::new(data_table.1).unwrap(),
+			field_hash_table_offset: NonZeroU64::new(field_table.0).unwrap(),
+			field_hash_table_size: NonZeroU64::new(field_table.1).unwrap(),
+			tail_object_offset: NonZeroU64::new(file.tail_object).unwrap(),
+			n_objects: NonZeroU64::new(file.n_objects).unwrap(),
+			n_entries: self.entries.len() as _,
+			tail_entry_seqnum: tail.and_then(|e| NonZeroU64::new(e.seqnum)),
+			head_entry_seqnum: head.and_then(|e| NonZeroU64::new(e.seqnum)),
+			entry_array_offset: NonZeroU64::new(arrays[0]).unwrap(),
+			head_entry_realtime: head
+				.map(|e| Timestamp::from_microsecond(e.realtime as _).unwrap()),
+			tail_entry_realtime: tail
+				.map(|e| Timestamp::from_microsecond(e.realtime as _).unwrap()),
+			tail_entry_monotonic: tail.and_then(|e| Monotonic::new(e.monotonic)),
+			n_data: Some(data_offsets.len() as _),
+			n_fields: Some(0),
+			n_tags: Some(n_tags),
+			n_entry_arrays: Some(arrays.len() as _),
+			data_hash_chain_depth: Some(data_hash_chain_depth),
+			field_hash_chain_depth: Some(0),
+			tail_entry_array_offset: NonZeroU32::new(*arrays.last().unwrap() as _),
+			tail_entry_array_n_entries: NonZeroU32::new(chunks.last().unwrap().len() as _),
+			tail_entry_offset: entry_offsets.last().and_then(|o| NonZeroU64::new(*o)),
+		};
+		file.patch(0, &header.to_bytes().unwrap());
+
+		file.bytes
+	}
+}
+
+#[derive(Debug)]
+struct FileLayout {
+	bytes: Vec<u8>,
+	tail_object: u64,
+	n_objects: u64,
+}
+
+impl FileLayout {
+	fn new(header_size: u64) -> Self {
+		Self {
+			bytes: vec![0; header_size as usize],
+			tail_object: 0,
+			n_objects: 0,
+		}
+	}
+
+	/// Append an object with the given payload (after the object header), returning its offset.
+	fn object(&mut self, r#type: ObjectType, payload: &[u8]) -> u64 {
+		self.bytes.resize(self.bytes.len().next_multiple_of(8), 0);
+		let offset = self.bytes.len() as u64;
+		let header = ObjectHeader {
+			r#type,
+			compression: DataCompression::None,
+			size: OBJECT_HEADER_SIZE + payload.len() as u64,
+		};
+		self.bytes.extend(header.to_bytes().unwrap());
+		self.bytes.extend(payload);
+		self.bytes.resize(self.bytes.len().next_multiple_of(8), 0);
+		self.tail_object = offset;
+		self.n_objects += 1;
+		offset
+	}
+
+	/// Append an empty hash table object, returning the (offset, size) of its items.
+	fn hash_table(&mut self, r#type: ObjectType, capacity: u64) -> (u64, u64) {
+		let size = capacity * HASH_ITEM_SIZE as u64;
+		let offset = self.object(r#type, &vec![0; size as _]);
+		(offset + OBJECT_HEADER_SIZE, size)
+	}
+
+	fn patch(&mut self, offset: u64, bytes: &[u8]) {
+		let offset = offset as usize;
+		self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+	}
+}
+
+/// A set of journal files held in memory.
+///
+/// Files opened with [`rotate`](AsyncFileWrite::rotate) are writable, and their contents are
+/// stored back when they're closed (or another file is opened).
+#[derive(Debug, Default)]
+pub(crate) struct MemoryFiles {
+	files: BTreeMap<PathBuf, Arc<[u8]>>,
+	open: Option<OpenFile>,
+	no_clone: bool,
+	contiguous: bool,
+	handles: Arc<OpenHandles>,
+
+	/// Changes made by each call to [`wait_for_change`](AsyncFileRead::wait_for_change), if any.
+	changes: VecDeque<Option<(PathBuf, Arc<[u8]>)>>,
+
+	/// Number of read calls made, to check IO patterns.
+	pub reads: usize,
+
+	/// Timeouts passed to [`wait_for_change`](AsyncFileRead::wait_for_change).
+	pub waits: Vec<Duration>,
+
+	/// Offset at which reads stay pending, as if the disk were slow, to cancel reads part-way.
+	pub stall_at: Option<u64>,
+}
+
+/// Count of the handles with a file open, shared between clones.
+#[derive(Debug, Default)]
+struct OpenHandles {
+	now: AtomicUsize,
+	peak: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct OpenFile {
+	path: PathBuf,
+	file: Cursor<Vec<u8>>,
+	writable: bool,
+	read_floor: u64,
+	read_ceiling: u64,
+}
+
+impl MemoryFiles {
+	pub fn with_file(mut self, info: &FilenameInfo, bytes: Vec<u8>) -> Self {
+		self.files.insert(Self::make_filename(info), bytes.into());
+		self
+	}
+
+	/// Make [`try_clone`](AsyncFileRead::try_clone) unsupported, like the trait default.
+	pub fn without_clone(mut self) -> Self {
+		self.no_clone = true;
+		self
+	}
+
+	/// Expose the open file as a slice through [`contiguous`](AsyncFileRead::contiguous), like an
+	/// implementation which holds files in memory.
+	pub fn with_contiguous(mut self) -> Self {
+		self.contiguous = true;
+		self
+	}
+
+	/// Queue a change to make on the next wait for changes, or `None` for a wait with no change.
+	///
+	/// Changes replace the contents of a file, as if it was written to while waiting. Once all
+	/// queued changes are made, waits sleep for their timeout.
+	pub fn with_change(mut self, change: Option<(&FilenameInfo, Vec<u8>)>) -> Self {
+		self.changes
+			.push_back(change.map(|(info, bytes)| (Self::make_filename(info), bytes.into())));
+		self
+	}
+
+	/// The most handles (this one and its clones) which had a file open at the same time.
+	pub fn peak_open(&self) -> usize {
+		self.handles.peak.load(Ordering::Relaxed)
+	}
+
+	/// Get the current contents of a file, including unsaved writes if it's open.
+	pub fn bytes(&self, info: &FilenameInfo) -> Option<Vec<u8>> {
+		let path = Self::make_filename(info);
+		match &self.open {
+			Some(open) if open.path == path => Some(open.file.get_ref().clone()),
+			_ => self.files.get(&path).map(|bytes| bytes.to_vec()),
+		}
+	}
+
+	fn set_open(&mut self, open: OpenFile) {
+		self.open = Some(open);
+		let now = self.handles.now.fetch_add(1, Ordering::Relaxed) + 1;
+		self.handles.peak.fetch_max(now, Ordering::Relaxed);
+	}
+
+	fn save(&mut self) {
+		if let Some(open) = self.open.take() {
+			self.handles.now.fetch_sub(1, Ordering::Relaxed);
+			if open.writable {
+				self.files.insert(open.path, open.file.into_inner().into());
+			}
+		}
+	}
+
+	fn open_file(&mut self) -> io::Result<&mut Cursor<Vec<u8>>> {
+		self.open
+			.as_mut()
+			.map(|open| &mut open.file)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "no file open"))
+	}
+}
+
+impl AsyncFileRead for MemoryFiles {
+	async fn open(&mut self, filename: &Path) -> io::Result<()> {
+		self.save();
+		let bytes = self
+			.files
+			.get(filename)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))?;
+		self.set_open(OpenFile {
+			path: filename.into(),
+			file: Cursor::new(bytes.to_vec()),
+			writable: false,
+			read_floor: MIN_HEADER_SIZE as _,
+			read_ceiling: u64::MAX,
+		});
+		Ok(())
+	}
+
+	async fn close(&mut self) {
+		self.save();
+	}
+
+	fn current(&self) -> Option<&Path> {
+		self.open.as_ref().map(|open| open.path.as_ref())
+	}
+
+	fn read_floor(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(MIN_HEADER_SIZE as _, |open| open.read_floor)
+	}
+
+	fn set_read_floor(&mut self, floor: u64) {
+		if let Some(open) = self.open.as_mut() {
+			open.read_floor = floor;
+		}
+	}
+
+	fn read_ceiling(&self) -> u64 {
+		self.open
+			.as_ref()
+			.map_or(u64::MAX, |open| open.read_ceiling)
+	}
+
+	fn set_read_ceiling(&mut self, ceiling: u64) {
+		if let Some(open) = self.open.as_mut() {
+			open.read_ceiling = ceiling;
+		}
+	}
+
+	fn contiguous(&self) -> Option<&[u8]> {
+		self.open
+			.as_ref()
+			.filter(|_| self.contiguous)
+			.map(|open| open.file.get_ref().as_slice())
+	}
+
+	fn try_clone(&self) -> io::Result<Self> {
+		if self.no_clone {
+			return Err(io::Error::new(io::ErrorKind::Unsupported, "no clone"));
+		}
+
+		Ok(Self {
+			files: self.files.clone(),
+			open: None,
+			no_clone: false,
+			contiguous: self.contiguous,
+			handles: self.handles.clone(),
+			changes: VecDeque::new(),
+			reads: 0,
+			waits: Vec::new(),
+			stall_at: self.stall_at,
+		})
+	}
+
+	async fn wait_for_change(&mut self, timeout: Duration) -> io::Result<()> {
+		self.waits.push(timeout);
+		match self.changes.pop_front() {
+			Some(Some((path, bytes))) => {
+				self.files.insert(path, bytes);
+			}
+			Some(None) => {}
+			None => crate::reader::sleep(timeout).await,
+		}
+		Ok(())
+	}
+
+	async fn file_size(&self, filename: &Path) -> io::Result<u64> {
+		// the open file may have been written to since it was opened
+		if let Some(open) = self.open.as_ref().filter(|open| open.path == filename) {
+			return Ok(open.file.get_ref().len() as u64);
+		}
+
+		self.files
+			.get(filename)
+			.map(|bytes| bytes.len() as u64)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+	}
+
+	fn list_files(
+		&self,
+		prefix: Option<&Path>,
+	) -> impl Stream<Item = io::Result<FilenameInfo>> + Unpin {
+		let prefix = prefix.map(|p| p.to_string_lossy().into_owned());
+		futures_util::stream::iter(
+			self.files
+				.keys()
+				.filter(|path| {
+					prefix
+						.as_ref()
+						.is_none_or(|prefix| path.to_string_lossy().starts_with(prefix))
+				})
+				.filter_map(|path| Self::parse_filename(path))
+				.map(Ok)
+				.collect::<Vec<_>>(),
+		)
+	}
+}
+
+impl Drop for MemoryFiles {
+	fn drop(&mut self) {
+		if self.open.is_some() {
+			self.handles.now.fetch_sub(1, Ordering::Relaxed);
+		}
+	}
+}
+
+impl AsyncFileWrite for MemoryFiles {
+	async fn rotate(&mut self, filename: &Path) -> io::Result<()> {
+		self.save();
+		let bytes = self.files.remove(filename).unwrap_or_default();
+		self.set_open(OpenFile {
+			path: filename.into(),
+			file: Cursor::new(bytes.to_vec()),
+			writable: true,
+			read_floor: MIN_HEADER_SIZE as _,
+			read_ceiling: u64::MAX,
+		});
+		Ok(())
+	}
+
+	fn writeable(&self) -> Option<bool> {
+		self.open.as_ref().map(|open| open.writable)
+	}
+}
+
+impl AsyncSeek for MemoryFiles {
+	fn poll_seek(
+		mut self: Pin<&mut Self>,
+		cx: &mut std::task::Context<'_>,
+		pos: io::SeekFrom,
+	) -> Poll<io::Result<u64>> {
+		match self.open_file() {
+			Ok(file) => Pin::new(file).poll_seek(cx, pos),
+			Err(err) => Poll::Ready(Err(err)),
+		}
+	}
+}
+
+impl AsyncRead for MemoryFiles {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut std::task::Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		self.reads += 1;
+		let stall_at = self.stall_at;
+		match self.open_file() {
+			Ok(file) if stall_at == Some(file.position()) => Poll::Pending,
+			Ok(file) => Pin::new(file).poll_read(cx, buf),
+			Err(err) => Poll::Ready(Err(err)),
+		}
+	}
+}
+
+impl AsyncWrite for MemoryFiles {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut std::task::Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		if self.writeable() != Some(true) {
+			return Poll::Ready(Err(io::Error::new(
+				io::ErrorKind::PermissionDenied,
+				"file not open for writing",
+			)));
+		}
+
+		match self.open_file() {
+			Ok(file) => Pin::new(file).poll_write(cx, buf),
+			Err(err) => Poll::Ready(Err(err)),
+		}
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_close(
+		mut self: Pin<&mut Self>,
+		_cx: &mut std::task::Context<'_>,
+	) -> Poll<io::Result<()>> {
+		self.save();
+		Poll::Ready(Ok(()))
+	}
+}
@@ -1,13 +1,99 @@
+use std::{
+	collections::HashMap,
+	io::SeekFrom,
+	num::{NonZeroU128, NonZeroU32, NonZeroU64},
+};
+
+use deku::{DekuContainerRead as _, DekuContainerWrite as _};
+use flagset::FlagSet;
+use futures_util::io::AsyncWriteExt as _;
+use jiff::Timestamp;
+
+pub use clock::{Clock, SystemClock};
 pub use file_write::AsyncFileWrite;
-pub use options::CreateOptions;
+pub use options::{Compression, CreateOptions};
 
+use crate::{
+	compression::compress,
+	hash::jenkins_hash64,
+	header::{
+		header_size_for_version, CompatibleFlag, Header, IncompatibleFlag, State, MAX_HEADER_SIZE,
+	},
+	monotonic::Monotonic,
+	objects::{
+		Data, DataCompression, DataObjectCompactPayloadHeader, DataObjectHeader, EntryArray,
+		EntryObjectHeader, Field, FieldObjectHeader, ObjectHeader, ObjectType, SimpleRead,
+		DATA_OBJECT_HEADER_SIZE, ENTRY_ARRAY_HEADER_SIZE, OBJECT_HEADER_SIZE,
+	},
+	reader::FilenameInfo,
+	tables::{ChainWalk, HashItem, HASH_ITEM_SIZE},
+};
+
+mod clock;
 mod file_write;
 mod options;
 
+/// Number of items in the entry array of a new file.
+const INITIAL_ENTRY_ARRAY_CAPACITY: u64 = 4;
+
 pub struct JournalWriter<T> {
 	options: CreateOptions,
-	_io: T,
-	prepared: bool,
+	io: T,
+
+	/// Header of the file being written, once prepared.
+	header: Option<Header>,
+
+	/// The latest monotonic time of each boot in the file being written.
+	boot_monotonic: HashMap<NonZeroU128, Monotonic>,
+
+	/// Where entries get their times and boot ID from, when not given.
+	clock: Box<dyn Clock>,
+
+	/// State of the pseudo-random IDs, if seeded with [`CreateOptions::id_seed`].
+	id_state: Option<u64>,
+}
+
+/// Metadata of an entry to write, which goes in its header rather than in its fields.
+///
+/// Everything is optional: see [`JournalWriter::write_entry_with`] for what's used instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+	/// The wallclock time of the entry.
+	pub realtime: Option<Timestamp>,
+
+	/// The monotonic time of the entry, within its boot.
+	pub monotonic: Option<Monotonic>,
+
+	/// The boot the entry was created in.
+	pub boot_id: Option<NonZeroU128>,
+
+	/// The sequence number of the entry.
+	///
+	/// Sequence numbers must increase within a file, so this is only used if it's after the last
+	/// entry's.
+	pub seqnum: Option<NonZeroU64>,
+}
+
+impl EntryMetadata {
+	pub fn with_realtime(mut self, realtime: Timestamp) -> Self {
+		self.realtime = Some(realtime);
+		self
+	}
+
+	pub fn with_monotonic(mut self, monotonic: Monotonic) -> Self {
+		self.monotonic = Some(monotonic);
+		self
+	}
+
+	pub fn with_boot_id(mut self, boot_id: NonZeroU128) -> Self {
+		self.boot_id = Some(boot_id);
+		self
+	}
+
+	pub fn with_seqnum(mut self, seqnum: NonZeroU64) -> Self {
+		self.seqnum = Some(seqnum);
+		self
+	}
 }
 
 impl<T> std::fmt::Debug for JournalWriter<T> {
@@ -15,53 +101,1474 @@ impl<T> std::fmt::Debug for JournalWriter<T> {
 		f.debug_struct("JournalWriter")
 			.field("options", &self.options)
 			.field("io", &std::any::type_name::<T>())
-			.field("prepared", &self.prepared)
+			.field("prepared", &self.header.is_some())
 			.finish()
 	}
 }
 
+impl<T> Drop for JournalWriter<T> {
+	fn drop(&mut self) {
+		if self.header.is_some() {
+			tracing::warn!(
+				"journal writer dropped without being closed, the file is left online and will look crashed to readers"
+			);
+		}
+	}
+}
+
 impl<T> JournalWriter<T>
 where
 	T: AsyncFileWrite,
 {
 	pub fn with_options(io: T, options: CreateOptions) -> Self {
 		Self {
+			clock: Box::new(SystemClock::new(options.boot_id)),
+			id_state: options.id_seed,
 			options,
-			_io: io,
-			prepared: false,
+			io,
+			header: None,
+			boot_monotonic: HashMap::new(),
 		}
 	}
 
+	/// Use another clock for the times and boot ID of entries.
+	///
+	/// The default is a [`SystemClock`] with the [`boot_id`](CreateOptions::boot_id) option.
+	pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+		self.clock = Box::new(clock);
+		self
+	}
+
 	/// Prepare the journal for writing.
 	///
 	/// This must be called before writing any entries. It will error if:
 	/// - the journal is already open (e.g. by another process)
 	/// - opening the journal file fails
 	/// - reading the journal header fails
+	/// - the journal file was created by another machine, unless
+	///   [`check_machine_id`](CreateOptions::check_machine_id) is disabled
 	/// - writing the journal status fails
+	///
+	/// Appending to an existing journal file is not supported yet, so this also errors if the
+	/// latest file of the journal already exists.
+	#[tracing::instrument(level = "trace", skip(self))]
 	pub async fn prepare(&mut self) -> std::io::Result<()> {
-		self.prepared = true;
-		todo!()
+		if self.options.seal {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"sealing is not supported yet",
+			));
+		}
+
+		let latest = T::make_filename(&FilenameInfo::Latest {
+			machine_id: self.options.machine_id,
+			scope: self.options.scope.clone(),
+		});
+		match self.io.open(&latest).await {
+			Ok(()) => {
+				let header = Header::read(&mut self.io).await;
+				self.io.close().await;
+				let header = header?;
+				if self.options.check_machine_id && header.machine_id != self.options.machine_id {
+					return Err(std::io::Error::new(
+						std::io::ErrorKind::PermissionDenied,
+						format!(
+							"journal file belongs to machine {:032x}, refusing to write to it from machine {:032x}",
+							header.machine_id, self.options.machine_id
+						),
+					));
+				}
+
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::AlreadyExists,
+					"journal file already exists, appending to existing files is not supported yet",
+				));
+			}
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+			Err(err) => return Err(err),
+		}
+
+		self.io.rotate(&latest).await?;
+		self.create().await
 	}
 
 	/// Write an entry (a set of key-value items) to the journal.
+	///
+	/// The entry is timestamped by the writer's [`Clock`]. By default that's the current time, and
+	/// as there's no portable way to read the system's monotonic clock, its monotonic timestamp
+	/// counts from when the writer was created. Use [`with_clock`](Self::with_clock) to change the
+	/// clock, or [`write_entry_with`](Self::write_entry_with) to set these for one entry.
 	pub async fn write_entry(
 		&mut self,
-		_fields: impl Iterator<Item = (String, bstr::BString)>,
+		fields: impl Iterator<Item = (String, bstr::BString)>,
 	) -> std::io::Result<()> {
-		if !self.prepared {
+		self.write_entry_with(EntryMetadata::default(), fields)
+			.await
+			.map(drop)
+	}
+
+	/// Write an entry to the journal, with some or all of its metadata.
+	///
+	/// Metadata which isn't given is filled in as for [`write_entry`](Self::write_entry): the
+	/// timestamps and boot ID are taken from the writer's [`Clock`], and the sequence number
+	/// follows the last entry's.
+	///
+	/// Monotonic timestamps must not go backwards within a boot, so this errors with
+	/// `InvalidInput` if the entry is before an earlier entry of the same boot in this file, even
+	/// if entries of other boots were written in between.
+	///
+	/// Each field is stored as a `FIELD=value` Data object, shared with other entries which have
+	/// the same one, and identical fields within the entry are only stored once. Entries must have
+	/// at least one field.
+	///
+	/// Returns the offset of the new Entry object.
+	#[tracing::instrument(level = "trace", skip(self, fields))]
+	pub async fn write_entry_with(
+		&mut self,
+		metadata: EntryMetadata,
+		fields: impl Iterator<Item = (String, bstr::BString)>,
+	) -> std::io::Result<NonZeroU64> {
+		if self.header.is_none() {
 			self.prepare().await?;
 		}
-		todo!()
+
+		let (realtime, monotonic, boot_id) =
+			match (metadata.realtime, metadata.monotonic, metadata.boot_id) {
+				(Some(realtime), Some(monotonic), Some(boot_id)) => (realtime, monotonic, boot_id),
+				(realtime, monotonic, boot_id) => {
+					let (now, now_monotonic, now_boot_id) = self.clock.now();
+					(
+						realtime.unwrap_or(now),
+						monotonic.unwrap_or(now_monotonic),
+						// a zero boot ID is replaced by the largest one, which is not zero
+						boot_id
+							.unwrap_or(NonZeroU128::new(now_boot_id).unwrap_or(NonZeroU128::MAX)),
+					)
+				}
+			};
+		if let Some(latest) = self
+			.boot_monotonic
+			.get(&boot_id)
+			.filter(|latest| latest.0 > monotonic.0)
+		{
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!(
+					"monotonic time {} is before an earlier entry's ({}) in the same boot {boot_id:032x}",
+					monotonic.0, latest.0
+				),
+			));
+		}
+
+		// (offset, hash, unkeyed hash) of each data
+		let mut items = Vec::new();
+		for (key, value) in fields {
+			let mut payload = key.into_bytes();
+			payload.push(b'=');
+			payload.extend_from_slice(&value);
+			let offset = self.intern_data(&payload).await?;
+			items.push((
+				offset,
+				self.header()?.hash(&payload),
+				jenkins_hash64(&payload),
+			));
+		}
+		items.sort_unstable_by_key(|(offset, _, _)| *offset);
+		items.dedup_by_key(|(offset, _, _)| *offset);
+		if items.is_empty() {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				"entries must have at least one field",
+			));
+		}
+
+		let header = self.header()?;
+		let is_compact = header.is_compact();
+		// UNWRAP: seqnums are at least one after zero
+		let next_seqnum =
+			NonZeroU64::new(header.tail_entry_seqnum.map_or(0, NonZeroU64::get) + 1).unwrap();
+		let entry = EntryObjectHeader {
+			seqnum: metadata
+				.seqnum
+				.filter(|seqnum| *seqnum > next_seqnum)
+				.unwrap_or(next_seqnum),
+			realtime,
+			monotonic,
+			boot_id,
+			xor_hash: items.iter().fold(0, |xor, (_, _, hash)| xor ^ hash),
+		};
+		let mut payload = entry
+			.to_bytes()
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		for (offset, hash, _) in &items {
+			if is_compact {
+				// compact files are smaller than 4GiB, so their offsets fit
+				payload.extend((*offset as u32).to_le_bytes());
+			} else {
+				payload.extend(offset.to_le_bytes());
+				payload.extend(hash.to_le_bytes());
+			}
+		}
+		let offset = self
+			.append_object(ObjectType::Entry, DataCompression::None, &payload)
+			.await?;
+
+		let header = self.header()?;
+		let (array, n_items) = self
+			.link_entry_array(header.entry_array_offset, header.n_entries, offset)
+			.await?;
+		for (data, _, _) in &items {
+			self.link_data_entry(*data, offset).await?;
+		}
+
+		self.boot_monotonic.insert(entry.boot_id, entry.monotonic);
+		let header = self.header_mut()?;
+		let has = |end: u64| header.header_size.get() >= end;
+		header.n_entries += 1;
+		header.head_entry_seqnum.get_or_insert(entry.seqnum);
+		header.tail_entry_seqnum = Some(entry.seqnum);
+		header.head_entry_realtime.get_or_insert(entry.realtime);
+		header.tail_entry_realtime = Some(entry.realtime);
+		header.tail_entry_monotonic = Some(entry.monotonic);
+		header.tail_entry_boot_id = Some(entry.boot_id);
+		if has(264) {
+			header.tail_entry_array_offset =
+				u32::try_from(array.get()).ok().and_then(NonZeroU32::new);
+			header.tail_entry_array_n_entries =
+				u32::try_from(n_items).ok().and_then(NonZeroU32::new);
+		}
+		if has(272) {
+			header.tail_entry_offset = Some(offset);
+		}
+		tracing::trace!(?offset, seqnum = ?entry.seqnum, "appended new entry");
+		Ok(offset)
 	}
 
 	/// Seal the journal.
 	///
 	/// This should be called at a regular interval to prevent tampering.
 	pub async fn seal(&mut self) -> std::io::Result<()> {
-		if !self.prepared {
+		if self.header.is_none() {
 			self.prepare().await?;
 		}
 		todo!()
 	}
+
+	/// Write the header out to the file, and flush.
+	///
+	/// Appending objects only updates the header in memory, so this should be called after a batch
+	/// of appends, to make them visible to readers.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn sync(&mut self) -> std::io::Result<()> {
+		let header = self.header.as_ref().ok_or_else(not_prepared)?;
+		header.write_to(&mut self.io).await?;
+		self.io.flush().await
+	}
+
+	/// Close the journal file.
+	///
+	/// This writes out the header with the file marked [`State::Offline`], so that readers know it
+	/// was closed cleanly and isn't being written to anymore, then closes the file.
+	///
+	/// Does nothing if the writer isn't prepared.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub async fn close(&mut self) -> std::io::Result<()> {
+		let Some(header) = self.header.as_mut() else {
+			return Ok(());
+		};
+
+		header.state = State::Offline;
+		self.sync().await?;
+		self.io.close().await;
+		self.header = None;
+		Ok(())
+	}
+
+	// == Internal ==
+
+	/// Lay out a new file in the current (empty) file: the header, the hash tables, and an empty
+	/// entry array.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn create(&mut self) -> std::io::Result<()> {
+		let field_hash_table_size = hash_table_size(self.options.field_hash_table_capacity)?;
+		let data_hash_table_size = hash_table_size(self.options.data_hash_table_capacity)?;
+
+		let header_size = self
+			.options
+			.compat_level
+			.map_or(MAX_HEADER_SIZE as _, header_size_for_version);
+		let compact = self.options.compact && self.options.supports_version(252);
+
+		let field_hash_table_offset = header_size;
+		let data_hash_table_offset = Self::write_object(
+			&mut self.io,
+			field_hash_table_offset,
+			ObjectType::FieldHashTable,
+			DataCompression::None,
+			&vec![0; field_hash_table_size.get() as _],
+		)
+		.await?;
+
+		let mut incompatible_flags = FlagSet::default();
+		if self.options.supports_version(246) {
+			incompatible_flags |= IncompatibleFlag::KeyedHash;
+		}
+		if compact {
+			incompatible_flags |= IncompatibleFlag::Compact;
+		}
+
+		let entry_array_offset = Self::write_object(
+			&mut self.io,
+			data_hash_table_offset,
+			ObjectType::DataHashTable,
+			DataCompression::None,
+			&vec![0; data_hash_table_size.get() as _],
+		)
+		.await?;
+
+		let item_size = if compact { 4 } else { 8 };
+		let end = Self::write_object(
+			&mut self.io,
+			entry_array_offset,
+			ObjectType::EntryArray,
+			DataCompression::None,
+			&vec![0; (ENTRY_ARRAY_HEADER_SIZE + INITIAL_ENTRY_ARRAY_CAPACITY * item_size) as _],
+		)
+		.await?;
+
+		// optional fields are only present if the header is large enough to hold them
+		let has = |end: u64| header_size >= end;
+
+		let file_id = match self.options.file_id {
+			Some(file_id) => file_id,
+			None => self.new_id().get(),
+		};
+		let seqnum_id = match self.options.seqnum_id {
+			Some(seqnum_id) => seqnum_id,
+			None => self.new_id(),
+		};
+
+		self.boot_monotonic.clear();
+		// UNWRAPs: all offsets are after the header, so non-zero
+		self.header = Some(Header {
+			compatible_flags: CompatibleFlag::TailEntryBootId.into(),
+			incompatible_flags,
+			state: State::Online,
+			file_id,
+			machine_id: self.options.machine_id,
+			tail_entry_boot_id: NonZeroU128::new(self.options.boot_id),
+			seqnum_id,
+			header_size: NonZeroU64::new(header_size).unwrap(),
+			arena_size: NonZeroU64::new(end - header_size).unwrap(),
+			data_hash_table_offset: NonZeroU64::new(data_hash_table_offset + OBJECT_HEADER_SIZE)
+				.unwrap(),
+			data_hash_table_size,
+			field_hash_table_offset: NonZeroU64::new(field_hash_table_offset + OBJECT_HEADER_SIZE)
+				.unwrap(),
+			field_hash_table_size,
+			tail_object_offset: NonZeroU64::new(entry_array_offset).unwrap(),
+			n_objects: NonZeroU64::new(3).unwrap(),
+			n_entries: 0,
+			tail_entry_seqnum: None,
+			head_entry_seqnum: None,
+			entry_array_offset: NonZeroU64::new(entry_array_offset).unwrap(),
+			head_entry_realtime: None,
+			tail_entry_realtime: None,
+			tail_entry_monotonic: None,
+			n_data: has(216).then_some(0),
+			n_fields: has(224).then_some(0),
+			n_tags: has(232).then_some(0),
+			n_entry_arrays: has(240).then_some(1),
+			data_hash_chain_depth: has(248).then_some(0),
+			field_hash_chain_depth: has(256).then_some(0),
+			tail_entry_array_offset: NonZeroU32::new(entry_array_offset as _).filter(|_| has(260)),
+			tail_entry_array_n_entries: None,
+			tail_entry_offset: None,
+		});
+
+		self.sync().await
+	}
+
+	/// Find the Field object for a field name, or append a new one.
+	///
+	/// Returns the offset of the Field object.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub(crate) async fn intern_field(&mut self, name: &[u8]) -> std::io::Result<u64> {
+		let header = self.header()?;
+		let hash = header.hash(name);
+		let item_offset = header.field_hash_table().item_offset(hash)?;
+		let n_objects = header.n_objects;
+		let item = Self::read_hash_item(&mut self.io, item_offset).await?;
+
+		let mut depth = 0;
+		let mut walk = ChainWalk::new(item.head_hash_offset, n_objects);
+		while let Some(offset) = walk.next()? {
+			depth += 1;
+			let field = Field::read_at(&mut self.io, offset.get()).await?;
+			if field.header.hash == hash && field.name == name {
+				tracing::trace!(?offset, ?depth, "found existing field");
+				return Ok(offset.get());
+			}
+			walk.link(field.header.next_hash_offset)?;
+		}
+
+		let mut payload = FieldObjectHeader {
+			hash,
+			next_hash_offset: 0,
+			next_data_offset: 0,
+		}
+		.to_bytes()
+		.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		payload.extend(name);
+		let offset = self
+			.append_object(ObjectType::Field, DataCompression::None, &payload)
+			.await?;
+		self.link_hash_item(item_offset, item, offset).await?;
+
+		let header = self.header_mut()?;
+		header.n_fields = header.n_fields.map(|n| n + 1);
+		header.field_hash_chain_depth = header.field_hash_chain_depth.map(|d| d.max(depth + 1));
+		tracing::trace!(?offset, chain_depth = depth + 1, "appended new field");
+		Ok(offset.get())
+	}
+
+	/// Find the Data object for a `FIELD=value` payload, or append a new one.
+	///
+	/// New Data objects are also linked into the list of their Field (which is created if needed).
+	///
+	/// Returns the offset of the Data object.
+	#[tracing::instrument(level = "trace", skip(self))]
+	pub(crate) async fn intern_data(&mut self, field_eq_value: &[u8]) -> std::io::Result<u64> {
+		let Some(eq) = field_eq_value.iter().position(|byte| *byte == b'=') else {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				"data payload must be of the form FIELD=value",
+			));
+		};
+
+		let header = self.header()?;
+		let is_compact = header.is_compact();
+		let hash = header.hash(field_eq_value);
+		let item_offset = header.data_hash_table().item_offset(hash)?;
+		let n_objects = header.n_objects;
+		let item = Self::read_hash_item(&mut self.io, item_offset).await?;
+
+		let mut depth = 0;
+		let mut walk = ChainWalk::new(item.head_hash_offset, n_objects);
+		while let Some(offset) = walk.next()? {
+			depth += 1;
+			ObjectHeader::read_at(&mut self.io, offset.get())
+				.await?
+				.check_type(ObjectType::Data)?;
+			let data =
+				DataObjectHeader::read_at(&mut self.io, offset.get() + OBJECT_HEADER_SIZE).await?;
+			if data.hash == hash {
+				let existing = Data::read_at(&mut self.io, offset.get(), is_compact).await?;
+				if existing.key.len() == eq
+					&& field_eq_value.starts_with(&existing.key)
+					&& field_eq_value[eq + 1..] == existing.value[..]
+				{
+					tracing::trace!(?offset, ?depth, "found existing data");
+					return Ok(offset.get());
+				}
+			}
+			walk.link(data.next_hash_offset)?;
+		}
+
+		let field_offset = self.intern_field(&field_eq_value[..eq]).await?;
+		let field =
+			FieldObjectHeader::read_at(&mut self.io, field_offset + OBJECT_HEADER_SIZE).await?;
+
+		let mut payload = DataObjectHeader {
+			hash,
+			next_hash_offset: 0,
+			next_field_offset: field.next_data_offset,
+			entry_offset: 0,
+			entry_array_offset: 0,
+			n_entries: 0,
+		}
+		.to_bytes()
+		.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		if is_compact {
+			payload.extend(
+				DataObjectCompactPayloadHeader {
+					tail_entry_array_offset: 0,
+					tail_entry_array_n_entries: 0,
+				}
+				.to_bytes()
+				.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?,
+			);
+		}
+		let compression = self
+			.compress_data(field_eq_value, &mut payload)?
+			.map_or(DataCompression::None, |compression| {
+				compression.data_compression()
+			});
+		let flag = compression.incompatible_flag();
+		let offset = self
+			.append_object(ObjectType::Data, compression, &payload)
+			.await?;
+		self.link_hash_item(item_offset, item, offset).await?;
+		if let Some(flag) = flag {
+			self.header_mut()?.incompatible_flags |= flag;
+		}
+
+		// new data goes at the head of the field's list
+		Self::write_at(
+			&mut self.io,
+			field_offset + OBJECT_HEADER_SIZE + FIELD_NEXT_DATA_OFFSET,
+			&offset.get().to_le_bytes(),
+		)
+		.await?;
+
+		let header = self.header_mut()?;
+		header.n_data = header.n_data.map(|n| n + 1);
+		header.data_hash_chain_depth = header.data_hash_chain_depth.map(|d| d.max(depth + 1));
+		tracing::trace!(?offset, chain_depth = depth + 1, "appended new data");
+		Ok(offset.get())
+	}
+
+	/// Add an entry to the list of entries of a Data object.
+	///
+	/// The first entry is stored in the Data object itself, and the rest in its entry array chain.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn link_data_entry(&mut self, data: u64, entry: NonZeroU64) -> std::io::Result<()> {
+		let header_offset = data + OBJECT_HEADER_SIZE;
+		let object = DataObjectHeader::read_at(&mut self.io, header_offset).await?;
+		if object.n_entries == 0 {
+			Self::write_at(
+				&mut self.io,
+				header_offset + DATA_ENTRY_OFFSET,
+				&entry.get().to_le_bytes(),
+			)
+			.await?;
+		} else {
+			let (array, n_items) = if let Some(first) = NonZeroU64::new(object.entry_array_offset) {
+				self.link_entry_array(first, object.n_entries - 1, entry)
+					.await?
+			} else {
+				let array = self
+					.append_entry_array(INITIAL_ENTRY_ARRAY_CAPACITY, entry)
+					.await?;
+				Self::write_at(
+					&mut self.io,
+					header_offset + DATA_ENTRY_ARRAY_OFFSET,
+					&array.get().to_le_bytes(),
+				)
+				.await?;
+				(array, 1)
+			};
+
+			if self.header()?.is_compact() {
+				let tail = DataObjectCompactPayloadHeader {
+					tail_entry_array_offset: array.get() as _,
+					tail_entry_array_n_entries: n_items as _,
+				}
+				.to_bytes()
+				.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+				Self::write_at(&mut self.io, header_offset + DATA_OBJECT_HEADER_SIZE, &tail)
+					.await?;
+			}
+		}
+
+		Self::write_at(
+			&mut self.io,
+			header_offset + DATA_N_ENTRIES,
+			&(object.n_entries + 1).to_le_bytes(),
+		)
+		.await
+	}
+
+	/// Put an entry at an index of an entry array chain, appending an array if the chain is full.
+	///
+	/// Appended arrays are twice the size of the last array of the chain. Returns the offset of the
+	/// array the entry went in, and how many items of that array are now filled.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn link_entry_array(
+		&mut self,
+		first: NonZeroU64,
+		mut index: u64,
+		entry: NonZeroU64,
+	) -> std::io::Result<(NonZeroU64, u64)> {
+		// borrow the header apart from the io
+		let header = self.header.as_ref().ok_or_else(not_prepared)?;
+		let item_size = header.sizeof_entry_array_item();
+		let mut walk = ChainWalk::new(Some(first), header.n_objects);
+		// UNWRAP: the walk starts on the first array
+		let mut array = EntryArray::read_at(&mut self.io, walk.next()?.unwrap(), header).await?;
+		while index >= array.capacity() {
+			index -= array.capacity();
+			walk.link(array.next_offset.map_or(0, NonZeroU64::get))?;
+			let Some(next) = walk.next()? else {
+				let capacity = (array.capacity() * 2).max(INITIAL_ENTRY_ARRAY_CAPACITY);
+				let next = self.append_entry_array(capacity, entry).await?;
+				Self::write_at(
+					&mut self.io,
+					array.offset.get() + OBJECT_HEADER_SIZE,
+					&next.get().to_le_bytes(),
+				)
+				.await?;
+				return Ok((next, 1));
+			};
+			let header = self.header.as_ref().ok_or_else(not_prepared)?;
+			array = EntryArray::read_at(&mut self.io, next, header).await?;
+		}
+
+		let item = &entry.get().to_le_bytes()[..item_size as usize];
+		Self::write_at(
+			&mut self.io,
+			array.offset.get() + OBJECT_HEADER_SIZE + ENTRY_ARRAY_HEADER_SIZE + index * item_size,
+			item,
+		)
+		.await?;
+		Ok((array.offset, index + 1))
+	}
+
+	/// Append a new entry array, with an entry as its first item.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn append_entry_array(
+		&mut self,
+		capacity: u64,
+		entry: NonZeroU64,
+	) -> std::io::Result<NonZeroU64> {
+		let item_size = self.header()?.sizeof_entry_array_item() as usize;
+		let mut payload = vec![0; ENTRY_ARRAY_HEADER_SIZE as usize + capacity as usize * item_size];
+		let first = ENTRY_ARRAY_HEADER_SIZE as usize;
+		payload[first..first + item_size].copy_from_slice(&entry.get().to_le_bytes()[..item_size]);
+		let offset = self
+			.append_object(ObjectType::EntryArray, DataCompression::None, &payload)
+			.await?;
+
+		let header = self.header_mut()?;
+		header.n_entry_arrays = header.n_entry_arrays.map(|n| n + 1);
+		Ok(offset)
+	}
+
+	/// Generate a new v4 UUID, seeded if configured.
+	fn new_id(&mut self) -> NonZeroU128 {
+		let uuid = match self.id_state.as_mut() {
+			None => uuid::Uuid::new_v4(),
+			Some(state) => {
+				let mut bytes = [0; 16];
+				for half in bytes.chunks_exact_mut(8) {
+					half.copy_from_slice(&splitmix64(state).to_le_bytes());
+				}
+				uuid::Builder::from_random_bytes(bytes).into_uuid()
+			}
+		};
+		// UNWRAP: v4 UUIDs have version bits set, so are never zero
+		NonZeroU128::new(uuid.as_u128()).unwrap()
+	}
+
+	/// Append a Data payload to an object, compressed if configured and worth it.
+	///
+	/// Returns the compression that was used.
+	#[tracing::instrument(level = "trace", skip(self, data, object))]
+	fn compress_data(
+		&self,
+		data: &[u8],
+		object: &mut Vec<u8>,
+	) -> std::io::Result<Option<Compression>> {
+		if let Some(compression) = self
+			.options
+			.compression
+			.filter(|_| data.len() as u64 >= self.options.compression_threshold)
+		{
+			let compressed = compress(&compression.data_compression(), data)?;
+			tracing::trace!(
+				?compression,
+				size = data.len(),
+				compressed = compressed.len(),
+				"compressed data"
+			);
+			if compressed.len() < data.len() {
+				object.extend(compressed);
+				return Ok(Some(compression));
+			}
+		}
+
+		object.extend(data);
+		Ok(None)
+	}
+
+	/// Append an object at the end of the arena, updating the header.
+	#[tracing::instrument(level = "trace", skip(self, payload))]
+	async fn append_object(
+		&mut self,
+		r#type: ObjectType,
+		compression: DataCompression,
+		payload: &[u8],
+	) -> std::io::Result<NonZeroU64> {
+		let header = self.header()?;
+		let offset = header.header_size.get() + header.arena_size.get();
+		let end = (offset + OBJECT_HEADER_SIZE + payload.len() as u64).next_multiple_of(8);
+		if header.is_compact() && end > u64::from(u32::MAX) {
+			return Err(std::io::Error::other(
+				"compact journal file is full, it needs to be rotated",
+			));
+		}
+
+		Self::write_object(&mut self.io, offset, r#type, compression, payload).await?;
+
+		// UNWRAPs: offsets are after the header, so non-zero
+		let header = self.header_mut()?;
+		header.arena_size = NonZeroU64::new(end - header.header_size.get()).unwrap();
+		header.tail_object_offset = NonZeroU64::new(offset).unwrap();
+		header.n_objects = header.n_objects.saturating_add(1);
+		Ok(NonZeroU64::new(offset).unwrap())
+	}
+
+	/// Add a newly appended object at the tail of a hash table item's chain.
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn link_hash_item(
+		&mut self,
+		item_offset: u64,
+		item: HashItem,
+		offset: NonZeroU64,
+	) -> std::io::Result<()> {
+		if let Some(tail) = item.tail_hash_offset {
+			Self::write_at(
+				&mut self.io,
+				tail.get() + OBJECT_HEADER_SIZE + NEXT_HASH_OFFSET,
+				&offset.get().to_le_bytes(),
+			)
+			.await?;
+		}
+
+		let item = HashItem {
+			head_hash_offset: item.head_hash_offset.or(Some(offset)),
+			tail_hash_offset: Some(offset),
+		};
+		let bytes = item
+			.to_bytes()
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		Self::write_at(&mut self.io, item_offset, &bytes).await
+	}
+
+	/// Read a hash table item.
+	#[tracing::instrument(level = "trace", skip(io))]
+	async fn read_hash_item(io: &mut T, item_offset: u64) -> std::io::Result<HashItem> {
+		let bytes = io.read_some_at(item_offset, HASH_ITEM_SIZE).await?;
+		HashItem::from_bytes((&bytes, 0))
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+			.map(|(_, item)| item)
+	}
+
+	/// Write an object at an offset, padded to 8 bytes, returning the offset after it.
+	#[tracing::instrument(level = "trace", skip(io, payload))]
+	async fn write_object(
+		io: &mut T,
+		offset: u64,
+		r#type: ObjectType,
+		compression: DataCompression,
+		payload: &[u8],
+	) -> std::io::Result<u64> {
+		let mut bytes = ObjectHeader {
+			r#type,
+			compression,
+			size: OBJECT_HEADER_SIZE + payload.len() as u64,
+		}
+		.to_bytes()
+		.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		bytes.extend(payload);
+		bytes.resize(bytes.len().next_multiple_of(8), 0);
+		Self::write_at(io, offset, &bytes).await?;
+		Ok(offset + bytes.len() as u64)
+	}
+
+	#[tracing::instrument(level = "trace", skip(io, bytes))]
+	async fn write_at(io: &mut T, offset: u64, bytes: &[u8]) -> std::io::Result<()> {
+		io.seek(SeekFrom::Start(offset)).await?;
+		io.write_all(bytes).await
+	}
+
+	/// The files being written to, for tests to read them back.
+	#[cfg(test)]
+	pub(crate) fn io(&self) -> &T {
+		&self.io
+	}
+
+	fn header(&self) -> std::io::Result<&Header> {
+		self.header.as_ref().ok_or_else(not_prepared)
+	}
+
+	fn header_mut(&mut self) -> std::io::Result<&mut Header> {
+		self.header.as_mut().ok_or_else(not_prepared)
+	}
+}
+
+/// Offset of `next_hash_offset` in both Data and Field object headers.
+const NEXT_HASH_OFFSET: u64 = 8;
+
+/// Offset of `next_data_offset` in the Field object header.
+const FIELD_NEXT_DATA_OFFSET: u64 = 16;
+
+/// Offset of `entry_offset` in the Data object header.
+const DATA_ENTRY_OFFSET: u64 = 24;
+
+/// Offset of `entry_array_offset` in the Data object header.
+const DATA_ENTRY_ARRAY_OFFSET: u64 = 32;
+
+/// Offset of `n_entries` in the Data object header.
+const DATA_N_ENTRIES: u64 = 40;
+
+fn not_prepared() -> std::io::Error {
+	std::io::Error::new(
+		std::io::ErrorKind::NotConnected,
+		"journal writer is not prepared",
+	)
+}
+
+fn hash_table_size(capacity: u64) -> std::io::Result<NonZeroU64> {
+	NonZeroU64::new(capacity * HASH_ITEM_SIZE as u64).ok_or_else(|| {
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			"hash tables must have a capacity of at least one",
+		)
+	})
+}
+
+/// Advance a SplitMix64 state and return the next pseudo-random number.
+fn splitmix64(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9e3779b97f4a7c15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+	z ^ (z >> 31)
+}
+
+#[cfg(test)]
+async fn test_writer(options: CreateOptions) -> JournalWriter<crate::testing::MemoryFiles> {
+	let mut writer = JournalWriter::with_options(crate::testing::MemoryFiles::default(), options);
+	writer.prepare().await.unwrap();
+	writer
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_intern_field() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+
+	let message = writer.intern_field(b"MESSAGE").await.unwrap();
+	let objects = writer.header.as_ref().unwrap().n_objects;
+	assert_eq!(writer.intern_field(b"MESSAGE").await.unwrap(), message);
+	assert_eq!(writer.header.as_ref().unwrap().n_objects, objects);
+
+	let priority = writer.intern_field(b"PRIORITY").await.unwrap();
+	assert_ne!(priority, message);
+	let header = writer.header.as_ref().unwrap();
+	assert_eq!(header.n_fields, Some(2));
+	assert_eq!(header.tail_object_offset.get(), priority);
+	assert_eq!(header.field_hash_chain_depth, Some(1));
+
+	let field = FieldObjectHeader::read_at(&mut writer.io, priority + OBJECT_HEADER_SIZE)
+		.await
+		.unwrap();
+	assert_eq!(field.hash, header.hash(b"PRIORITY"));
+	assert_eq!(field.next_data_offset, 0);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_intern_data() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+
+	let hello = writer.intern_data(b"MESSAGE=hello").await.unwrap();
+	assert_eq!(writer.intern_data(b"MESSAGE=hello").await.unwrap(), hello);
+	let world = writer.intern_data(b"MESSAGE=world").await.unwrap();
+	assert_ne!(world, hello);
+	let header = writer.header.as_ref().unwrap();
+	assert_eq!(header.n_data, Some(2));
+	assert_eq!(header.n_fields, Some(1));
+
+	let data = Data::read_at(&mut writer.io, world, true).await.unwrap();
+	assert_eq!(data.key, "MESSAGE");
+	assert_eq!(data.value, "world");
+	assert_eq!(data.header.hash, header.hash(b"MESSAGE=world"));
+	assert_eq!(data.header.next_field_offset, hello);
+
+	// the field's list of data starts with the newest
+	let field_offset = writer.intern_field(b"MESSAGE").await.unwrap();
+	let field = FieldObjectHeader::read_at(&mut writer.io, field_offset + OBJECT_HEADER_SIZE)
+		.await
+		.unwrap();
+	assert_eq!(field.next_data_offset, world);
+
+	let err = writer.intern_data(b"MESSAGE").await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_intern_collisions() {
+	use futures_util::StreamExt as _;
+
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	// with a single slot, everything collides
+	let mut writer = test_writer(
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system")
+			.with_compact(false)
+			.with_data_hash_table_capacity(1)
+			.with_field_hash_table_capacity(1),
+	)
+	.await;
+
+	let a = writer.intern_data(b"A=1").await.unwrap();
+	let b = writer.intern_data(b"B=2").await.unwrap();
+	let c = writer.intern_data(b"A=3").await.unwrap();
+	assert_eq!(writer.intern_data(b"A=1").await.unwrap(), a);
+	assert_eq!(writer.intern_data(b"B=2").await.unwrap(), b);
+	assert_eq!(writer.intern_data(b"A=3").await.unwrap(), c);
+
+	let header = writer.header.as_ref().unwrap();
+	assert_eq!(header.n_data, Some(3));
+	assert_eq!(header.n_fields, Some(2));
+	assert_eq!(header.data_hash_chain_depth, Some(3));
+	assert_eq!(header.field_hash_chain_depth, Some(2));
+
+	let table = header.data_hash_table();
+	let hash = header.hash(b"B=2");
+	let item = table.slot(&mut writer.io, hash).await.unwrap();
+	assert_eq!(item.head_hash_offset, NonZeroU64::new(a));
+	assert_eq!(item.tail_hash_offset, NonZeroU64::new(c));
+
+	let chain = table
+		.chain(&mut writer.io, hash, |offset| {
+			offset.get() + OBJECT_HEADER_SIZE + NEXT_HASH_OFFSET
+		})
+		.map(|offset| offset.unwrap().get())
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(chain, vec![a, b, c]);
+}
+
+#[cfg(all(test, feature = "zstd"))]
+#[tokio::test]
+async fn test_intern_data_compressed() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let mut writer = test_writer(
+		CreateOptions::new(MACHINE_ID, BOOT_ID, "system").with_compression(Some(Compression::Zstd)),
+	)
+	.await;
+
+	let short = writer.intern_data(b"MESSAGE=short").await.unwrap();
+	assert!(!writer
+		.header
+		.as_ref()
+		.unwrap()
+		.incompatible_flags
+		.contains(IncompatibleFlag::CompressedZstd));
+
+	let message = format!("MESSAGE={}", "all work and no play ".repeat(100));
+	let long = writer.intern_data(message.as_bytes()).await.unwrap();
+	assert_eq!(writer.intern_data(message.as_bytes()).await.unwrap(), long);
+	assert!(writer
+		.header
+		.as_ref()
+		.unwrap()
+		.incompatible_flags
+		.contains(IncompatibleFlag::CompressedZstd));
+
+	let data = Data::read_at(&mut writer.io, short, true).await.unwrap();
+	assert_eq!(data.compression, DataCompression::None);
+
+	let data = Data::read_at(&mut writer.io, long, true).await.unwrap();
+	assert_eq!(data.compression, DataCompression::Zstd);
+	assert_eq!(data.key, "MESSAGE");
+	assert_eq!(data.value, message["MESSAGE=".len()..]);
+	assert_eq!(data.uncompressed_size, message.len() as u64);
+	assert!(data.compressed_size < data.uncompressed_size);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_sync_header() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+	let created = Header::read(&mut writer.io).await.unwrap();
+	assert_eq!(created.state, State::Online);
+	assert_eq!(created.n_data, Some(0));
+	assert_eq!(created.tail_entry_seqnum, None);
+	assert_eq!(Some(&created), writer.header.as_ref());
+
+	writer.intern_data(b"MESSAGE=hello").await.unwrap();
+	assert_eq!(Header::read(&mut writer.io).await.unwrap(), created);
+
+	writer.sync().await.unwrap();
+	let synced = Header::read(&mut writer.io).await.unwrap();
+	assert_eq!(synced.n_data, Some(1));
+	assert_eq!(synced.n_fields, Some(1));
+	assert_eq!(Some(&synced), writer.header.as_ref());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_compat_level() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let mut writer =
+		test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system").with_compat_level(187)).await;
+	let hello = writer.intern_data(b"MESSAGE=hello").await.unwrap();
+	writer.sync().await.unwrap();
+
+	let header = Header::read(&mut writer.io).await.unwrap();
+	assert_eq!(header.header_size.get(), 224);
+	assert_eq!(header.n_data, Some(1));
+	assert_eq!(header.n_fields, Some(1));
+	assert_eq!(header.n_tags, None);
+	assert_eq!(header.n_entry_arrays, None);
+	assert_eq!(header.data_hash_chain_depth, None);
+	assert_eq!(header.tail_entry_array_offset, None);
+	assert_eq!(header.tail_entry_offset, None);
+	assert_eq!(
+		header.field_hash_table_offset.get(),
+		224 + OBJECT_HEADER_SIZE
+	);
+	assert!(!header.is_compact());
+	assert!(!header
+		.incompatible_flags
+		.contains(IncompatibleFlag::KeyedHash));
+
+	let data = Data::read_at(&mut writer.io, hello, false).await.unwrap();
+	assert_eq!(data.value, "hello");
+
+	for (version, header_size) in [(186, 208), (189, 240), (246, 256), (253, 264), (257, 272)] {
+		assert_eq!(header_size_for_version(version), header_size, "{version}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_close_offline() {
+	use crate::{
+		reader::AsyncFileRead,
+		testing::{BOOT_ID, MACHINE_ID},
+	};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+	writer.intern_data(b"MESSAGE=hello").await.unwrap();
+	writer.close().await.unwrap();
+	assert!(writer.header.is_none());
+	assert_eq!(writer.io.current(), None);
+
+	let latest = crate::testing::MemoryFiles::make_filename(&FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	});
+	writer.io.open(&latest).await.unwrap();
+	let header = Header::read(&mut writer.io).await.unwrap();
+	assert_eq!(header.state, State::Offline);
+	assert_eq!(header.n_data, Some(1));
+
+	// closing again is a no-op
+	writer.close().await.unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_read_empty_file() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{JournalReader, JournalSelection, Seek},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+	};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+	writer.close().await.unwrap();
+
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let bytes = writer.io.bytes(&latest).unwrap();
+	let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, bytes));
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	for seek in [Seek::Oldest, Seek::Newest, Seek::Seqnum(1)] {
+		reader.seek(seek).await.unwrap();
+		assert!(reader.entries().next().await.is_none(), "{seek:?}");
+	}
+	assert_eq!(reader.summary().await.unwrap().n_entries, 0);
+	assert!(reader.verify_all().await.unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_verify_field_chains() {
+	use crate::{
+		reader::{ChainError, JournalReader, JournalSelection},
+		testing::{MemoryFiles, BOOT_ID, MACHINE_ID},
+	};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+	let hello = writer.intern_data(b"MESSAGE=hello").await.unwrap();
+	let world = writer.intern_data(b"MESSAGE=world").await.unwrap();
+	let priority = writer.intern_data(b"PRIORITY=6").await.unwrap();
+	let message = writer.intern_field(b"MESSAGE").await.unwrap();
+	writer.close().await.unwrap();
+
+	let latest = FilenameInfo::Latest {
+		machine_id: MACHINE_ID,
+		scope: "system".into(),
+	};
+	let bytes = writer.io.bytes(&latest).unwrap();
+	let verify = |bytes: Vec<u8>| {
+		let latest = latest.clone();
+		async move {
+			let mut reader = JournalReader::new(MemoryFiles::default().with_file(&latest, bytes));
+			reader
+				.select(JournalSelection {
+					machine_id: MACHINE_ID,
+					scope: "system".into(),
+				})
+				.await
+				.unwrap();
+			reader.verify_field_chains().await.unwrap()
+		}
+	};
+	assert_eq!(verify(bytes.clone()).await, vec![]);
+
+	// MESSAGE links to world, which links to hello
+	let patch = |at: u64, to: u64| {
+		let mut bytes = bytes.clone();
+		bytes[at as usize..at as usize + 8].copy_from_slice(&to.to_le_bytes());
+		bytes
+	};
+	let field_next_data = message + OBJECT_HEADER_SIZE + FIELD_NEXT_DATA_OFFSET;
+	let data_next_field = |data: u64| data + OBJECT_HEADER_SIZE + 16;
+	assert_eq!(
+		verify(patch(field_next_data, bytes.len() as u64 + 8)).await,
+		vec![ChainError::Dangling {
+			from: message,
+			to: bytes.len() as u64 + 8
+		}]
+	);
+	assert_eq!(
+		verify(patch(data_next_field(world), message)).await,
+		vec![ChainError::WrongType {
+			from: world,
+			to: message,
+			found: ObjectType::Field
+		}]
+	);
+	assert_eq!(
+		verify(patch(data_next_field(world), priority)).await,
+		vec![ChainError::WrongField {
+			field: message,
+			data: priority
+		}]
+	);
+	assert_eq!(
+		verify(patch(data_next_field(hello), world)).await,
+		vec![ChainError::Cycle { start: message }]
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_write_entry() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{BOOT_ID, MACHINE_ID},
+	};
+
+	for compact in [true, false] {
+		let mut writer =
+			test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system").with_compact(compact))
+				.await;
+		// enough entries to grow the entry array chains past their first arrays
+		for n in 1..=10 {
+			let fields = [
+				("MESSAGE".to_string(), format!("entry {n}").into()),
+				("PRIORITY".to_string(), "6".into()),
+				("PRIORITY".to_string(), "6".into()),
+			];
+			writer.write_entry(fields.into_iter()).await.unwrap();
+		}
+		let err = writer.write_entry(std::iter::empty()).await.unwrap_err();
+		assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+		let priority = writer.intern_data(b"PRIORITY=6").await.unwrap();
+		let data = Data::read_at(&mut writer.io, priority, compact)
+			.await
+			.unwrap();
+		assert_eq!(data.header.n_entries, 10);
+		let header = writer.header.as_ref().unwrap();
+		assert_eq!(header.n_entries, 10);
+		assert_eq!(header.head_entry_seqnum, NonZeroU64::new(1));
+		assert_eq!(header.tail_entry_seqnum, NonZeroU64::new(10));
+		assert_eq!(header.n_entry_arrays, Some(4));
+		writer.close().await.unwrap();
+
+		let mut reader = JournalReader::new(writer.io.try_clone().unwrap());
+		reader
+			.select(JournalSelection {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.await
+			.unwrap();
+		let seqnums = reader
+			.entries()
+			.map(|entry| {
+				let entry = entry.unwrap();
+				assert_eq!(entry.boot_id(), BOOT_ID);
+				assert_eq!(entry.objects.len(), 2);
+				entry.seqnum().get()
+			})
+			.collect::<Vec<_>>()
+			.await;
+		assert_eq!(seqnums, (1..=10).collect::<Vec<_>>());
+		assert!(reader.verify_all().await.unwrap(), "compact: {compact}");
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_prepare_other_machine() {
+	use crate::testing::{JournalBuilder, MemoryFiles, BOOT_ID, MACHINE_ID};
+
+	let other = 0xae257a224b70405a9042a99aef057ce0;
+	let journal = JournalBuilder::new();
+	// a file from MACHINE_ID, under the name of the other machine's journal
+	let files = || {
+		MemoryFiles::default().with_file(
+			&FilenameInfo::Latest {
+				machine_id: other,
+				scope: "system".into(),
+			},
+			journal.build(),
+		)
+	};
+
+	let mut writer =
+		JournalWriter::with_options(files(), CreateOptions::new(other, BOOT_ID, "system"));
+	let err = writer.prepare().await.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+	assert!(
+		err.to_string().contains(&format!("{MACHINE_ID:032x}")),
+		"{err}"
+	);
+
+	// without the check, it's only refused because appending isn't supported
+	let mut writer = JournalWriter::with_options(
+		files(),
+		CreateOptions::new(other, BOOT_ID, "system").with_check_machine_id(false),
+	);
+	assert_eq!(
+		writer.prepare().await.unwrap_err().kind(),
+		std::io::ErrorKind::AlreadyExists
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_with_clock() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{BOOT_ID, MACHINE_ID},
+	};
+
+	let realtime = Timestamp::from_microsecond(1_700_000_000_000_000).unwrap();
+	let boot_id = 0x5eed5eed5eed5eed5eed5eed5eed5eed;
+	let mut ticks = 0;
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system"))
+		.await
+		.with_clock(move || {
+			ticks += 1;
+			// UNWRAP: at least one
+			(realtime, Monotonic::new(ticks).unwrap(), boot_id)
+		});
+	for n in 1..=2 {
+		let fields = [("MESSAGE".to_string(), format!("entry {n}").into())];
+		writer.write_entry(fields.into_iter()).await.unwrap();
+	}
+	// given metadata takes precedence, and the clock isn't read if everything is given
+	let given = EntryMetadata::default()
+		.with_realtime(Timestamp::from_microsecond(1000).unwrap())
+		.with_monotonic(Monotonic::new(1000).unwrap())
+		.with_boot_id(NonZeroU128::new(BOOT_ID).unwrap());
+	for metadata in [given, EntryMetadata::default()] {
+		let fields = [("MESSAGE".to_string(), "entry".into())];
+		writer
+			.write_entry_with(metadata, fields.into_iter())
+			.await
+			.unwrap();
+	}
+	writer.close().await.unwrap();
+
+	let mut reader = JournalReader::new(writer.io.try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	let headers = reader
+		.entries()
+		.map(|entry| {
+			let entry = entry.unwrap();
+			(
+				entry.header.realtime,
+				entry.header.monotonic.0.get(),
+				entry.header.boot_id.get(),
+			)
+		})
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(
+		headers,
+		[
+			(realtime, 1, boot_id),
+			(realtime, 2, boot_id),
+			(Timestamp::from_microsecond(1000).unwrap(), 1000, BOOT_ID),
+			(realtime, 3, boot_id),
+		]
+	);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_write_monotonic_regression() {
+	use futures_util::StreamExt as _;
+
+	use crate::{
+		reader::{AsyncFileRead as _, JournalReader, JournalSelection},
+		testing::{BOOT_ID, MACHINE_ID},
+	};
+
+	let mut writer = test_writer(CreateOptions::new(MACHINE_ID, BOOT_ID, "system")).await;
+	let metadata = |boot_id: u128, monotonic: u64| {
+		EntryMetadata::default()
+			.with_boot_id(NonZeroU128::new(boot_id).unwrap())
+			.with_monotonic(Monotonic::new(monotonic).unwrap())
+	};
+	let fields = || [("MESSAGE".to_string(), "entry".into())].into_iter();
+
+	writer
+		.write_entry_with(metadata(BOOT_ID, 500), fields())
+		.await
+		.unwrap();
+	// the same time is fine
+	writer
+		.write_entry_with(metadata(BOOT_ID, 500), fields())
+		.await
+		.unwrap();
+	let err = writer
+		.write_entry_with(metadata(BOOT_ID, 400), fields())
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+	assert_eq!(writer.header.as_ref().unwrap().n_entries, 2);
+
+	// another boot starts over
+	writer
+		.write_entry_with(metadata(0x0b0075, 10), fields())
+		.await
+		.unwrap();
+	let header = writer.header.as_ref().unwrap();
+	assert_eq!(header.tail_entry_boot_id, NonZeroU128::new(0x0b0075));
+	assert_eq!(header.tail_entry_monotonic, Monotonic::new(10));
+	// but going back to the first boot still has to be after its latest entry
+	let err = writer
+		.write_entry_with(metadata(BOOT_ID, 400), fields())
+		.await
+		.unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+	writer
+		.write_entry_with(metadata(BOOT_ID, 600), fields())
+		.await
+		.unwrap();
+	writer.close().await.unwrap();
+
+	// the verifier agrees with what was written
+	let mut reader = JournalReader::new(writer.io().try_clone().unwrap());
+	reader
+		.select(JournalSelection {
+			machine_id: MACHINE_ID,
+			scope: "system".into(),
+		})
+		.await
+		.unwrap();
+	assert_eq!(reader.entries().count().await, 4);
+	assert!(reader.verify_all().await.unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_deterministic_ids() {
+	use crate::testing::{BOOT_ID, MACHINE_ID};
+
+	let write = |options: CreateOptions| async move {
+		let mut writer = test_writer(options).await.with_clock(|| {
+			(
+				Timestamp::from_microsecond(1_700_000_000_000_000).unwrap(),
+				Monotonic::new(1).unwrap(),
+				BOOT_ID,
+			)
+		});
+		for n in 1..=3 {
+			let fields = [("MESSAGE".to_string(), format!("entry {n}").into())];
+			writer.write_entry(fields.into_iter()).await.unwrap();
+		}
+		writer.close().await.unwrap();
+		writer
+			.io
+			.bytes(&FilenameInfo::Latest {
+				machine_id: MACHINE_ID,
+				scope: "system".into(),
+			})
+			.unwrap()
+	};
+	// file_id is at 24..40 and seqnum_id at 72..88 in the header
+	let ids = |bytes: &[u8]| {
+		(
+			u128::from_le_bytes(bytes[24..40].try_into().unwrap()),
+			u128::from_le_bytes(bytes[72..88].try_into().unwrap()),
+		)
+	};
+	let options = CreateOptions::new(MACHINE_ID, BOOT_ID, "system");
+
+	let seqnum_id = NonZeroU128::new(0x5e91d).unwrap();
+	let pinned = options
+		.clone()
+		.with_file_id(0xf11e1d)
+		.with_seqnum_id(seqnum_id);
+	let golden = write(pinned.clone()).await;
+	assert_eq!(ids(&golden), (0xf11e1d, seqnum_id.get()));
+	assert_eq!(write(pinned).await, golden);
+
+	let seeded = write(options.clone().with_id_seed(42)).await;
+	assert_eq!(write(options.clone().with_id_seed(42)).await, seeded);
+	let (file_id, seqnum_id) = ids(&seeded);
+	assert_ne!(file_id, seqnum_id);
+	assert_eq!(uuid::Uuid::from_u128(file_id).get_version_num(), 4);
+	assert_ne!(
+		ids(&write(options.clone().with_id_seed(43)).await),
+		(file_id, seqnum_id)
+	);
+
+	// random by default
+	assert_ne!(
+		ids(&write(options.clone()).await),
+		ids(&write(options).await)
+	);
 }
@@ -0,0 +1,57 @@
+use std::time::Instant;
+
+use jiff::Timestamp;
+
+use crate::monotonic::Monotonic;
+
+/// Where a [`JournalWriter`](super::JournalWriter) gets the times and boot of the entries it
+/// writes.
+///
+/// This is called for each entry whose [`EntryMetadata`](super::EntryMetadata) doesn't have all
+/// of the realtime, monotonic time, and boot ID, and those it doesn't have are taken from it. The
+/// default is the [`SystemClock`]. Replace it to write entries at given times, e.g. when
+/// backfilling historical logs, or in tests.
+///
+/// Closures returning `(realtime, monotonic, boot_id)` are clocks. A zero boot ID is replaced by
+/// the largest one, as entries can't have a zero boot ID.
+pub trait Clock: Send {
+	/// The realtime, monotonic time, and boot ID of an entry written now.
+	fn now(&mut self) -> (Timestamp, Monotonic, u128);
+}
+
+impl<F> Clock for F
+where
+	F: FnMut() -> (Timestamp, Monotonic, u128) + Send,
+{
+	fn now(&mut self) -> (Timestamp, Monotonic, u128) {
+		self()
+	}
+}
+
+/// The system's wallclock, with monotonic time counted from when the clock was created.
+///
+/// The boot ID is the one given, which is the writer's
+/// [`boot_id`](super::CreateOptions::boot_id) option for the default clock.
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+	boot_id: u128,
+	started: Instant,
+}
+
+impl SystemClock {
+	pub fn new(boot_id: u128) -> Self {
+		Self {
+			boot_id,
+			started: Instant::now(),
+		}
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&mut self) -> (Timestamp, Monotonic, u128) {
+		let micros = u64::try_from(self.started.elapsed().as_micros()).unwrap_or(u64::MAX);
+		// UNWRAP: at least one
+		let monotonic = Monotonic::new(micros.max(1)).unwrap();
+		(Timestamp::now(), monotonic, self.boot_id)
+	}
+}
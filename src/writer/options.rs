@@ -1,3 +1,7 @@
+use std::num::NonZeroU128;
+
+use crate::objects::DataCompression;
+
 /// Options used when creating new journal files.
 ///
 /// The machine ID, boot ID, and scope are required, the rest have defaults, which are like
@@ -44,6 +48,14 @@ pub struct CreateOptions {
 	/// Defaults to Zstd.
 	pub compression: Option<Compression>,
 
+	/// The minimum size of a Data payload, in bytes, for it to be compressed.
+	///
+	/// Smaller payloads are always stored uncompressed, and larger ones are only stored compressed
+	/// if that actually makes them smaller.
+	///
+	/// Defaults to 512.
+	pub compression_threshold: u64,
+
 	/// The capacity of the data hash table, in entries.
 	///
 	/// This should be scaled according to the desired maximum file size for the journal.
@@ -59,6 +71,50 @@ pub struct CreateOptions {
 	///
 	/// Defaults to 333.
 	pub field_hash_table_capacity: u64,
+
+	/// The oldest version of systemd which should be able to read the journal files.
+	///
+	/// This picks the size of the header, leaving out the fields which that version doesn't know
+	/// about, and disables the keyed hash (added in 246) and the compact format (added in 252)
+	/// when the version predates them. It doesn't change the compression, so pick one which the
+	/// version supports.
+	///
+	/// Defaults to None, for the latest format.
+	pub compat_level: Option<u32>,
+
+	/// Whether to refuse to write to a file created by another machine.
+	///
+	/// In systemd, only the machine whose ID is in the header of a journal file may write to it,
+	/// so this compares it to [`machine_id`](Self::machine_id). Disable it if your own journals
+	/// don't follow that convention.
+	///
+	/// Defaults to true.
+	pub check_machine_id: bool,
+
+	/// The file ID of new files.
+	///
+	/// This should normally be left random: it's what tells files apart, and it also keys the hash
+	/// tables of the file. Pinning it is for tests which compare written files byte for byte, e.g.
+	/// against golden files.
+	///
+	/// Defaults to None, for a random v4 UUID.
+	pub file_id: Option<u128>,
+
+	/// The sequence number domain of new files.
+	///
+	/// Like [`file_id`](Self::file_id), this should normally be left random, and pinning it is for
+	/// tests.
+	///
+	/// Defaults to None, for a random v4 UUID.
+	pub seqnum_id: Option<NonZeroU128>,
+
+	/// Seed the IDs of new files which aren't pinned, so they're the same from run to run.
+	///
+	/// The IDs are then still v4 UUIDs, but from a pseudo-random sequence rather than from the
+	/// system's randomness. This is only for tests, as IDs are then predictable.
+	///
+	/// Defaults to None, for random IDs.
+	pub id_seed: Option<u64>,
 }
 
 impl CreateOptions {
@@ -70,8 +126,14 @@ impl CreateOptions {
 			seal: false,
 			compact: true,
 			compression: Some(Compression::default()),
+			compression_threshold: 512,
 			data_hash_table_capacity: 2048,
 			field_hash_table_capacity: 333,
+			compat_level: None,
+			check_machine_id: true,
+			file_id: None,
+			seqnum_id: None,
+			id_seed: None,
 		}
 	}
 
@@ -90,6 +152,11 @@ impl CreateOptions {
 		self
 	}
 
+	pub fn with_compression_threshold(mut self, compression_threshold: u64) -> Self {
+		self.compression_threshold = compression_threshold;
+		self
+	}
+
 	pub fn with_data_hash_table_capacity(mut self, data_hash_table_capacity: u64) -> Self {
 		self.data_hash_table_capacity = data_hash_table_capacity;
 		self
@@ -99,6 +166,37 @@ impl CreateOptions {
 		self.field_hash_table_capacity = field_hash_table_capacity;
 		self
 	}
+
+	pub fn with_compat_level(mut self, systemd_version: u32) -> Self {
+		self.compat_level = Some(systemd_version);
+		self
+	}
+
+	pub fn with_check_machine_id(mut self, check_machine_id: bool) -> Self {
+		self.check_machine_id = check_machine_id;
+		self
+	}
+
+	pub fn with_file_id(mut self, file_id: u128) -> Self {
+		self.file_id = Some(file_id);
+		self
+	}
+
+	pub fn with_seqnum_id(mut self, seqnum_id: NonZeroU128) -> Self {
+		self.seqnum_id = Some(seqnum_id);
+		self
+	}
+
+	pub fn with_id_seed(mut self, seed: u64) -> Self {
+		self.id_seed = Some(seed);
+		self
+	}
+
+	/// Whether files are written for at least this version of systemd.
+	pub(crate) fn supports_version(&self, systemd_version: u32) -> bool {
+		self.compat_level
+			.is_none_or(|compat_level| compat_level >= systemd_version)
+	}
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -113,3 +211,34 @@ pub enum Compression {
 	#[default]
 	Zstd,
 }
+
+impl Compression {
+	/// The compression of Data objects written with this algorithm.
+	///
+	/// See [`DataCompression::incompatible_flag`] for the header flag that goes with it.
+	pub fn data_compression(&self) -> DataCompression {
+		match self {
+			Self::Xz => DataCompression::Xz,
+			Self::Lz4 => DataCompression::Lz4,
+			Self::Zstd => DataCompression::Zstd,
+		}
+	}
+}
+
+#[test]
+fn test_compression_flags() {
+	use crate::header::IncompatibleFlag;
+
+	for (compression, flag) in [
+		(Compression::Xz, IncompatibleFlag::CompressedXz),
+		(Compression::Lz4, IncompatibleFlag::CompressedLz4),
+		(Compression::Zstd, IncompatibleFlag::CompressedZstd),
+	] {
+		assert_eq!(
+			compression.data_compression().incompatible_flag(),
+			Some(flag),
+			"{compression:?}"
+		);
+	}
+	assert_eq!(DataCompression::None.incompatible_flag(), None);
+}